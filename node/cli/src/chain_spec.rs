@@ -386,6 +386,12 @@ pub fn testnet_genesis(
 		pallet_membership_Instance1: Some(Default::default()),
 		pallet_treasury: Some(Default::default()),
 		pallet_vesting: Some(Default::default()),
+		rclaim: Some(Default::default()),
+		rdexn_swap: Some(Default::default()),
+		rtoken_balances: Some(Default::default()),
+		rdexn_signatures: Some(Default::default()),
+		bridge_swap: Some(Default::default()),
+		bridge_common: Some(Default::default()),
 	}
 }
 
@@ -513,6 +519,12 @@ pub fn genesis(
 		pallet_vesting: Some(VestingConfig {
 			vesting: vesting,
 		}),
+		rclaim: Some(Default::default()),
+		rdexn_swap: Some(Default::default()),
+		rtoken_balances: Some(Default::default()),
+		rdexn_signatures: Some(Default::default()),
+		bridge_swap: Some(Default::default()),
+		bridge_common: Some(Default::default()),
 	}
 }
 
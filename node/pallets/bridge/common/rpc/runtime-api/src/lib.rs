@@ -0,0 +1,35 @@
+// Copyright 2019-2021 Stafi Protocol.
+// This file is part of Stafi.
+
+// Stafi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Stafi.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime API definition for the bridge common pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::prelude::*;
+use node_primitives::ChainId;
+pub use bridge_common::{DepositNonce, DepositRecord, ProposalInfo, ReserveStatus};
+
+sp_api::decl_runtime_apis! {
+	pub trait BridgeCommonApi<AccountId, BlockNumber> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+	{
+		/// Outbound deposit records of `dest_id` with nonce in [start, end]
+		fn deposit_records(dest_id: ChainId, start: DepositNonce, end: DepositNonce) -> Vec<(DepositNonce, DepositRecord<AccountId, BlockNumber>)>;
+
+		/// Proposals of `src_id` that are neither executed nor expired
+		fn open_proposals(src_id: ChainId) -> Vec<ProposalInfo<AccountId, BlockNumber>>;
+
+		/// Reserves of bridged resources against the balances of the bridge account
+		fn reserves() -> Vec<ReserveStatus>;
+	}
+}
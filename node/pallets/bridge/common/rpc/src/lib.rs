@@ -0,0 +1,124 @@
+// Copyright 2019-2021 Stafi Protocol.
+// This file is part of Stafi.
+
+// Stafi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Stafi.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC interface for the bridge common pallet.
+
+use std::sync::Arc;
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use node_primitives::ChainId;
+pub use bridge_common_rpc_runtime_api::{BridgeCommonApi as BridgeCommonRuntimeApi, DepositNonce, DepositRecord, ProposalInfo, ReserveStatus};
+
+#[rpc]
+pub trait BridgeCommonApi<BlockHash, AccountId, BlockNumber> {
+	/// Outbound deposit records of `dest_id` with nonce in [start, end]
+	#[rpc(name = "bridgeCommon_depositRecords")]
+	fn deposit_records(
+		&self,
+		dest_id: ChainId,
+		start: DepositNonce,
+		end: DepositNonce,
+		at: Option<BlockHash>,
+	) -> Result<Vec<(DepositNonce, DepositRecord<AccountId, BlockNumber>)>>;
+
+	/// Proposals of `src_id` that are neither executed nor expired
+	#[rpc(name = "bridgeCommon_openProposals")]
+	fn open_proposals(
+		&self,
+		src_id: ChainId,
+		at: Option<BlockHash>,
+	) -> Result<Vec<ProposalInfo<AccountId, BlockNumber>>>;
+
+	/// Reserves of bridged resources against the balances of the bridge account
+	#[rpc(name = "bridgeCommon_reserves")]
+	fn reserves(&self, at: Option<BlockHash>) -> Result<Vec<ReserveStatus>>;
+}
+
+/// A struct that implements the [`BridgeCommonApi`].
+pub struct BridgeCommon<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> BridgeCommon<C, B> {
+	/// Create new `BridgeCommon` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+/// Converts a runtime api error into an rpc error.
+pub(crate) fn runtime_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to query bridge common.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, Block, AccountId, BlockNumber> BridgeCommonApi<<Block as BlockT>::Hash, AccountId, BlockNumber>
+	for BridgeCommon<C, Block>
+where
+	Block: BlockT,
+	C: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: BridgeCommonRuntimeApi<Block, AccountId, BlockNumber>,
+	AccountId: Codec,
+	BlockNumber: Codec,
+{
+	fn deposit_records(
+		&self,
+		dest_id: ChainId,
+		start: DepositNonce,
+		end: DepositNonce,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(DepositNonce, DepositRecord<AccountId, BlockNumber>)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.deposit_records(&at, dest_id, start, end).map_err(runtime_error)
+	}
+
+	fn open_proposals(
+		&self,
+		src_id: ChainId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<ProposalInfo<AccountId, BlockNumber>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.open_proposals(&at, src_id).map_err(runtime_error)
+	}
+
+	fn reserves(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<ReserveStatus>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.reserves(&at).map_err(runtime_error)
+	}
+}
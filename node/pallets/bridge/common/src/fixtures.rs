@@ -0,0 +1,169 @@
+// Copyright 2019-2021 Stafi Protocol.
+// This file is part of Stafi.
+
+// Stafi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Stafi.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Ethereum deposit fixture: a London block header, the transactions and receipts trie proofs of its
+//! 4th transaction and the deposit parameters. The transaction is an EIP-1559 call of
+//! `deposit(uint8,bytes32,bytes)` on the bridge contract, its receipt holds the token transfer to the
+//! handler and the bridge `Deposit(uint8,bytes32,uint64)` log.
+//!
+//! The block is built offline and is not a recorded mainnet block: it follows the mainnet encoding
+//! (header fields, legacy and typed transactions and receipts, blooms, trie layout) but uses the mock
+//! resource id and chain identity.
+//!
+//! TODO: replace it with a recorded mainnet ChainBridge deposit, citing its block number and tx hash here.
+//! It could not be captured yet as it needs an archive node: take the header from `debug_getBlockRlp`,
+//! build the trie proofs of the deposit from `eth_getBlockByNumber` and `eth_getBlockReceipts`, then set
+//! `TX_INDEX`, `LOG_INDEX`, `DEPOSIT_NONCE`, `bridge_contract` and `recipient` from the deposit and point
+//! the resource id of the test at the mainnet one.
+
+/// chunks of hex, joined to keep the lines short
+fn join(chunks: &[&str]) -> Vec<u8> {
+    hex::decode(chunks.concat()).unwrap()
+}
+
+pub fn header() -> Vec<u8> {
+    join(&[
+        "f90200a088e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6a01dcc4de8dec75d7aab85b5",
+        "67b6ccd41ad312451b948a7413f0a142fd40d49347945a0b54d5dc17e0aadc383d2db43b0a0d3e029c4ca069e39af32b",
+        "d0cc2d5f8ad822a3afcd7fe8d7211e4ca7c42654cdbda7a9b74516a0f57305c44f60c871d091deb22eea4047f205bac3",
+        "c196a01834680291cfa6575fa081e1f263c3b1fecc3866c6899f272158793e6e1b44b14f831f9db4801a113f75b90100",
+        "000000000000000000000000010000000000000000000000000000000000000002000001000000000000000000000000",
+        "000010000000000000000000001400000014200000000000080000080000000000000000000400000000000000000000",
+        "000000000002000000000000022000000000400000000000000100100000000000000000000000000000008000000000",
+        "000000000100000000000000000000000000000000002100000000000000000000000000000000000000000000000000",
+        "000000020000000000000000000000000000000000000000000001000000400000000000000000000000000000000000",
+        "000040010000000000000000000000008083c5d4888401c9c3808302bb3884610bdaa680a00000000000000000000000",
+        "000000000000000000000000000000000000000000880000000000000000843b9aca00",
+    ])
+}
+
+pub fn block_hash() -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hex::decode("e03177dee593a7018e96e528323b72aa09e87a8dc808560f0ca2f4db6a48618f").unwrap());
+    hash
+}
+
+pub fn transactions_root() -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hex::decode("f57305c44f60c871d091deb22eea4047f205bac3c196a01834680291cfa6575f").unwrap());
+    hash
+}
+
+pub fn receipts_root() -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hex::decode("81e1f263c3b1fecc3866c6899f272158793e6e1b44b14f831f9db4801a113f75").unwrap());
+    hash
+}
+
+pub const TX_INDEX: u32 = 3;
+pub const LOG_INDEX: u32 = 1;
+pub const DEPOSIT_NONCE: u64 = 7;
+
+/// nodes of the transactions trie from the root down to the deposit transaction
+pub fn tx_nodes() -> Vec<Vec<u8>> {
+    vec![
+        join(&[
+            "f851a0576ac7cd7b1d08bf999c89732a54093bc136a19e53c5048e9e7f4a526338b27480808080808080a0018b21d115",
+            "102a4db81e640ec9d75b924cda21d63db522fb56cbae11baacf85f8080808080808080",
+        ]),
+        join(&[
+            "f89180a029b0fde3200f8ca6748bd3dc4f821a9dc7f37e6383f3ae189fb979249c8379eba021c80a68589896329998ec",
+            "241f5f708d608e7bfeef03757faa4160b58d2d0409a0ac758bf3656bfee9bf391dc82f5f2adc35abe0bf08aad19e807f",
+            "7f4bd3608e86a0c84a5ade9f1f6eb45e88c55132a6e8033e6d0560cb0b6dbf2e277eb811b14c2d808080808080808080",
+            "808080",
+        ]),
+        join(&[
+            "f9015920b9015502f90151010b8459682f008509502f900083030d4094c0b0a0e3f0e8a5e2e0e1f08a5c2e0a57d4b9f2",
+            "a180b8e405e2ca1700000000000000000000000000000000000000000000000000000000000000010000000000000000",
+            "000000000000000000000000000000000072656d61726b02000000000000000000000000000000000000000000000000",
+            "000000000000006000000000000000000000000000000000000000000000000000000000000000600000000000000000",
+            "000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000",
+            "0000000000000020d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dc001a00000000000",
+            "00000000000000000000000000000000000000000000000000111ca00000000000000000000000000000000000000000",
+            "00000000000000000000222d",
+        ]),
+    ]
+}
+
+/// nodes of the receipts trie from the root down to the deposit receipt
+pub fn receipt_nodes() -> Vec<Vec<u8>> {
+    vec![
+        join(&[
+            "f851a03f148a08d2e7604cca622c981ec40427632697edc693fce3bee19878d91edcd980808080808080a0e58215be84",
+            "8c1293dd381210359d84485553000a82b67410406d183b42adbbdd8080808080808080",
+        ]),
+        join(&[
+            "f89180a0c5e154e471c520cd32bd3c0418871dedb6f17c3df174e30c803313fff7079d16a07c090d611d8c0584d82def",
+            "a26f3117b412adee799bc734d08fc97bbfeb44dbd0a02c036c224b0e660dd2d4e7ce2a084b09b722120f5fd3d72673aa",
+            "0e99cf72013fa0b47a736cf779849a8814eade796a018ff2c62a7bb096f673c65c9563d3b76dcb808080808080808080",
+            "808080",
+        ]),
+        join(&[
+            "f9024e20b9024a02f902460183026930b901000000000000000000000000000100000000000000000000000000000000",
+            "000000020000010000000000000000000000000000100000000000000000000014000000142000000000000800000800",
+            "000000000000000004000000000000000000000000000000020000000000000220000000004000000000000001001000",
+            "000000000000000000000000000080000000000000000001000000000000000000000000000000000021000000000000",
+            "000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000010000",
+            "0040000000000000000000000000000000000000004001000000000000000000000000f9013bf89b94a0b86991c6218b",
+            "36c1d19d4a2e9eb0ce3606eb48f863a0ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+            "a00000000000000000000000005a0b54d5dc17e0aadc383d2db43b0a0d3e029c4ca00000000000000000000000002b6a",
+            "b4b880a45a07d83cf4d664df4ab85705bc07a00000000000000000000000000000000000000000000000000000000000",
+            "000020f89c94c0b0a0e3f0e8a5e2e0e1f08a5c2e0a57d4b9f2a1f884a0dbb69440df8433824a026ef190652f29929eb6",
+            "4b4d1d5d2a69be8afe3e6eaed8a00000000000000000000000000000000000000000000000000000000000000001a000",
+            "00000000000000000000000000000000000000000000000072656d61726b02a000000000000000000000000000000000",
+            "0000000000000000000000000000000780",
+        ]),
+    ]
+}
+
+/// the deposit transaction, EIP-1559 typed
+pub fn transaction() -> Vec<u8> {
+    join(&[
+        "02f90151010b8459682f008509502f900083030d4094c0b0a0e3f0e8a5e2e0e1f08a5c2e0a57d4b9f2a180b8e405e2ca",
+        "170000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000",
+        "0000000000000000000072656d61726b0200000000000000000000000000000000000000000000000000000000000000",
+        "600000000000000000000000000000000000000000000000000000000000000060000000000000000000000000000000",
+        "000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000",
+        "20d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dc001a0000000000000000000000000",
+        "000000000000000000000000000000000000111ca0000000000000000000000000000000000000000000000000000000",
+        "000000222d",
+    ])
+}
+
+/// receipt of the deposit transaction
+pub fn receipt() -> Vec<u8> {
+    join(&[
+        "02f902460183026930b90100000000000000000000000000010000000000000000000000000000000000000002000001",
+        "000000000000000000000000000010000000000000000000001400000014200000000000080000080000000000000000",
+        "000400000000000000000000000000000002000000000000022000000000400000000000000100100000000000000000",
+        "000000000000008000000000000000000100000000000000000000000000000000002100000000000000000000000000",
+        "000000000000000000000000000000020000000000000000000000000000000000000000000001000000400000000000",
+        "00000000000000000000000000004001000000000000000000000000f9013bf89b94a0b86991c6218b36c1d19d4a2e9e",
+        "b0ce3606eb48f863a0ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3efa0000000000000",
+        "0000000000005a0b54d5dc17e0aadc383d2db43b0a0d3e029c4ca00000000000000000000000002b6ab4b880a45a07d8",
+        "3cf4d664df4ab85705bc07a00000000000000000000000000000000000000000000000000000000000000020f89c94c0",
+        "b0a0e3f0e8a5e2e0e1f08a5c2e0a57d4b9f2a1f884a0dbb69440df8433824a026ef190652f29929eb64b4d1d5d2a69be",
+        "8afe3e6eaed8a00000000000000000000000000000000000000000000000000000000000000001a00000000000000000",
+        "000000000000000000000000000000000072656d61726b02a00000000000000000000000000000000000000000000000",
+        "00000000000000000780",
+    ])
+}
+
+pub fn bridge_contract() -> [u8; 20] {
+    let mut contract = [0u8; 20];
+    contract.copy_from_slice(&hex::decode("c0b0a0e3f0e8a5e2e0e1f08a5c2e0a57d4b9f2a1").unwrap());
+    contract
+}
+
+/// recipient of the deposit, 32 bytes so the deposited amount is 32
+pub fn recipient() -> Vec<u8> {
+    hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d").unwrap()
+}
@@ -17,24 +17,36 @@ use frame_support::{
     Parameter, decl_error, decl_event, decl_module, decl_storage,
     dispatch::DispatchResult, ensure,
     traits::{EnsureOrigin, Get},
-    weights::{GetDispatchInfo, Pays},
+    weights::{GetDispatchInfo, Pays, Weight},
+    storage::{IterableStorageDoubleMap, unhashed, migration::{StorageIterator, take_storage_value}},
 };
 
 use frame_system::{self as system, ensure_signed, ensure_root};
 use sp_core::U256;
 use sp_runtime::{
     RuntimeDebug, ModuleId, DispatchError,
-    traits::{AccountIdConversion, Dispatchable}
+    traits::{AccountIdConversion, Dispatchable, Zero}
 };
-use node_primitives::{ChainId, ETH_CHAIN_ID, BSC_CHAIN_ID, Balance, RSymbol, XSymbol};
+use node_primitives::{ChainId, ChainType, ETH_CHAIN_ID, BSC_CHAIN_ID, Balance, RSymbol, XSymbol};
 use bridge_relayers as brelayers;
+use general_signature::verify_chain_recipient;
+use rtoken_balances::traits::Currency as RCurrency;
+
+pub mod models;
+pub use models::*;
+pub mod proof;
 
 #[cfg(test)]
 mod mock;
 #[cfg(test)]
+mod fixtures;
+#[cfg(test)]
 mod tests;
 
-const DEFAULT_RELAYER_THRESHOLD: u32 = 1;
+/// max number of deposit records returned by one query
+pub const MAX_DEPOSIT_RECORDS_QUERY: DepositNonce = 1000;
+/// legacy votes indexed per block by the V3 migration
+const LEGACY_INDEX_BATCH: u32 = 100;
 const MODULE_ID: ModuleId = ModuleId(*b"cb/bridg");
 
 pub type DepositNonce = u64;
@@ -51,12 +63,14 @@ pub fn derive_resource_id(chain: ChainId, id: &[u8]) -> ResourceId {
     return r_id;
 }
 
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
 pub enum ProposalStatus {
     Active,
 	Passed,
     Expired,
     Executed,
+    Held,
 }
 
 #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
@@ -67,14 +81,14 @@ pub struct ProposalVotes<AccountId, BlockNumber> {
 }
 
 impl<A: PartialEq, B: PartialOrd + Default> ProposalVotes<A, B> {
-    /// derivate next status according to threshold and now
-    fn derivate(&mut self, threshold: u32, now: B) -> ProposalStatus {
+    /// derivate next status according to the votes of current relayers, threshold and now
+    fn derivate(&mut self, valid_votes: usize, threshold: u32, now: B) -> ProposalStatus {
         if self.is_completed() {
             self.status.clone()
         } else if self.expiry <= now {
             self.status = ProposalStatus::Expired;
             ProposalStatus::Expired
-        } else if self.voted.len() >= threshold as usize {
+        } else if valid_votes >= threshold as usize {
             self.status = ProposalStatus::Passed;
             ProposalStatus::Passed
         } else {
@@ -82,9 +96,11 @@ impl<A: PartialEq, B: PartialOrd + Default> ProposalVotes<A, B> {
         }
     }
 
-    /// Returns true if the proposal has been rejected or approved, otherwise false.
+    /// Returns true if the proposal has been rejected, approved or held, otherwise false.
     fn is_completed(&self) -> bool {
-        self.status == ProposalStatus::Executed || self.status == ProposalStatus::Expired
+        self.status == ProposalStatus::Executed ||
+            self.status == ProposalStatus::Expired ||
+            self.status == ProposalStatus::Held
     }
 
     /// Returns true if `who` has voted for or against the proposal
@@ -101,6 +117,45 @@ impl<A: PartialEq, B: PartialOrd + Default> ProposalVotes<A, B> {
     fn to_be_executed(&mut self) {
         self.status = ProposalStatus::Executed;
     }
+
+    /// Set status to Held
+    fn to_be_held(&mut self) {
+        self.status = ProposalStatus::Held;
+    }
+}
+
+/// Extracts the transfer carried by a proposal, so that inbound transfers can be rate limited.
+pub trait ProposalInspector<Proposal> {
+    /// resource and amount moved by the proposal, None if it is not a transfer
+    fn transfer_of(proposal: &Proposal) -> Option<(ResourceId, u128)>;
+
+    /// recipient of the transfer as encoded in the source chain deposit, None if it is not a transfer
+    fn recipient_of(_proposal: &Proposal) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+impl<Proposal> ProposalInspector<Proposal> for () {
+    fn transfer_of(_proposal: &Proposal) -> Option<(ResourceId, u128)> {
+        None
+    }
+}
+
+/// Storage version of the pallet
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Releases {
+    /// relayers declared in both bridge_common and bridge_relayers
+    V1_0_0,
+    /// relayers only declared per chain in bridge_relayers
+    V2_0_0,
+    /// existing votes indexed into the proposal expiry and pruning schedules, LEGACY_INDEX_BATCH per block
+    V3_0_0,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1_0_0
+    }
 }
 
 impl<AccountId, BlockNumber: Default> Default for ProposalVotes<AccountId, BlockNumber> {
@@ -124,20 +179,45 @@ pub trait Trait: system::Trait + brelayers::Trait {
     type ChainIdentity: Get<ChainId>;
 
     type ProposalLifetime: Get<Self::BlockNumber>;
+
+    /// Length of the period over which transfer limits are accounted
+    type LimitPeriod: Get<Self::BlockNumber>;
+
+    /// Blocks an inbound proposal over the limit is held until the admin sets another delay
+    type DefaultHoldDelay: Get<Self::BlockNumber>;
+
+    /// Extracts resource and amount from inbound proposals
+    type ProposalInspector: ProposalInspector<Self::Proposal>;
+
+    /// Number of latest deposit records kept per destination chain
+    type DepositRecordsRetained: Get<DepositNonce>;
+
+    /// Blocks the votes of a completed proposal are kept before being pruned
+    type CompletedProposalRetention: Get<Self::BlockNumber>;
+
+    /// Currency mechanism of rtoken, whose supply can be migrated to other chains
+    type RTokenCurrency: RCurrency<Self::AccountId>;
 }
 
 decl_event! {
     pub enum Event<T> where
-        AccountId = <T as system::Trait>::AccountId
+        AccountId = <T as system::Trait>::AccountId,
+        BlockNumber = <T as system::Trait>::BlockNumber
     {
         /// Chain now available for transfers (chain_id)
         ChainWhitelisted(ChainId),
         /// Chain now unavailable
         ChainRemoved(ChainId),
+        /// Chain type set, which decides the address format of recipients: chain_id, chain_type
+        ChainTypeSet(ChainId, ChainType),
         /// FunglibleTransfer is for relaying fungibles (AccountId, dest_id, nonce, resource_id, amount, recipient, metadata)
         FungibleTransfer(AccountId, ChainId, DepositNonce, ResourceId, U256, Vec<u8>),
         /// Set Chain fees
         ChainFeesSet(ChainId, Balance),
+        /// Set fee of a resource on a chain: chain_id, resource_id, fee
+        ResourceFeeSet(ChainId, ResourceId, ResourceFee),
+        /// Remove fee of a resource on a chain: chain_id, resource_id
+        ResourceFeeRemoved(ChainId, ResourceId),
         /// Vote submitted in favour of proposal
         VoteFor(ChainId, DepositNonce, AccountId),
         /// Vot submitted against proposal
@@ -150,6 +230,30 @@ decl_event! {
         ProposalExecuted(ChainId, DepositNonce),
         /// set migrate target
         SetMigrateTarget(RSymbol, ChainId),
+        /// rtoken burnt to be minted on the migrate target: symbol, dest_id, who, amount, supply migrated to dest_id
+        RTokenMigrated(RSymbol, ChainId, AccountId, u128, u128),
+        /// rtoken migrated back and minted locally: symbol, src_id, recipient, amount, supply still migrated to src_id
+        RTokenMigratedBack(RSymbol, ChainId, AccountId, u128, u128),
+        /// migration finalized, local minting disabled: symbol, target, local issuance, total migrated supply
+        MigrationFinalized(RSymbol, ChainId, u128, u128),
+        /// reserve of a resource set: resource_id, reserve
+        ResourceReserveSet(ResourceId, ResourceReserve),
+        /// transfer limit of a resource over all chains set: resource_id, limit
+        ResourceLimitSet(ResourceId, TransferLimit),
+        /// transfer limit of a resource on one chain set: chain_id, resource_id, limit
+        ChainResourceLimitSet(ChainId, ResourceId, TransferLimit),
+        /// delay of held proposals set
+        HoldDelaySet(BlockNumber),
+        /// Proposal exceeded the inbound limit and is held: src_id, nonce, release block
+        ProposalHeld(ChainId, DepositNonce, BlockNumber),
+        /// bridge contract whose receipts prove deposits of a source chain set, None disables proofs: chain_id, contract
+        ReceiptProofContractSet(ChainId, Option<[u8; 20]>),
+        /// header authority set
+        HeaderAuthoritySet(AccountId),
+        /// block header attested: chain_id, block hash
+        HeaderAttested(ChainId, [u8; 32]),
+        /// deposit of a proposal proven by its receipt: src_id, nonce
+        ProposalVerified(ChainId, DepositNonce),
     }
 }
 
@@ -183,12 +287,50 @@ decl_error! {
         ServicePaused,
         /// invalid chain fee
         InvalidChainFee,
+        /// percentage fees must be paid in the transferred token
+        InvalidResourceFee,
         /// invalid ethereum Address
         InvalidEthereumAddress,
+        /// recipient is not a valid address of the destination chain
+        InvalidRecipient,
+        /// chain type of the destination chain is not set
+        ChainTypeNotSet,
         /// invalid fee recipient account
         InvalidFeesRecipientAccount,
         /// rsymbol not mapped
         RsymbolNotMapped,
+        /// relayer threshold of the source chain is not set
+        RelayerThresholdNotSet,
+        /// transfer exceeds the limit of this period
+        TransferLimitExceeded,
+        /// proposal is not held
+        ProposalNotHeld,
+        /// hold delay of the proposal has not passed yet
+        HoldDelayNotPassed,
+        /// hold delay must not be zero
+        InvalidHoldDelay,
+        /// migrate target of the rsymbol is not set
+        MigrateTargetNotSet,
+        /// migration of the rsymbol has been finalized
+        MigrationAlreadyFinalized,
+        /// amount migrated back exceeds the supply migrated to the source chain
+        MigratedSupplyExceeded,
+        /// unlocking more than the reserve of the resource
+        InsufficientReserve,
+        /// proposals of the source chain need a receipt proof before they can be voted
+        ProposalNotVerified,
+        /// source chain does not take receipt proofs
+        ReceiptProofNotRequired,
+        /// caller is not the header authority
+        MustBeHeaderAuthority,
+        /// block header can't be decoded
+        InvalidHeader,
+        /// block header has not been attested
+        HeaderNotAttested,
+        /// receipt proof doesn't match the receipts root or holds no deposit log
+        InvalidReceiptProof,
+        /// deposit log doesn't match the proposal
+        DepositMismatch,
     }
 }
 
@@ -197,9 +339,21 @@ decl_storage! {
         /// All whitelisted chains and their respective transaction counts
         pub ChainNonces get(fn chains): map hasher(twox_64_concat) ChainId => Option<DepositNonce>;
 
+        /// chain type of destination chains, deciding the address format of recipients
+        pub ChainTypes get(fn chain_types): map hasher(twox_64_concat) ChainId => Option<ChainType>;
+
+        /// Latest outbound deposits: (dest_id, nonce) => deposit record
+        pub DepositRecords get(fn deposit_records):
+            double_map hasher(twox_64_concat) ChainId, hasher(twox_64_concat) DepositNonce
+            => Option<DepositRecord<T::AccountId, T::BlockNumber>>;
+
         /// fee to cover the commission happened on other chains such as ethereum
         pub ChainFees get(fn chain_fees): map hasher(twox_64_concat) ChainId => Option<Balance>;
 
+        /// fee of a resource on a chain, takes precedence over ChainFees
+        pub ResourceFees get(fn resource_fees):
+            double_map hasher(twox_64_concat) ChainId, hasher(blake2_128_concat) ResourceId => Option<ResourceFee>;
+
         /// Proxy accounts for setting chain fees
         ProxyAccounts get(fn proxy_accounts): map hasher(twox_64_concat) T::AccountId => Option<u8>;
 
@@ -209,14 +363,10 @@ decl_storage! {
         /// True if the bridge is paused.
         pub IsPaused get(fn is_paused): bool = false;
 
-        /// Number of votes required for a proposal to execute
-        RelayerThreshold get(fn relayer_threshold): u32 = DEFAULT_RELAYER_THRESHOLD;
-
-        /// Tracks current relayer set
-        pub Relayers get(fn relayers): map hasher(blake2_128_concat) T::AccountId => bool;
-
-        /// Number of relayers in set
-        pub RelayerCount get(fn relayer_count): u32;
+        /// Storage version of the pallet
+        StorageVersion get(fn storage_version) build(|_| Releases::V3_0_0): Releases;
+        /// raw key of the last vote indexed by the V3 migration, none once all are indexed
+        LegacyVotesCursor get(fn legacy_votes_cursor): Option<Vec<u8>>;
 
         /// All known proposals.
         /// The key is the hash of the call and the deposit ID, to ensure it's unique.
@@ -224,6 +374,35 @@ decl_storage! {
             double_map hasher(blake2_128_concat) ChainId, hasher(blake2_128_concat) (DepositNonce, T::Proposal)
             => Option<ProposalVotes<T::AccountId, T::BlockNumber>>;
 
+        /// Proposals that are neither executed nor expired, per source chain
+        pub ActiveProposals get(fn active_proposals):
+            double_map hasher(twox_64_concat) ChainId, hasher(blake2_128_concat) (DepositNonce, T::Proposal) => bool;
+
+        /// Proposals to be expired at a block
+        ExpiringProposals get(fn expiring_proposals):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<(ChainId, DepositNonce, T::Proposal)>;
+
+        /// Completed proposals whose votes are pruned at a block
+        PrunableProposals get(fn prunable_proposals):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<(ChainId, DepositNonce, T::Proposal)>;
+
+        /// Executed deposit nonces per source chain above ExecutedNonceFloor, kept after the votes are pruned so a nonce can't be replayed
+        pub ExecutedNonces get(fn executed_nonces):
+            double_map hasher(twox_64_concat) ChainId, hasher(twox_64_concat) DepositNonce => bool;
+        /// All deposit nonces of a source chain up to this one are executed and dropped from ExecutedNonces
+        pub ExecutedNonceFloor get(fn executed_nonce_floor): map hasher(twox_64_concat) ChainId => DepositNonce;
+
+        /// bridge contract of source chains whose proposals need a receipt proof
+        pub ReceiptProofContracts get(fn receipt_proof_contracts): map hasher(twox_64_concat) ChainId => Option<[u8; 20]>;
+        /// account attesting block headers of source chains
+        pub HeaderAuthority get(fn header_authority): Option<T::AccountId>;
+        /// trie roots of attested block headers: (chain_id, block hash) => transactions and receipts root
+        pub AttestedHeaders get(fn attested_headers):
+            double_map hasher(twox_64_concat) ChainId, hasher(identity) [u8; 32] => Option<AttestedHeader>;
+        /// proposals whose deposit has been proven by a receipt
+        pub VerifiedProposals get(fn verified_proposals):
+            double_map hasher(blake2_128_concat) ChainId, hasher(blake2_128_concat) (DepositNonce, T::Proposal) => bool;
+
         /// Utilized by the bridge software to map resource IDs to actual methods
         pub Resources get(fn resources): map hasher(blake2_128_concat) ResourceId => Option<Vec<u8>>;
 
@@ -239,6 +418,36 @@ decl_storage! {
 
         /// rsymbol => chainId
         pub MigrateTarget get(fn migrate_target): map hasher(blake2_128_concat) RSymbol => Option<ChainId>;
+        /// rtoken supply burnt here and minted on a chain: (rsymbol, chainId) => supply
+        pub MigratedSupply get(fn migrated_supply):
+            double_map hasher(blake2_128_concat) RSymbol, hasher(twox_64_concat) ChainId => u128;
+        /// rtoken supply migrated to all chains, local issuance plus this is the total supply
+        pub TotalMigrated get(fn total_migrated): map hasher(blake2_128_concat) RSymbol => u128;
+        /// rsymbol => target chain of a finalized migration
+        pub FinalizedMigrations get(fn finalized_migrations): map hasher(blake2_128_concat) RSymbol => Option<ChainId>;
+        /// locked and minted amounts of resources
+        pub ResourceReserves get(fn resource_reserves): map hasher(blake2_128_concat) ResourceId => ResourceReserve;
+
+        /// source chain of the proposal being executed
+        pub CurrentProposalSource get(fn current_proposal_source): Option<ChainId>;
+
+        /// transfer limit of a resource summed over all chains
+        pub ResourceLimits get(fn resource_limits): map hasher(blake2_128_concat) ResourceId => Option<TransferLimit>;
+        /// transfer limit of a resource on one chain
+        pub ChainResourceLimits get(fn chain_resource_limits):
+            double_map hasher(twox_64_concat) ChainId, hasher(blake2_128_concat) ResourceId => Option<TransferLimit>;
+        /// amount of a resource transferred in the current limit period over all chains
+        pub ResourceUsage get(fn resource_usage): map hasher(blake2_128_concat) ResourceId => TransferUsage<T::BlockNumber>;
+        /// amount of a resource transferred in the current limit period on one chain
+        pub ChainResourceUsage get(fn chain_resource_usage):
+            double_map hasher(twox_64_concat) ChainId, hasher(blake2_128_concat) ResourceId => TransferUsage<T::BlockNumber>;
+
+        /// blocks an inbound proposal over the limit is held before it can be executed
+        pub HoldDelay get(fn hold_delay): T::BlockNumber = T::DefaultHoldDelay::get();
+        /// held proposals and the block from which they can be executed
+        pub HeldProposals get(fn held_proposals):
+            double_map hasher(blake2_128_concat) ChainId, hasher(blake2_128_concat) (DepositNonce, T::Proposal)
+            => Option<T::BlockNumber>;
     }
 }
 
@@ -249,9 +458,33 @@ decl_module! {
         const ChainIdentity: ChainId = T::ChainIdentity::get();
         const BridgeAccountId: T::AccountId = MODULE_ID.into_account();
         const ProposalLifetime: T::BlockNumber = T::ProposalLifetime::get();
+        const LimitPeriod: T::BlockNumber = T::LimitPeriod::get();
+        const DefaultHoldDelay: T::BlockNumber = T::DefaultHoldDelay::get();
+        const DepositRecordsRetained: DepositNonce = T::DepositRecordsRetained::get();
+        const CompletedProposalRetention: T::BlockNumber = T::CompletedProposalRetention::get();
 
         fn deposit_event() = default;
 
+        fn on_initialize(now: T::BlockNumber) -> Weight {
+            Self::expire_proposals(now)
+                .saturating_add(Self::prune_proposals(now))
+                .saturating_add(Self::index_legacy_proposals(now))
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            let mut weight = 0;
+            if Self::storage_version() == Releases::V1_0_0 {
+                weight = weight.saturating_add(Self::remove_legacy_relayers());
+                StorageVersion::put(Releases::V2_0_0);
+            }
+            if Self::storage_version() == Releases::V2_0_0 {
+                <LegacyVotesCursor>::put(Self::votes_prefix());
+                StorageVersion::put(Releases::V3_0_0);
+                weight = weight.saturating_add(T::DbWeight::get().writes(2));
+            }
+            weight
+        }
+
         /// Stores a method name on chain under an associated resource ID.
         ///
         /// # <weight>
@@ -338,11 +571,144 @@ decl_module! {
             ensure!(brelayers::Module::<T>::is_relayer(src_id, &who), brelayers::Error::<T>::MustBeRelayer);
             ensure!(Self::chain_whitelisted(src_id), Error::<T>::ChainNotWhitelisted);
             ensure!(Self::resources(resource_id).is_some(), Error::<T>::ResourceDoesNotExist);
+            ensure!(
+                Self::receipt_proof_contracts(src_id).is_none() || Self::verified_proposals(src_id, (nonce, call.clone())),
+                Error::<T>::ProposalNotVerified
+            );
+
+            Self::commit_vote(who, nonce, src_id, call.clone())?;
+            Self::try_resolve_proposal(nonce, src_id, call)
+        }
+
+        /// Proves the deposit of a proposal by its transaction receipt and commits a vote in favour of it.
+        ///
+        /// Required for the first vote on proposals from source chains with a receipt proof contract.
+        ///
+        /// # <weight>
+        /// - weight of proposed call, regardless of whether execution is performed
+        /// - O(proof nodes) hashing
+        /// # </weight>
+        #[weight = (call.get_dispatch_info().weight + 500_000_000, call.get_dispatch_info().class, Pays::Yes)]
+        pub fn acknowledge_proposal_with_proof(origin, nonce: DepositNonce, src_id: ChainId, resource_id: ResourceId, call: Box<T::Proposal>, proof: ReceiptProof) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(brelayers::Module::<T>::is_relayer(src_id, &who), brelayers::Error::<T>::MustBeRelayer);
+            ensure!(Self::chain_whitelisted(src_id), Error::<T>::ChainNotWhitelisted);
+            ensure!(Self::resources(resource_id).is_some(), Error::<T>::ResourceDoesNotExist);
+
+            if !Self::verified_proposals(src_id, (nonce, call.clone())) {
+                Self::verify_deposit(nonce, src_id, resource_id, &call, &proof)?;
+                <VerifiedProposals<T>>::insert(src_id, (nonce, call.clone()), true);
+                Self::deposit_event(RawEvent::ProposalVerified(src_id, nonce));
+            }
 
             Self::commit_vote(who, nonce, src_id, call.clone())?;
             Self::try_resolve_proposal(nonce, src_id, call)
         }
 
+        /// Executes a held proposal once its hold delay has passed, callable by relayers of `src_id`.
+        ///
+        /// # <weight>
+        /// - weight of proposed call
+        /// # </weight>
+        #[weight = (call.get_dispatch_info().weight + 195_000_000, call.get_dispatch_info().class, Pays::Yes)]
+        pub fn execute_held_proposal(origin, nonce: DepositNonce, src_id: ChainId, call: Box<T::Proposal>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(brelayers::Module::<T>::is_relayer(src_id, &who), brelayers::Error::<T>::MustBeRelayer);
+            let release = <HeldProposals<T>>::get(src_id, (nonce, call.clone())).ok_or(Error::<T>::ProposalNotHeld)?;
+            ensure!(system::Module::<T>::block_number() >= release, Error::<T>::HoldDelayNotPassed);
+
+            Self::release_held_proposal(nonce, src_id, call)
+        }
+
+        /// Executes a held proposal before its hold delay has passed.
+        ///
+        /// # <weight>
+        /// - weight of proposed call
+        /// # </weight>
+        #[weight = (call.get_dispatch_info().weight + 195_000_000, call.get_dispatch_info().class, Pays::Yes)]
+        pub fn approve_held_proposal(origin, nonce: DepositNonce, src_id: ChainId, call: Box<T::Proposal>) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            ensure!(<HeldProposals<T>>::contains_key(src_id, (nonce, call.clone())), Error::<T>::ProposalNotHeld);
+
+            Self::release_held_proposal(nonce, src_id, call)
+        }
+
+        /// Cancels a held proposal.
+        ///
+        /// # <weight>
+        /// - O(1) lookup and removal
+        /// # </weight>
+        #[weight = 195_000_000]
+        pub fn reject_held_proposal(origin, nonce: DepositNonce, src_id: ChainId, call: Box<T::Proposal>) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            ensure!(<HeldProposals<T>>::contains_key(src_id, (nonce, call.clone())), Error::<T>::ProposalNotHeld);
+            let mut votes = <Votes<T>>::get(src_id, (nonce, call.clone())).ok_or(Error::<T>::ProposalDoesNotExist)?;
+
+            votes.status = ProposalStatus::Expired;
+            <Votes<T>>::insert(src_id, (nonce, call.clone()), votes);
+            <HeldProposals<T>>::remove(src_id, (nonce, call.clone()));
+            Self::complete_proposal(src_id, nonce, call);
+
+            Self::deposit_event(RawEvent::ProposalCancelled(src_id, nonce));
+            Ok(())
+        }
+
+        /// Set transfer limit of a resource summed over all chains.
+        ///
+        /// # <weight>
+        /// - O(1) insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn set_resource_limit(origin, resource_id: ResourceId, limit: TransferLimit) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+
+            if limit.is_unlimited() {
+                <ResourceLimits>::remove(&resource_id);
+            } else {
+                <ResourceLimits>::insert(&resource_id, &limit);
+            }
+
+            Self::deposit_event(RawEvent::ResourceLimitSet(resource_id, limit));
+            Ok(())
+        }
+
+        /// Set transfer limit of a resource on one chain.
+        ///
+        /// # <weight>
+        /// - O(1) insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn set_chain_resource_limit(origin, chain_id: ChainId, resource_id: ResourceId, limit: TransferLimit) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            ensure!(Self::chain_whitelisted(chain_id), Error::<T>::InvalidChainId);
+
+            if limit.is_unlimited() {
+                <ChainResourceLimits>::remove(chain_id, &resource_id);
+            } else {
+                <ChainResourceLimits>::insert(chain_id, &resource_id, &limit);
+            }
+
+            Self::deposit_event(RawEvent::ChainResourceLimitSet(chain_id, resource_id, limit));
+            Ok(())
+        }
+
+        /// Set blocks an inbound proposal over the limit is held.
+        ///
+        /// # <weight>
+        /// - O(1) insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn set_hold_delay(origin, delay: T::BlockNumber) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            ensure!(!delay.is_zero(), Error::<T>::InvalidHoldDelay);
+
+            <HoldDelay<T>>::put(delay);
+
+            Self::deposit_event(RawEvent::HoldDelaySet(delay));
+            Ok(())
+        }
+
         /// Enables a chain ID as a source or destination for a bridge transfer.
         ///
         /// # <weight>
@@ -364,11 +730,94 @@ decl_module! {
             Self::ensure_admin(origin)?;
             ensure!(Self::chain_whitelisted(id), Error::<T>::ChainNotWhitelisted);
             <ChainNonces>::remove(id);
+            <ChainTypes>::remove(id);
 
             Self::deposit_event(RawEvent::ChainRemoved(id));
             Ok(())
         }
 
+        /// Set the chain type of a destination chain.
+        ///
+        /// # <weight>
+        /// - O(1) lookup and insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn set_chain_type(origin, id: ChainId, chain_type: ChainType) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            ensure!(Self::chain_whitelisted(id), Error::<T>::ChainNotWhitelisted);
+
+            <ChainTypes>::insert(id, chain_type);
+
+            Self::deposit_event(RawEvent::ChainTypeSet(id, chain_type));
+            Ok(())
+        }
+
+        /// Set the bridge contract of a source chain, requiring receipt proofs for its proposals. None disables proofs.
+        ///
+        /// # <weight>
+        /// - O(1) insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn set_receipt_proof_contract(origin, id: ChainId, contract: Option<[u8; 20]>) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            ensure!(Self::chain_whitelisted(id), Error::<T>::ChainNotWhitelisted);
+
+            match contract {
+                Some(c) => <ReceiptProofContracts>::insert(id, c),
+                None => <ReceiptProofContracts>::remove(id),
+            }
+
+            Self::deposit_event(RawEvent::ReceiptProofContractSet(id, contract));
+            Ok(())
+        }
+
+        /// Set the account attesting block headers.
+        ///
+        /// # <weight>
+        /// - O(1) insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn set_header_authority(origin, who: T::AccountId) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+
+            <HeaderAuthority<T>>::put(&who);
+
+            Self::deposit_event(RawEvent::HeaderAuthoritySet(who));
+            Ok(())
+        }
+
+        /// Attests an RLP encoded block header of a source chain, so transactions and receipts of the block can be proven.
+        ///
+        /// # <weight>
+        /// - O(header) hashing and decoding
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn attest_header(origin, id: ChainId, header: Vec<u8>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::header_authority() == Some(who), Error::<T>::MustBeHeaderAuthority);
+            ensure!(Self::receipt_proof_contracts(id).is_some(), Error::<T>::ReceiptProofNotRequired);
+
+            let (hash, transactions_root, receipts_root) = proof::decode_header(&header).ok_or(Error::<T>::InvalidHeader)?;
+            <AttestedHeaders>::insert(id, hash, AttestedHeader { transactions_root, receipts_root });
+
+            Self::deposit_event(RawEvent::HeaderAttested(id, hash));
+            Ok(())
+        }
+
+        /// Removes an attested block header.
+        ///
+        /// # <weight>
+        /// - O(1) removal
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn remove_attested_header(origin, id: ChainId, hash: [u8; 32]) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::header_authority() == Some(who), Error::<T>::MustBeHeaderAuthority);
+
+            <AttestedHeaders>::remove(id, hash);
+            Ok(())
+        }
+
         /// Set proxy accounts.
         ///
         /// # <weight>
@@ -413,6 +862,42 @@ decl_module! {
             Ok(())
         }
 
+        /// Set fee of a resource on a chain.
+        ///
+        /// # <weight>
+        /// - O(1) lookup and insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn set_resource_fee(origin, id: ChainId, resource_id: ResourceId, fee: ResourceFee) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Self::chain_whitelisted(id), Error::<T>::InvalidChainId);
+            ensure!(<ProxyAccounts<T>>::contains_key(&who), Error::<T>::InvalidProxyAccount);
+            ensure!(fee.is_valid(), Error::<T>::InvalidResourceFee);
+
+            <ResourceFees>::insert(id, &resource_id, &fee);
+
+            Self::deposit_event(RawEvent::ResourceFeeSet(id, resource_id, fee));
+            Ok(())
+        }
+
+        /// Remove fee of a resource on a chain, falling back to the chain fees.
+        ///
+        /// # <weight>
+        /// - O(1) lookup and removal
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn remove_resource_fee(origin, id: ChainId, resource_id: ResourceId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(<ProxyAccounts<T>>::contains_key(&who), Error::<T>::InvalidProxyAccount);
+
+            <ResourceFees>::remove(id, &resource_id);
+
+            Self::deposit_event(RawEvent::ResourceFeeRemoved(id, resource_id));
+            Ok(())
+        }
+
         /// Set fees recipient account.
         ///
         /// # <weight>
@@ -450,6 +935,7 @@ decl_module! {
         pub fn set_migrate_target(origin, symbol: RSymbol, id: ChainId) -> DispatchResult {
             Self::ensure_admin(origin)?;
             ensure!(Self::chain_whitelisted(id), Error::<T>::InvalidChainId);
+            ensure!(Self::finalized_migrations(symbol).is_none(), Error::<T>::MigrationAlreadyFinalized);
 
             <MigrateTarget>::insert(symbol, id);
 
@@ -457,24 +943,84 @@ decl_module! {
             Ok(())
         }
 
+        /// Set the reserve of a resource, to account for amounts bridged before reserves were tracked.
+        ///
+        /// # <weight>
+        /// - O(1) insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn set_resource_reserve(origin, resource_id: ResourceId, reserve: ResourceReserve) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+
+            <ResourceReserves>::insert(&resource_id, &reserve);
+
+            Self::deposit_event(RawEvent::ResourceReserveSet(resource_id, reserve));
+            Ok(())
+        }
+
+        /// Finalize the migration of an rtoken to its migrate target, disabling local minting for good.
+        ///
+        /// Local holders can still migrate, but nothing can be migrated back.
+        ///
+        /// # <weight>
+        /// - O(1) lookup and insert
+        /// # </weight>
+        #[weight = 100_000_000]
+        pub fn finalize_migration(origin, symbol: RSymbol) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            let target = Self::migrate_target(symbol).ok_or(Error::<T>::MigrateTargetNotSet)?;
+            ensure!(Self::finalized_migrations(symbol).is_none(), Error::<T>::MigrationAlreadyFinalized);
+
+            <FinalizedMigrations>::insert(symbol, target);
+            T::RTokenCurrency::disable_mint(symbol);
+
+            let local_issuance = T::RTokenCurrency::total_issuance(symbol);
+            Self::deposit_event(RawEvent::MigrationFinalized(symbol, target, local_issuance, Self::total_migrated(symbol)));
+            Ok(())
+        }
+
     }
 }
 
 impl<T: Trait> Module<T> {
-    pub fn swapable(recipient: &Vec<u8>, dest_id: ChainId) -> Result<(Balance, T::AccountId, T::AccountId), DispatchError> {
+    /// Checks an outbound transfer of `amount` of `resource_id` and returns (fee, fees recipient, bridge account)
+    pub fn swapable(recipient: &Vec<u8>, dest_id: ChainId, resource_id: ResourceId, amount: Balance) -> Result<(TransferFee, T::AccountId, T::AccountId), DispatchError> {
         ensure!(!Self::check_is_paused(), Error::<T>::ServicePaused);
         ensure!(Self::chain_whitelisted(dest_id), Error::<T>::InvalidChainId);
 
-        let fee = Self::get_chain_fees(dest_id).ok_or(Error::<T>::InvalidChainFee)?;
+        let fee = Self::get_transfer_fee(dest_id, resource_id, amount).ok_or(Error::<T>::InvalidChainFee)?;
         let receiver = Self::get_fees_recipient_account().ok_or(Error::<T>::InvalidFeesRecipientAccount)?;
 
-        if dest_id == ETH_CHAIN_ID || dest_id == BSC_CHAIN_ID {
-            Self::check_eth_recipient(recipient)?;
-        }
+        Self::check_recipient(recipient, dest_id)?;
+        Self::ensure_within_limits(dest_id, resource_id, amount, TransferDirection::Outbound)?;
 
         Ok((fee, receiver, Self::account_id()))
     }
 
+    /// Checks the recipient against the address format of the destination chain,
+    /// chains without a known chain type are rejected
+    pub fn check_recipient(recipient: &Vec<u8>, dest_id: ChainId) -> DispatchResult {
+        match Self::dest_chain_type(dest_id) {
+            Some(ChainType::Ethereum) => Self::check_eth_recipient(recipient),
+            Some(chain_type) => {
+                ensure!(verify_chain_recipient(chain_type, recipient), Error::<T>::InvalidRecipient);
+                Ok(())
+            },
+            None => Err(Error::<T>::ChainTypeNotSet.into()),
+        }
+    }
+
+    /// Chain type of a destination chain, ETH and BSC are EVM chains unless set otherwise
+    pub fn dest_chain_type(dest_id: ChainId) -> Option<ChainType> {
+        Self::chain_types(dest_id).or_else(|| {
+            if dest_id == ETH_CHAIN_ID || dest_id == BSC_CHAIN_ID {
+                Some(ChainType::Ethereum)
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn check_eth_recipient(recipient: &Vec<u8>) -> DispatchResult {
         ensure!(recipient.len() == 20, Error::<T>::InvalidEthereumAddress);
 
@@ -504,6 +1050,14 @@ impl<T: Trait> Module<T> {
         return Self::chain_fees(id);
     }
 
+    /// Get fee of transferring `amount` of a resource, the resource fee takes precedence over chain fees
+    pub fn get_transfer_fee(id: ChainId, resource_id: ResourceId, amount: Balance) -> Option<TransferFee> {
+        match Self::resource_fees(id, &resource_id) {
+            Some(fee) => Some(fee.fee_of(amount)),
+            None => Self::get_chain_fees(id).map(TransferFee::Native),
+        }
+    }
+
     /// Provides an AccountId for the fees.
     pub fn get_fees_recipient_account() -> Option<T::AccountId> {
         return Self::fees_recipient_account();
@@ -547,7 +1101,18 @@ impl<T: Trait> Module<T> {
             Self::chain_whitelisted(dest_id),
             Error::<T>::ChainNotWhitelisted
         );
+        let value = Self::amount_to_balance(amount);
+        Self::ensure_within_limits(dest_id, resource_id, value, TransferDirection::Outbound)?;
+        Self::consume_limits(dest_id, resource_id, value, TransferDirection::Outbound);
+
         let nonce = Self::bump_nonce(dest_id);
+        Self::record_deposit(dest_id, nonce, DepositRecord {
+            resource_id,
+            amount,
+            recipient: to.clone(),
+            source: source.clone(),
+            block: system::Module::<T>::block_number(),
+        });
         Self::deposit_event(RawEvent::FungibleTransfer(
             source,
             dest_id,
@@ -559,14 +1124,52 @@ impl<T: Trait> Module<T> {
         Ok(())
     }
 
+    /// Removes the global relayer set, relayers are only read from the per chain sets of bridge_relayers.
+    ///
+    /// The legacy set never took part in voting, so it is dropped rather than merged into the per chain sets.
+    fn remove_legacy_relayers() -> Weight {
+        let module: &[u8] = b"BridgeCommon";
+        let removed = StorageIterator::<bool>::new(module, b"Relayers").drain().count() as Weight;
+        take_storage_value::<u32>(module, b"RelayerCount", &[]);
+        take_storage_value::<u32>(module, b"RelayerThreshold", &[]);
+
+        T::DbWeight::get().reads_writes(removed + 3, removed + 3)
+    }
+
+    /// Stores a deposit record and prunes the one falling out of the retention window
+    fn record_deposit(dest_id: ChainId, nonce: DepositNonce, record: DepositRecord<T::AccountId, T::BlockNumber>) {
+        <DepositRecords<T>>::insert(dest_id, nonce, record);
+        let retained = T::DepositRecordsRetained::get();
+        if nonce > retained {
+            <DepositRecords<T>>::remove(dest_id, nonce - retained);
+        }
+    }
+
+    /// Deposit records of `dest_id` with nonce in [start, end], at most MAX_DEPOSIT_RECORDS_QUERY of them
+    pub fn deposit_records_in_range(dest_id: ChainId, start: DepositNonce, end: DepositNonce) -> Vec<(DepositNonce, DepositRecord<T::AccountId, T::BlockNumber>)> {
+        let end = end.min(start.saturating_add(MAX_DEPOSIT_RECORDS_QUERY - 1));
+        (start..=end)
+            .filter_map(|nonce| Self::deposit_records(dest_id, nonce).map(|record| (nonce, record)))
+            .collect()
+    }
+
     /// Commits a vote for a proposal. If the proposal doesn't exist it will be created.
     fn commit_vote(who: T::AccountId, nonce: DepositNonce, src_id: ChainId, prop: Box<T::Proposal>) -> DispatchResult {
         let now = system::Module::<T>::block_number();
-        let mut votes = <Votes<T>>::get(src_id, (nonce, prop.clone())).unwrap_or_else(|| {
-            let mut v = ProposalVotes::default();
-            v.expiry = now + T::ProposalLifetime::get();
-            v
-        });
+        ensure!(!Self::is_executed(src_id, nonce), Error::<T>::ProposalAlreadyCompleted);
+        let threshold = brelayers::Module::<T>::relayer_threshold(src_id);
+        ensure!(threshold > 0, Error::<T>::RelayerThresholdNotSet);
+
+        let mut votes = match <Votes<T>>::get(src_id, (nonce, prop.clone())) {
+            Some(votes) => votes,
+            None => {
+                let mut v = ProposalVotes::default();
+                v.expiry = now + T::ProposalLifetime::get();
+                <ActiveProposals<T>>::insert(src_id, (nonce, prop.clone()), true);
+                <ExpiringProposals<T>>::mutate(v.expiry, |proposals| proposals.push((src_id, nonce, (*prop).clone())));
+                v
+            },
+        };
 
         // Ensure the proposal isn't complete and relayer hasn't already voted
         ensure!(!votes.is_completed(), Error::<T>::ProposalAlreadyCompleted);
@@ -574,11 +1177,14 @@ impl<T: Trait> Module<T> {
         if votes.is_expired(now) {
             votes.status = ProposalStatus::Expired;
             <Votes<T>>::insert(src_id, (nonce, prop.clone()), votes.clone());
+            Self::complete_proposal(src_id, nonce, prop.clone());
         }
         ensure!(!votes.is_expired(now), Error::<T>::ProposalExpired);
 
         votes.voted.push(who.clone());
-        votes.derivate(brelayers::RelayerThreshold::get(src_id), now);
+        // votes of accounts removed from the relayer set no longer count
+        let valid_votes = votes.voted.iter().filter(|v| brelayers::Module::<T>::is_relayer(src_id, v)).count();
+        votes.derivate(valid_votes, threshold, now);
         <Votes<T>>::insert(src_id, (nonce, prop.clone()), votes);
 
         Self::deposit_event(RawEvent::VoteFor(src_id, nonce, who.clone()));
@@ -594,13 +1200,22 @@ impl<T: Trait> Module<T> {
         match votes.status {
             ProposalStatus::Passed => {
                 Self::deposit_event(RawEvent::ProposalPassed(src_id, nonce));
-                let call = prop.clone();
-                call.dispatch(system::RawOrigin::Signed(Self::account_id()).into())
-                    .map(|_| ())
-                    .map_err(|e| e.error)?;
-                votes.to_be_executed();
-                <Votes<T>>::insert(src_id, (nonce, prop), votes.clone());
-                Self::deposit_event(RawEvent::ProposalExecuted(src_id, nonce));
+                let transfer = T::ProposalInspector::transfer_of(&prop);
+                if let Some((resource_id, amount)) = transfer {
+                    if Self::ensure_within_limits(src_id, resource_id, amount, TransferDirection::Inbound).is_err() {
+                        let release = system::Module::<T>::block_number() + Self::hold_delay();
+                        votes.to_be_held();
+                        <Votes<T>>::insert(src_id, (nonce, prop.clone()), votes);
+                        <HeldProposals<T>>::insert(src_id, (nonce, prop), release);
+                        Self::deposit_event(RawEvent::ProposalHeld(src_id, nonce, release));
+                        return Ok(());
+                    }
+                }
+                Self::execute_proposal(nonce, src_id, prop, votes)?;
+                // only a dispatched proposal counts toward the limits
+                if let Some((resource_id, amount)) = transfer {
+                    Self::consume_limits(src_id, resource_id, amount, TransferDirection::Inbound);
+                }
                 Ok(())
             },
             ProposalStatus::Expired => {
@@ -610,6 +1225,336 @@ impl<T: Trait> Module<T> {
             _ => Ok(()),
         }
     }
+
+    /// Dispatches a proposal with the bridge origin and marks it executed.
+    fn execute_proposal(nonce: DepositNonce, src_id: ChainId, prop: Box<T::Proposal>, mut votes: ProposalVotes<T::AccountId, T::BlockNumber>) -> DispatchResult {
+        ensure!(!Self::is_executed(src_id, nonce), Error::<T>::ProposalAlreadyCompleted);
+        let call = prop.clone();
+        <CurrentProposalSource>::put(src_id);
+        let result = call.dispatch(system::RawOrigin::Signed(Self::account_id()).into());
+        <CurrentProposalSource>::kill();
+        result.map(|_| ()).map_err(|e| e.error)?;
+        votes.to_be_executed();
+        <Votes<T>>::insert(src_id, (nonce, prop.clone()), votes);
+        <ExecutedNonces>::insert(src_id, nonce, true);
+        Self::complete_proposal(src_id, nonce, prop);
+        Self::deposit_event(RawEvent::ProposalExecuted(src_id, nonce));
+        Ok(())
+    }
+
+    /// Drops a completed proposal from the active index and schedules its votes for pruning
+    fn complete_proposal(src_id: ChainId, nonce: DepositNonce, prop: Box<T::Proposal>) {
+        <ActiveProposals<T>>::remove(src_id, (nonce, prop.clone()));
+        let prune_at = system::Module::<T>::block_number() + T::CompletedProposalRetention::get();
+        <PrunableProposals<T>>::mutate(prune_at, |proposals| proposals.push((src_id, nonce, *prop)));
+    }
+
+    /// Expires the proposals reaching their expiry at `now`
+    fn expire_proposals(now: T::BlockNumber) -> Weight {
+        let expiring = <ExpiringProposals<T>>::take(now);
+        let count = expiring.len() as Weight;
+        for (src_id, nonce, prop) in expiring {
+            if let Some(mut votes) = <Votes<T>>::get(src_id, (nonce, prop.clone())) {
+                if votes.status == ProposalStatus::Active || votes.status == ProposalStatus::Passed {
+                    votes.status = ProposalStatus::Expired;
+                    <Votes<T>>::insert(src_id, (nonce, prop.clone()), votes);
+                    Self::complete_proposal(src_id, nonce, Box::new(prop));
+                    Self::deposit_event(RawEvent::ProposalCancelled(src_id, nonce));
+                }
+            }
+        }
+
+        T::DbWeight::get().reads_writes(1 + 2 * count, 1 + 3 * count)
+    }
+
+    /// Removes the votes of proposals completed CompletedProposalRetention blocks ago
+    fn prune_proposals(now: T::BlockNumber) -> Weight {
+        let prunable = <PrunableProposals<T>>::take(now);
+        let count = prunable.len() as Weight;
+        let mut floors = 0;
+        let mut chains: Vec<ChainId> = vec![];
+        for (src_id, nonce, prop) in prunable {
+            <VerifiedProposals<T>>::remove(src_id, (nonce, prop.clone()));
+            <Votes<T>>::remove(src_id, (nonce, prop));
+            if !chains.contains(&src_id) {
+                chains.push(src_id);
+            }
+        }
+        for src_id in chains {
+            floors += 1 + Self::raise_executed_floor(src_id);
+        }
+
+        T::DbWeight::get().reads_writes(1 + floors, 1 + 2 * count + floors)
+    }
+
+    /// Whether the deposit `nonce` of `src_id` has been executed
+    pub fn is_executed(src_id: ChainId, nonce: DepositNonce) -> bool {
+        nonce <= Self::executed_nonce_floor(src_id) || Self::executed_nonces(src_id, nonce)
+    }
+
+    /// Moves the contiguous run of executed nonces above the floor of `src_id` into the floor, returns the number moved
+    fn raise_executed_floor(src_id: ChainId) -> Weight {
+        let mut floor = Self::executed_nonce_floor(src_id);
+        let mut raised = 0;
+        while <ExecutedNonces>::take(src_id, floor + 1) {
+            floor += 1;
+            raised += 1;
+        }
+        if raised > 0 {
+            <ExecutedNonceFloor>::insert(src_id, floor);
+        }
+        raised
+    }
+
+    /// Indexes up to LEGACY_INDEX_BATCH votes stored before proposals were scheduled, resuming after the cursor
+    fn index_legacy_proposals(now: T::BlockNumber) -> Weight {
+        let mut key = match Self::legacy_votes_cursor() {
+            Some(cursor) => cursor,
+            None => return T::DbWeight::get().reads(1),
+        };
+        let prefix = Self::votes_prefix();
+        let mut count: u32 = 0;
+        while count < LEGACY_INDEX_BATCH {
+            key = match sp_io::storage::next_key(&key).filter(|next| next.starts_with(&prefix)) {
+                Some(next) => next,
+                None => {
+                    <LegacyVotesCursor>::kill();
+                    return T::DbWeight::get().reads_writes(2 * count as Weight + 2, 2 * count as Weight + 1);
+                },
+            };
+            count += 1;
+            let votes = unhashed::get::<ProposalVotes<T::AccountId, T::BlockNumber>>(&key);
+            if let (Some((src_id, nonce, prop)), Some(votes)) = (Self::decode_votes_key(&key[prefix.len()..]), votes) {
+                Self::index_legacy_proposal(now, src_id, nonce, prop, votes);
+            }
+        }
+        <LegacyVotesCursor>::put(key);
+
+        T::DbWeight::get().reads_writes(2 * count as Weight + 1, 2 * count as Weight + 1)
+    }
+
+    /// Indexes one vote: open ones become active and expiring, completed ones are scheduled for pruning.
+    /// Votes made since the upgrade are indexed already, indexing them again changes nothing.
+    fn index_legacy_proposal(
+        now: T::BlockNumber,
+        src_id: ChainId,
+        nonce: DepositNonce,
+        prop: T::Proposal,
+        mut votes: ProposalVotes<T::AccountId, T::BlockNumber>,
+    ) {
+        match votes.status.clone() {
+            ProposalStatus::Active | ProposalStatus::Passed if !votes.is_expired(now) => {
+                if !<ActiveProposals<T>>::contains_key(src_id, (nonce, prop.clone())) {
+                    <ActiveProposals<T>>::insert(src_id, (nonce, prop.clone()), true);
+                    <ExpiringProposals<T>>::mutate(votes.expiry, |proposals| proposals.push((src_id, nonce, prop)));
+                }
+                return;
+            },
+            ProposalStatus::Held => {
+                <ActiveProposals<T>>::insert(src_id, (nonce, prop), true);
+                return;
+            },
+            ProposalStatus::Active | ProposalStatus::Passed => {
+                votes.status = ProposalStatus::Expired;
+                <Votes<T>>::insert(src_id, (nonce, prop.clone()), votes);
+            },
+            ProposalStatus::Executed => if !Self::is_executed(src_id, nonce) {
+                <ExecutedNonces>::insert(src_id, nonce, true);
+            },
+            ProposalStatus::Expired => (),
+        }
+        let prune_at = now + T::CompletedProposalRetention::get();
+        <PrunableProposals<T>>::mutate(prune_at, |proposals| proposals.push((src_id, nonce, prop)));
+    }
+
+    /// Raw key prefix of Votes
+    fn votes_prefix() -> Vec<u8> {
+        [sp_io::hashing::twox_128(b"BridgeCommon"), sp_io::hashing::twox_128(b"Votes")].concat()
+    }
+
+    /// Decodes the source chain, nonce and proposal from a raw Votes key without its prefix
+    fn decode_votes_key(key: &[u8]) -> Option<(ChainId, DepositNonce, T::Proposal)> {
+        let mut input = key.get(16..)?;
+        let src_id = ChainId::decode(&mut input).ok()?;
+        let mut input = input.get(16..)?;
+        let (nonce, prop) = <(DepositNonce, T::Proposal)>::decode(&mut input).ok()?;
+        Some((src_id, nonce, prop))
+    }
+
+    /// Checks that the receipt and transaction prove a deposit on the bridge contract of `src_id` matching the proposal.
+    ///
+    /// The receipt proves the deposit succeeded with this nonce, the transaction carries its amount and recipient.
+    fn verify_deposit(nonce: DepositNonce, src_id: ChainId, resource_id: ResourceId, prop: &T::Proposal, receipt_proof: &ReceiptProof) -> DispatchResult {
+        let contract = Self::receipt_proof_contracts(src_id).ok_or(Error::<T>::ReceiptProofNotRequired)?;
+        let header = Self::attested_headers(src_id, receipt_proof.block_hash).ok_or(Error::<T>::HeaderNotAttested)?;
+
+        let key = proof::encode_index(receipt_proof.tx_index);
+        let receipt = proof::verify_trie_proof(header.receipts_root, &key, &receipt_proof.receipt_nodes)
+            .ok_or(Error::<T>::InvalidReceiptProof)?;
+        let transaction = proof::verify_trie_proof(header.transactions_root, &key, &receipt_proof.tx_nodes)
+            .ok_or(Error::<T>::InvalidReceiptProof)?;
+        let deposit = proof::decode_deposit_log(&receipt, receipt_proof.log_index).ok_or(Error::<T>::InvalidReceiptProof)?;
+        let call = proof::decode_deposit_transaction(&transaction).ok_or(Error::<T>::InvalidReceiptProof)?;
+
+        let (prop_resource, amount) = T::ProposalInspector::transfer_of(prop).ok_or(Error::<T>::DepositMismatch)?;
+        let recipient = T::ProposalInspector::recipient_of(prop).ok_or(Error::<T>::DepositMismatch)?;
+        ensure!(
+            deposit.contract == contract &&
+                call.contract == contract &&
+                deposit.dest_id == U256::from(T::ChainIdentity::get()) &&
+                call.dest_id == deposit.dest_id &&
+                deposit.nonce == U256::from(nonce) &&
+                deposit.resource_id == resource_id &&
+                call.resource_id == resource_id &&
+                prop_resource == resource_id &&
+                call.amount == U256::from(amount) &&
+                call.recipient == recipient,
+            Error::<T>::DepositMismatch
+        );
+
+        Ok(())
+    }
+
+    /// Open proposals of `src_id` with their votes, for relayers to find stuck proposals
+    pub fn open_proposals(src_id: ChainId) -> Vec<ProposalInfo<T::AccountId, T::BlockNumber>> {
+        let threshold = brelayers::Module::<T>::relayer_threshold(src_id);
+        <ActiveProposals<T>>::iter_prefix(src_id)
+            .filter_map(|((nonce, prop), _)| {
+                Self::votes(src_id, (nonce, prop.clone())).map(|votes| ProposalInfo {
+                    nonce,
+                    call: prop.encode(),
+                    votes: votes.voted.iter().filter(|v| brelayers::Module::<T>::is_relayer(src_id, v)).count() as u32,
+                    threshold,
+                    voted: votes.voted,
+                    status: votes.status,
+                    expiry: votes.expiry,
+                })
+            })
+            .collect()
+    }
+
+    /// Executes a held proposal, bypassing the inbound limit but counting its amount in the current period.
+    fn release_held_proposal(nonce: DepositNonce, src_id: ChainId, prop: Box<T::Proposal>) -> DispatchResult {
+        let votes = <Votes<T>>::get(src_id, (nonce, prop.clone())).ok_or(Error::<T>::ProposalDoesNotExist)?;
+        ensure!(votes.status == ProposalStatus::Held, Error::<T>::ProposalNotHeld);
+
+        Self::execute_proposal(nonce, src_id, prop.clone(), votes)?;
+        if let Some((resource_id, amount)) = T::ProposalInspector::transfer_of(&prop) {
+            Self::consume_limits(src_id, resource_id, amount, TransferDirection::Inbound);
+        }
+        <HeldProposals<T>>::remove(src_id, (nonce, prop));
+        Ok(())
+    }
+
+    /// Records `amount` of a resource locked in the bridge account by an outbound transfer
+    pub fn lock_reserve(resource_id: ResourceId, amount: u128) {
+        <ResourceReserves>::mutate(&resource_id, |r| r.locked = r.locked.saturating_add(amount));
+    }
+
+    /// Releases `amount` of a resource for an inbound transfer, failing if more than the locked amount
+    pub fn release_reserve(resource_id: ResourceId, amount: u128) -> DispatchResult {
+        <ResourceReserves>::try_mutate(&resource_id, |r| -> DispatchResult {
+            r.locked = r.locked.checked_sub(amount).ok_or(Error::<T>::InsufficientReserve)?;
+            Ok(())
+        })
+    }
+
+    /// Records `amount` of a resource minted by an inbound transfer
+    pub fn note_minted(resource_id: ResourceId, amount: u128) {
+        <ResourceReserves>::mutate(&resource_id, |r| r.minted = r.minted.saturating_add(amount));
+    }
+
+    /// Records `amount` of a resource burnt by an outbound transfer.
+    /// Saturates as the supply minted before reserves were tracked is unknown.
+    pub fn note_burnt(resource_id: ResourceId, amount: u128) {
+        <ResourceReserves>::mutate(&resource_id, |r| r.minted = r.minted.saturating_sub(amount));
+    }
+
+    /// Total supply of an rtoken, its local issuance plus the supply migrated to other chains.
+    ///
+    /// Migrations only burn and mint through `migrate_out` and `migrate_back`, which move the same
+    /// amount between the local issuance and the migrated supply, so they leave it unchanged.
+    pub fn rtoken_total_supply(symbol: RSymbol) -> u128 {
+        T::RTokenCurrency::total_issuance(symbol).saturating_add(Self::total_migrated(symbol))
+    }
+
+    /// Part of an inbound transfer of `amount` rtoken from `src_id` minted back against the supply migrated there,
+    /// the rest was locked in the bridge account before the migration and is released from the reserve
+    pub fn migrated_part(symbol: RSymbol, src_id: ChainId, amount: u128) -> u128 {
+        amount.min(Self::migrated_supply(symbol, src_id))
+    }
+
+    /// Burns `amount` of rtoken of `who` to be minted on `dest_id`
+    pub fn migrate_out(who: &T::AccountId, symbol: RSymbol, dest_id: ChainId, amount: u128) -> DispatchResult {
+        let supply = Self::migrated_supply(symbol, dest_id).checked_add(amount).ok_or(Error::<T>::MigratedSupplyExceeded)?;
+        let total = Self::total_migrated(symbol).checked_add(amount).ok_or(Error::<T>::MigratedSupplyExceeded)?;
+
+        T::RTokenCurrency::burn(who, symbol, amount)?;
+        <MigratedSupply>::insert(symbol, dest_id, supply);
+        <TotalMigrated>::insert(symbol, total);
+
+        Self::deposit_event(RawEvent::RTokenMigrated(symbol, dest_id, who.clone(), amount, supply));
+        Ok(())
+    }
+
+    /// Mints `amount` of rtoken migrated back from `src_id` to `recipient`
+    pub fn migrate_back(recipient: &T::AccountId, symbol: RSymbol, src_id: ChainId, amount: u128) -> DispatchResult {
+        ensure!(Self::finalized_migrations(symbol).is_none(), Error::<T>::MigrationAlreadyFinalized);
+        let supply = Self::migrated_supply(symbol, src_id).checked_sub(amount).ok_or(Error::<T>::MigratedSupplyExceeded)?;
+
+        T::RTokenCurrency::mint(recipient, symbol, amount)?;
+        <MigratedSupply>::insert(symbol, src_id, supply);
+        <TotalMigrated>::mutate(symbol, |total| *total = total.saturating_sub(amount));
+
+        Self::deposit_event(RawEvent::RTokenMigratedBack(symbol, src_id, recipient.clone(), amount, supply));
+        Ok(())
+    }
+
+    /// Index of the current limit period
+    fn current_period() -> T::BlockNumber {
+        let period = T::LimitPeriod::get();
+        if period.is_zero() {
+            return Zero::zero();
+        }
+        system::Module::<T>::block_number() / period
+    }
+
+    /// Converts a bridge amount into a balance, saturating at u128::MAX
+    pub fn amount_to_balance(amount: U256) -> Balance {
+        if amount > U256::from(u128::max_value()) {
+            u128::max_value()
+        } else {
+            amount.low_u128()
+        }
+    }
+
+    /// Checks that moving `amount` of `resource_id` from/to `chain_id` fits in the limits of the current period
+    pub fn ensure_within_limits(chain_id: ChainId, resource_id: ResourceId, amount: Balance, direction: TransferDirection) -> DispatchResult {
+        let period = Self::current_period();
+        let fits = |limit: Option<TransferLimit>, used: u128| {
+            limit.and_then(|l| l.cap(direction)).map_or(true, |cap| used.saturating_add(amount) <= cap)
+        };
+
+        let chain_used = Self::chain_resource_usage(chain_id, &resource_id).in_period(period).used(direction);
+        ensure!(fits(Self::chain_resource_limits(chain_id, &resource_id), chain_used), Error::<T>::TransferLimitExceeded);
+        let resource_used = Self::resource_usage(&resource_id).in_period(period).used(direction);
+        ensure!(fits(Self::resource_limits(&resource_id), resource_used), Error::<T>::TransferLimitExceeded);
+
+        Ok(())
+    }
+
+    /// Records `amount` of `resource_id` against the limits of the current period
+    fn consume_limits(chain_id: ChainId, resource_id: ResourceId, amount: Balance, direction: TransferDirection) {
+        let period = Self::current_period();
+        <ChainResourceUsage<T>>::mutate(chain_id, &resource_id, |usage| {
+            *usage = usage.clone().in_period(period);
+            usage.add(direction, amount);
+        });
+        <ResourceUsage<T>>::mutate(&resource_id, |usage| {
+            *usage = usage.clone().in_period(period);
+            usage.add(direction, amount);
+        });
+    }
 }
 
 /// Simple ensure origin for the bridge account
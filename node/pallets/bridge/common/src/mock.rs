@@ -15,7 +15,7 @@ use sp_core::H256;
 use frame_support::{assert_ok, impl_outer_origin, impl_outer_dispatch, parameter_types, weights::Weight, traits::{Get}};
 use frame_system::{EnsureRoot};
 use node_primitives::{ChainId, BlockNumber};
-use crate::{Module, Trait, ResourceId};
+use crate::{Module, Trait, ResourceId, ProposalInspector, derive_resource_id};
 
 pub(crate) type Balance = u128;
 
@@ -27,6 +27,7 @@ impl_outer_dispatch! {
 	pub enum Call for Test where origin: Origin {
 		frame_system::System,
 		bridge_relayers::BridgeRelayers,
+		rtoken_balances::RBalances,
 		self::BridgeCommon,
 	}
 }
@@ -76,6 +77,10 @@ impl bridge_relayers::Trait for Test {
 	type Event = ();
 }
 
+impl rtoken_balances::Trait for Test {
+	type Event = ();
+}
+
 thread_local! {
 	static EXISTENTIAL_DEPOSIT: RefCell<Balance> = RefCell::new(0);
 }
@@ -100,6 +105,34 @@ impl pallet_balances::Trait for Test {
 parameter_types! {
 	pub const ChainIdentity: ChainId = 1;
 	pub const ProposalLifetime: BlockNumber = 50;
+	pub const LimitPeriod: BlockNumber = 100;
+	pub const DefaultHoldDelay: BlockNumber = 5;
+	pub const DepositRecordsRetained: u64 = 3;
+	pub const CompletedProposalRetention: BlockNumber = 10;
+}
+
+/// Treats a remark as an inbound transfer of `remark_resource_id()` to the remark itself, its length being the amount.
+/// Setting heap pages is a transfer of that many that always fails, the bridge origin not being root.
+pub struct RemarkInspector;
+impl ProposalInspector<Call> for RemarkInspector {
+	fn transfer_of(proposal: &Call) -> Option<(ResourceId, u128)> {
+		match proposal {
+			Call::System(frame_system::Call::remark(r)) => Some((remark_resource_id(), r.len() as u128)),
+			Call::System(frame_system::Call::set_heap_pages(pages)) => Some((remark_resource_id(), *pages as u128)),
+			_ => None,
+		}
+	}
+
+	fn recipient_of(proposal: &Call) -> Option<Vec<u8>> {
+		match proposal {
+			Call::System(frame_system::Call::remark(r)) => Some(r.clone()),
+			_ => None,
+		}
+	}
+}
+
+pub fn remark_resource_id() -> ResourceId {
+	derive_resource_id(2, b"remark")
 }
 
 impl Trait for Test {
@@ -108,11 +141,18 @@ impl Trait for Test {
 	type ChainIdentity = ChainIdentity;
 	type Proposal = Call;
 	type ProposalLifetime = ProposalLifetime;
+	type LimitPeriod = LimitPeriod;
+	type DefaultHoldDelay = DefaultHoldDelay;
+	type DepositRecordsRetained = DepositRecordsRetained;
+	type CompletedProposalRetention = CompletedProposalRetention;
+	type ProposalInspector = RemarkInspector;
+	type RTokenCurrency = RBalances;
 }
 
 
 pub type System = frame_system::Module<Test>;
 pub type BridgeRelayers = bridge_relayers::Module<Test>;
+pub type RBalances = rtoken_balances::Module<Test>;
 pub type BridgeCommon = Module<Test>;
 
 // Relayers
@@ -0,0 +1,220 @@
+use codec::{Decode, Encode};
+use sp_core::U256;
+use sp_runtime::{Perbill, RuntimeDebug};
+use sp_std::prelude::*;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+use crate::{DepositNonce, ProposalStatus, ResourceId};
+
+/// Direction of a bridge transfer, seen from this chain
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub enum TransferDirection {
+    /// transfer out of this chain
+    Outbound,
+    /// transfer into this chain
+    Inbound,
+}
+
+/// Caps on the amount that can be moved within one limit period, `None` means unlimited
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
+pub struct TransferLimit {
+    /// cap of outbound transfers
+    pub outbound: Option<u128>,
+    /// cap of inbound transfers
+    pub inbound: Option<u128>,
+}
+
+impl TransferLimit {
+    /// cap of the given direction
+    pub fn cap(&self, direction: TransferDirection) -> Option<u128> {
+        match direction {
+            TransferDirection::Outbound => self.outbound,
+            TransferDirection::Inbound => self.inbound,
+        }
+    }
+
+    /// Returns true if neither direction is capped
+    pub fn is_unlimited(&self) -> bool {
+        self.outbound.is_none() && self.inbound.is_none()
+    }
+}
+
+/// Amount already moved within a limit period
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
+pub struct TransferUsage<BlockNumber> {
+    /// index of the limit period
+    pub period: BlockNumber,
+    /// amount transferred out
+    pub outbound: u128,
+    /// amount transferred in
+    pub inbound: u128,
+}
+
+impl<B: PartialEq> TransferUsage<B> {
+    /// usage in `period`, reset if the recorded period has rolled over
+    pub fn in_period(self, period: B) -> Self {
+        if self.period == period {
+            self
+        } else {
+            Self { period, outbound: 0, inbound: 0 }
+        }
+    }
+
+    /// amount used in the given direction
+    pub fn used(&self, direction: TransferDirection) -> u128 {
+        match direction {
+            TransferDirection::Outbound => self.outbound,
+            TransferDirection::Inbound => self.inbound,
+        }
+    }
+
+    /// record `amount` in the given direction
+    pub fn add(&mut self, direction: TransferDirection, amount: u128) {
+        match direction {
+            TransferDirection::Outbound => self.outbound = self.outbound.saturating_add(amount),
+            TransferDirection::Inbound => self.inbound = self.inbound.saturating_add(amount),
+        }
+    }
+}
+
+/// How the fee of a resource is calculated
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub enum FeeRate {
+    /// flat amount per transfer
+    Flat(u128),
+    /// share of the transferred amount
+    Percent(Perbill),
+}
+
+/// Fee of transferring a resource to a chain
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ResourceFee {
+    /// fee rate
+    pub rate: FeeRate,
+    /// charge the fee in the transferred token instead of FIS
+    pub in_token: bool,
+}
+
+impl ResourceFee {
+    /// Percentage fees are taken from the transferred amount, so they must be paid in the token
+    pub fn is_valid(&self) -> bool {
+        match self.rate {
+            FeeRate::Flat(_) => true,
+            FeeRate::Percent(_) => self.in_token,
+        }
+    }
+
+    /// fee of transferring `amount`
+    pub fn fee_of(&self, amount: u128) -> TransferFee {
+        let fee = match self.rate {
+            FeeRate::Flat(fee) => fee,
+            FeeRate::Percent(rate) => rate * amount,
+        };
+        if self.in_token {
+            TransferFee::Token(fee)
+        } else {
+            TransferFee::Native(fee)
+        }
+    }
+}
+
+/// Fee charged for one outbound transfer
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub enum TransferFee {
+    /// paid in FIS
+    Native(u128),
+    /// paid in the transferred token
+    Token(u128),
+}
+
+impl TransferFee {
+    /// fee amount regardless of the asset it is paid in
+    pub fn amount(&self) -> u128 {
+        match self {
+            TransferFee::Native(fee) | TransferFee::Token(fee) => *fee,
+        }
+    }
+}
+
+/// Outbound deposit, kept so relayers can resync without replaying blocks
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct DepositRecord<AccountId, BlockNumber> {
+    /// resource id
+    pub resource_id: ResourceId,
+    /// amount
+    pub amount: U256,
+    /// recipient on the destination chain
+    pub recipient: Vec<u8>,
+    /// depositor
+    pub source: AccountId,
+    /// block the deposit was made in
+    pub block: BlockNumber,
+}
+
+/// Open proposal as seen by relayers
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ProposalInfo<AccountId, BlockNumber> {
+    /// deposit nonce on the source chain
+    pub nonce: DepositNonce,
+    /// SCALE encoded proposal call
+    pub call: Vec<u8>,
+    /// votes of current relayers
+    pub votes: u32,
+    /// votes required
+    pub threshold: u32,
+    /// accounts that have voted
+    pub voted: Vec<AccountId>,
+    /// status
+    pub status: ProposalStatus,
+    /// expiry block
+    pub expiry: BlockNumber,
+}
+
+/// Proof that a deposit happened on an Ethereum source chain
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ReceiptProof {
+    /// hash of the block including the deposit transaction, attested by the header authority
+    pub block_hash: [u8; 32],
+    /// index of the deposit transaction in the block
+    pub tx_index: u32,
+    /// index of the deposit log in the receipt
+    pub log_index: u32,
+    /// RLP encoded nodes of the transactions trie from the root down to the deposit transaction
+    pub tx_nodes: Vec<Vec<u8>>,
+    /// RLP encoded nodes of the receipts trie from the root down to the receipt
+    pub receipt_nodes: Vec<Vec<u8>>,
+}
+
+/// Roots of an attested block header of a source chain
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
+pub struct AttestedHeader {
+    /// root of the transactions trie
+    pub transactions_root: [u8; 32],
+    /// root of the receipts trie
+    pub receipts_root: [u8; 32],
+}
+
+/// Bridged amounts of a resource on this chain
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
+pub struct ResourceReserve {
+    /// locked in the bridge account by outbound transfers, the most inbound transfers can unlock
+    pub locked: u128,
+    /// minted by inbound transfers and not yet burnt by outbound ones
+    pub minted: u128,
+}
+
+/// Reserve of a resource against what the bridge account actually holds
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ReserveStatus {
+    /// resource id
+    pub resource_id: ResourceId,
+    /// bridged amounts
+    pub reserve: ResourceReserve,
+    /// balance of the bridge account in the resource, solvent if not below the locked amount
+    pub held: u128,
+}
@@ -0,0 +1,360 @@
+// Copyright 2019-2021 Stafi Protocol.
+// This file is part of Stafi.
+
+// Stafi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Stafi.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verification of Ethereum deposits against an attested block header.
+//!
+//! Only the pieces needed by the bridge are implemented: RLP decoding, Merkle-Patricia
+//! proofs of the transactions and receipts tries, decoding of the bridge `Deposit` log
+//! and of the `deposit` call made to the bridge contract.
+//!
+//! The `Deposit` event of the ChainBridge contract only carries the destination chain,
+//! resource id and nonce, all indexed. Amount and recipient are in the deposit data
+//! passed to `deposit(uint8,bytes32,bytes)`, so they are taken from the transaction at
+//! the same index as the receipt. Deposits made through another contract can't be
+//! decoded this way and have to be proposed by votes only.
+
+use sp_std::prelude::*;
+use sp_core::U256;
+use sp_io::hashing::keccak_256;
+
+use crate::ResourceId;
+
+/// keccak256("Deposit(uint8,bytes32,uint64)"), the event emitted by the bridge contract as
+/// `Deposit(uint8 indexed destinationChainID, bytes32 indexed resourceID, uint64 indexed depositNonce)`
+pub const DEPOSIT_EVENT_SIGNATURE: [u8; 32] = [
+    0xdb, 0xb6, 0x94, 0x40, 0xdf, 0x84, 0x33, 0x82, 0x4a, 0x02, 0x6e, 0xf1, 0x90, 0x65, 0x2f, 0x29,
+    0x92, 0x9e, 0xb6, 0x4b, 0x4d, 0x1d, 0x5d, 0x2a, 0x69, 0xbe, 0x8a, 0xfe, 0x3e, 0x6e, 0xae, 0xd8,
+];
+
+/// first 4 bytes of keccak256("deposit(uint8,bytes32,bytes)"), the bridge contract method making deposits
+pub const DEPOSIT_SELECTOR: [u8; 4] = [0x05, 0xe2, 0xca, 0x17];
+
+/// index of transactionsRoot in a block header
+const TRANSACTIONS_ROOT_INDEX: usize = 4;
+/// index of receiptsRoot in a block header
+const RECEIPTS_ROOT_INDEX: usize = 5;
+
+/// One decoded RLP item
+#[derive(Clone, Copy)]
+pub struct RlpItem<'a> {
+    /// true if the item is a list
+    pub is_list: bool,
+    /// content of the item without its prefix
+    pub payload: &'a [u8],
+    /// the whole encoded item
+    pub raw: &'a [u8],
+}
+
+impl<'a> RlpItem<'a> {
+    /// Decodes the first item of `data`, returning it and the remaining bytes
+    pub fn decode(data: &'a [u8]) -> Option<(Self, &'a [u8])> {
+        let prefix = *data.first()?;
+        let (is_list, offset, len) = match prefix {
+            0x00..=0x7f => (false, 0, 1),
+            0x80..=0xb7 => (false, 1, (prefix - 0x80) as usize),
+            0xb8..=0xbf => {
+                let len_of_len = (prefix - 0xb7) as usize;
+                (false, 1 + len_of_len, read_len(data.get(1..1 + len_of_len)?)?)
+            },
+            0xc0..=0xf7 => (true, 1, (prefix - 0xc0) as usize),
+            0xf8..=0xff => {
+                let len_of_len = (prefix - 0xf7) as usize;
+                (true, 1 + len_of_len, read_len(data.get(1..1 + len_of_len)?)?)
+            },
+        };
+        let end = offset.checked_add(len)?;
+        let item = RlpItem {
+            is_list,
+            payload: data.get(offset..end)?,
+            raw: data.get(..end)?,
+        };
+        Some((item, &data[end..]))
+    }
+
+    /// Decodes `data` which must hold exactly one list, returning its items
+    pub fn decode_list(data: &'a [u8]) -> Option<Vec<Self>> {
+        let (item, rest) = Self::decode(data)?;
+        if !rest.is_empty() {
+            return None;
+        }
+        item.items()
+    }
+
+    /// Items of a list
+    pub fn items(&self) -> Option<Vec<Self>> {
+        if !self.is_list {
+            return None;
+        }
+        let mut items = Vec::new();
+        let mut rest = self.payload;
+        while !rest.is_empty() {
+            let (item, remaining) = Self::decode(rest)?;
+            items.push(item);
+            rest = remaining;
+        }
+        Some(items)
+    }
+
+    /// Payload of a string item
+    pub fn bytes(&self) -> Option<&'a [u8]> {
+        if self.is_list {
+            None
+        } else {
+            Some(self.payload)
+        }
+    }
+}
+
+fn read_len(bytes: &[u8]) -> Option<usize> {
+    if bytes.is_empty() || bytes.len() > 4 || bytes[0] == 0 {
+        return None;
+    }
+    Some(bytes.iter().fold(0usize, |len, b| (len << 8) | *b as usize))
+}
+
+/// RLP encoding of an unsigned integer, used as the key of the transactions and receipts tries
+pub fn encode_index(index: u32) -> Vec<u8> {
+    let bytes = index.to_be_bytes();
+    let trimmed: Vec<u8> = bytes.iter().skip_while(|b| **b == 0).cloned().collect();
+    match trimmed.len() {
+        0 => vec![0x80],
+        1 if trimmed[0] < 0x80 => trimmed,
+        len => {
+            let mut encoded = vec![0x80 + len as u8];
+            encoded.extend(trimmed);
+            encoded
+        },
+    }
+}
+
+/// Returns (block hash, transactions root, receipts root) of an RLP encoded block header
+pub fn decode_header(header: &[u8]) -> Option<([u8; 32], [u8; 32], [u8; 32])> {
+    let items = RlpItem::decode_list(header)?;
+    let transactions_root = to_hash(items.get(TRANSACTIONS_ROOT_INDEX)?.bytes()?)?;
+    let receipts_root = to_hash(items.get(RECEIPTS_ROOT_INDEX)?.bytes()?)?;
+    Some((keccak_256(header), transactions_root, receipts_root))
+}
+
+fn to_hash(bytes: &[u8]) -> Option<[u8; 32]> {
+    if bytes.len() != 32 {
+        return None;
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(bytes);
+    Some(hash)
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| vec![b >> 4, b & 0x0f]).collect()
+}
+
+/// Reference to a child node, either by hash or embedded when its encoding is shorter than 32 bytes
+enum NodeRef<'a> {
+    Hash([u8; 32]),
+    Inline(&'a [u8]),
+}
+
+impl<'a> NodeRef<'a> {
+    fn from_item(item: &RlpItem<'a>) -> Option<Self> {
+        if item.is_list {
+            Some(NodeRef::Inline(item.raw))
+        } else {
+            to_hash(item.payload).map(NodeRef::Hash)
+        }
+    }
+}
+
+/// Verifies a Merkle-Patricia proof of `key` against `root`, returning the proven value
+pub fn verify_trie_proof(root: [u8; 32], key: &[u8], proof: &[Vec<u8>]) -> Option<Vec<u8>> {
+    let key = to_nibbles(key);
+    let mut key: &[u8] = &key;
+    let mut nodes = proof.iter();
+    let mut next = NodeRef::Hash(root);
+
+    loop {
+        let node: &[u8] = match next {
+            NodeRef::Hash(hash) => {
+                let node = nodes.next()?;
+                if keccak_256(node) != hash {
+                    return None;
+                }
+                node
+            },
+            NodeRef::Inline(node) => node,
+        };
+        let items = RlpItem::decode_list(node)?;
+        match items.len() {
+            // branch node
+            17 => {
+                if key.is_empty() {
+                    let value = items[16].bytes()?;
+                    return if value.is_empty() { None } else { Some(value.to_vec()) };
+                }
+                let child = &items[key[0] as usize];
+                if !child.is_list && child.payload.is_empty() {
+                    return None;
+                }
+                next = NodeRef::from_item(child)?;
+                key = &key[1..];
+            },
+            // extension or leaf node, the path is hex-prefix encoded
+            2 => {
+                let path = to_nibbles(items[0].bytes()?);
+                let flag = *path.first()?;
+                let path = if flag & 1 == 1 { &path[1..] } else { path.get(2..)? };
+                if !key.starts_with(path) {
+                    return None;
+                }
+                key = &key[path.len()..];
+                if flag & 2 == 2 {
+                    return if key.is_empty() { Some(items[1].bytes()?.to_vec()) } else { None };
+                }
+                next = NodeRef::from_item(&items[1])?;
+            },
+            _ => return None,
+        }
+    }
+}
+
+/// Deposit made on the bridge contract, as decoded from its log
+#[derive(PartialEq, Eq, Clone, sp_runtime::RuntimeDebug)]
+pub struct DepositLog {
+    /// contract that emitted the log
+    pub contract: [u8; 20],
+    /// destination chain
+    pub dest_id: U256,
+    /// resource id
+    pub resource_id: ResourceId,
+    /// deposit nonce
+    pub nonce: U256,
+}
+
+/// Decodes the `log_index`th log of a successful receipt as a bridge deposit.
+///
+/// Typed (EIP-2718) receipts are prefixed by their transaction type.
+pub fn decode_deposit_log(receipt: &[u8], log_index: u32) -> Option<DepositLog> {
+    let fields = RlpItem::decode_list(strip_type(receipt)?)?;
+    if fields.len() != 4 || fields[0].bytes()? != [1u8] {
+        return None;
+    }
+    let logs = fields[3].items()?;
+    let log = logs.get(log_index as usize)?.items()?;
+    if log.len() != 3 {
+        return None;
+    }
+
+    let contract = to_address(log[0].bytes()?)?;
+    let topics = log[1].items()?
+        .iter()
+        .map(|t| t.bytes().and_then(to_hash))
+        .collect::<Option<Vec<[u8; 32]>>>()?;
+    // all parameters are indexed, the data is empty
+    if topics.len() != 4 || topics[0] != DEPOSIT_EVENT_SIGNATURE || !log[2].bytes()?.is_empty() {
+        return None;
+    }
+
+    Some(DepositLog {
+        contract,
+        dest_id: U256::from_big_endian(&topics[1]),
+        resource_id: topics[2],
+        nonce: U256::from_big_endian(&topics[3]),
+    })
+}
+
+/// Call of `deposit(uint8 destinationChainID, bytes32 resourceID, bytes data)` on the bridge contract
+#[derive(PartialEq, Eq, Clone, sp_runtime::RuntimeDebug)]
+pub struct DepositCall {
+    /// contract called by the transaction
+    pub contract: [u8; 20],
+    /// destination chain
+    pub dest_id: U256,
+    /// resource id
+    pub resource_id: ResourceId,
+    /// amount, the first word of the deposit data
+    pub amount: U256,
+    /// recipient on the destination chain, following its length in the deposit data
+    pub recipient: Vec<u8>,
+}
+
+/// Decodes a legacy or typed (EIP-2930, EIP-1559) transaction calling `deposit` on a contract.
+///
+/// The deposit data is the one of the fungible handler: amount (32 bytes), recipient length (32 bytes), recipient.
+pub fn decode_deposit_transaction(transaction: &[u8]) -> Option<DepositCall> {
+    // positions of `to` and `data` in the transaction fields
+    let (to_index, data_index) = match *transaction.first()? {
+        0x01 => (4, 6),
+        0x02 => (5, 7),
+        0xc0..=0xff => (3, 5),
+        _ => return None,
+    };
+    let fields = RlpItem::decode_list(strip_type(transaction)?)?;
+    let contract = to_address(fields.get(to_index)?.bytes()?)?;
+    let input = fields.get(data_index)?.bytes()?;
+    if input.get(..4)? != DEPOSIT_SELECTOR {
+        return None;
+    }
+
+    // abi encoded (uint8 destinationChainID, bytes32 resourceID, bytes data)
+    let args = &input[4..];
+    let dest_id = word(args, 0)?;
+    let resource_id = to_hash(args.get(32..64)?)?;
+    let data = abi_bytes(args, word(args, 64)?)?;
+
+    let amount = word(data, 0)?;
+    let recipient_len = word(data, 32)?;
+    if recipient_len > U256::from(data.len()) {
+        return None;
+    }
+    let recipient = data.get(64..64 + recipient_len.as_usize())?.to_vec();
+
+    Some(DepositCall {
+        contract,
+        dest_id,
+        resource_id,
+        amount,
+        recipient,
+    })
+}
+
+/// Payload of a receipt or transaction without the EIP-2718 type byte
+fn strip_type(encoded: &[u8]) -> Option<&[u8]> {
+    match encoded.first()? {
+        0x00..=0x7f => Some(&encoded[1..]),
+        _ => Some(encoded),
+    }
+}
+
+fn to_address(bytes: &[u8]) -> Option<[u8; 20]> {
+    if bytes.len() != 20 {
+        return None;
+    }
+    let mut address = [0u8; 20];
+    address.copy_from_slice(bytes);
+    Some(address)
+}
+
+/// 32 bytes word of abi encoded `data` at `offset`
+fn word(data: &[u8], offset: usize) -> Option<U256> {
+    data.get(offset..offset.checked_add(32)?).map(U256::from_big_endian)
+}
+
+/// dynamic `bytes` of abi encoded `data`, whose length is at `offset`
+fn abi_bytes(data: &[u8], offset: U256) -> Option<&[u8]> {
+    if offset > U256::from(data.len()) {
+        return None;
+    }
+    let offset = offset.as_usize();
+    let len = word(data, offset)?;
+    if len > U256::from(data.len()) {
+        return None;
+    }
+    data.get(offset + 32..offset + 32 + len.as_usize())
+}
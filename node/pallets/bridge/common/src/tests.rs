@@ -231,6 +231,7 @@ fn create_sucessful_proposal() {
     let r_id = derive_resource_id(src_id, b"remark");
 
     new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
         let prop_id = 1;
 		let proposal = make_proposal(vec![10]);
 
@@ -274,6 +275,7 @@ fn proposal_expires_should_work() {
     let r_id = derive_resource_id(src_id, b"remark");
 
     new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
         let prop_id = 1;
         let proposal = make_proposal(vec![10]);
 
@@ -322,6 +324,734 @@ fn proposal_expires_should_work() {
     })
 }
 
+#[test]
+fn deposit_records_should_be_kept_and_pruned() {
+    let dest_id = 2;
+    let r_id = derive_resource_id(dest_id, b"token");
+
+    new_test_ext_initialized(dest_id, r_id, b"BridgeSwap.transfer".to_vec()).execute_with(|| {
+        System::set_block_number(5);
+        for i in 1..=4u64 {
+            assert_ok!(BridgeCommon::transfer_fungible(i, dest_id, r_id, vec![i as u8], U256::from(i * 10)));
+        }
+
+        let expected = DepositRecord {
+            resource_id: r_id,
+            amount: U256::from(40),
+            recipient: vec![4],
+            source: 4,
+            block: 5,
+        };
+        assert_eq!(BridgeCommon::deposit_records(dest_id, 4), Some(expected.clone()));
+        // only the latest DepositRecordsRetained records are kept
+        assert_eq!(BridgeCommon::deposit_records(dest_id, 1), None);
+
+        let records = BridgeCommon::deposit_records_in_range(dest_id, 0, 10);
+        assert_eq!(records.iter().map(|(nonce, _)| *nonce).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(records[2], (4, expected));
+        assert_eq!(BridgeCommon::deposit_records_in_range(dest_id, 3, 3).len(), 1);
+        assert!(BridgeCommon::deposit_records_in_range(3, 0, 10).is_empty());
+    })
+}
+
+#[test]
+fn recipient_should_match_dest_chain_type() {
+    let sol_chain = 5;
+    let r_id = derive_resource_id(1, b"token");
+
+    new_test_ext().execute_with(|| {
+        assert_ok!(BridgeCommon::whitelist_chain(Origin::root(), sol_chain));
+        assert_ok!(BridgeCommon::set_proxy_accounts(Origin::root(), 40));
+        assert_ok!(BridgeCommon::set_chain_fees(Origin::signed(40), sol_chain, 1));
+        assert_ok!(BridgeCommon::set_fees_recipient_account(Origin::root(), 1));
+
+        // unregistered chains are rejected
+        assert_noop!(
+            BridgeCommon::swapable(&vec![1; 20], sol_chain, r_id, 10),
+            Error::<Test>::ChainTypeNotSet,
+        );
+
+        assert_noop!(
+            BridgeCommon::set_chain_type(Origin::signed(42), sol_chain, ChainType::Solana),
+            BadOrigin,
+        );
+        assert_noop!(
+            BridgeCommon::set_chain_type(Origin::root(), 6, ChainType::Solana),
+            Error::<Test>::ChainNotWhitelisted,
+        );
+        assert_ok!(BridgeCommon::set_chain_type(Origin::root(), sol_chain, ChainType::Solana));
+        assert_eq!(BridgeCommon::chain_types(sol_chain), Some(ChainType::Solana));
+
+        assert_noop!(
+            BridgeCommon::swapable(&vec![1; 20], sol_chain, r_id, 10),
+            Error::<Test>::InvalidRecipient,
+        );
+        assert_ok!(BridgeCommon::swapable(&vec![1; 32], sol_chain, r_id, 10));
+
+        assert_ok!(BridgeCommon::set_chain_type(Origin::root(), sol_chain, ChainType::Ethereum));
+        assert_noop!(
+            BridgeCommon::swapable(&vec![1; 32], sol_chain, r_id, 10),
+            Error::<Test>::InvalidEthereumAddress,
+        );
+
+        // ETH and BSC are EVM chains by default
+        assert_eq!(BridgeCommon::dest_chain_type(ETH_CHAIN_ID), Some(ChainType::Ethereum));
+        assert_eq!(BridgeCommon::dest_chain_type(BSC_CHAIN_ID), Some(ChainType::Ethereum));
+
+        assert_ok!(BridgeCommon::remove_whitelist_chain(Origin::root(), sol_chain));
+        assert_eq!(BridgeCommon::chain_types(sol_chain), None);
+    })
+}
+
+#[test]
+fn completed_proposals_should_be_pruned() {
+    use frame_support::traits::OnInitialize;
+
+    let src_id = 2;
+    let r_id = derive_resource_id(src_id, b"remark");
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        let proposal = make_proposal(vec![10]);
+
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(proposal.clone())));
+        let open = BridgeCommon::open_proposals(src_id);
+        assert_eq!(open, vec![ProposalInfo {
+            nonce: 1,
+            call: proposal.encode(),
+            votes: 1,
+            threshold: 2,
+            voted: vec![RELAYER_A],
+            status: ProposalStatus::Active,
+            expiry: ProposalLifetime::get() as u64,
+        }]);
+
+        System::set_block_number(3);
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 1, src_id, r_id, Box::new(proposal.clone())));
+        assert!(BridgeCommon::open_proposals(src_id).is_empty());
+        assert_eq!(BridgeCommon::executed_nonces(src_id, 1), true);
+
+        let prune_at = 3 + CompletedProposalRetention::get() as u64;
+        BridgeCommon::on_initialize(prune_at - 1);
+        assert!(BridgeCommon::votes(src_id, (1, proposal.clone())).is_some());
+        BridgeCommon::on_initialize(prune_at);
+        assert!(BridgeCommon::votes(src_id, (1, proposal.clone())).is_none());
+        assert_eq!(BridgeCommon::executed_nonces(src_id, 1), false);
+        assert_eq!(BridgeCommon::executed_nonce_floor(src_id), 1);
+
+        // pruned nonce can't be executed again
+        System::set_block_number(prune_at);
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(proposal.clone())),
+            Error::<Test>::ProposalAlreadyCompleted,
+        );
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(make_proposal(vec![11]))),
+            Error::<Test>::ProposalAlreadyCompleted,
+        );
+    })
+}
+
+#[test]
+fn executed_nonces_should_be_folded_into_floor() {
+    use frame_support::traits::OnInitialize;
+
+    let src_id = 2;
+    let r_id = derive_resource_id(src_id, b"remark");
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        let execute = |nonce: DepositNonce| {
+            let proposal = Box::new(make_proposal(vec![nonce as u8]));
+            assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), nonce, src_id, r_id, proposal.clone()));
+            assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), nonce, src_id, r_id, proposal));
+        };
+
+        // nonce 2 executes first, it is kept until nonce 1 is executed as well
+        System::set_block_number(1);
+        execute(2);
+        let first_prune = 1 + CompletedProposalRetention::get() as u64;
+        BridgeCommon::on_initialize(first_prune);
+        assert_eq!(BridgeCommon::executed_nonce_floor(src_id), 0);
+        assert_eq!(BridgeCommon::executed_nonces(src_id, 2), true);
+        assert!(BridgeCommon::is_executed(src_id, 2));
+        assert!(!BridgeCommon::is_executed(src_id, 1));
+
+        System::set_block_number(2);
+        execute(1);
+        BridgeCommon::on_initialize(first_prune + 1);
+        assert_eq!(BridgeCommon::executed_nonce_floor(src_id), 2);
+        assert_eq!(BridgeCommon::executed_nonces(src_id, 1), false);
+        assert_eq!(BridgeCommon::executed_nonces(src_id, 2), false);
+
+        System::set_block_number(first_prune + 1);
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 2, src_id, r_id, Box::new(make_proposal(vec![2]))),
+            Error::<Test>::ProposalAlreadyCompleted,
+        );
+    })
+}
+
+#[test]
+fn legacy_votes_should_be_indexed_on_upgrade() {
+    use frame_support::traits::{OnInitialize, OnRuntimeUpgrade};
+
+    let src_id = 2;
+    let r_id = derive_resource_id(src_id, b"remark");
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        StorageVersion::put(Releases::V2_0_0);
+        System::set_block_number(10);
+        let votes = |status: ProposalStatus, expiry: u64| ProposalVotes { voted: vec![RELAYER_A], status, expiry };
+        let open = make_proposal(vec![1]);
+        let stale = make_proposal(vec![2]);
+        let executed = make_proposal(vec![3]);
+        let held = make_proposal(vec![4]);
+        <Votes<Test>>::insert(src_id, (1, open.clone()), votes(ProposalStatus::Active, 20));
+        <Votes<Test>>::insert(src_id, (2, stale.clone()), votes(ProposalStatus::Active, 5));
+        <Votes<Test>>::insert(src_id, (3, executed.clone()), votes(ProposalStatus::Executed, 5));
+        <Votes<Test>>::insert(src_id, (4, held.clone()), votes(ProposalStatus::Held, 5));
+
+        BridgeCommon::on_runtime_upgrade();
+        assert_eq!(BridgeCommon::storage_version(), Releases::V3_0_0);
+        // indexed lazily from the next block initialization
+        assert!(BridgeCommon::open_proposals(src_id).is_empty());
+        BridgeCommon::on_initialize(10);
+        assert_eq!(BridgeCommon::legacy_votes_cursor(), None);
+
+        let open_nonces: Vec<DepositNonce> = BridgeCommon::open_proposals(src_id).iter().map(|p| p.nonce).collect();
+        assert_eq!(open_nonces.len(), 2);
+        assert!(open_nonces.contains(&1) && open_nonces.contains(&4));
+        assert_eq!(BridgeCommon::votes(src_id, (2, stale.clone())).unwrap().status, ProposalStatus::Expired);
+        assert!(BridgeCommon::is_executed(src_id, 3));
+
+        // the open proposal expires at its own expiry, completed ones are pruned after the retention
+        let prune_at = 10 + CompletedProposalRetention::get() as u64;
+        assert_eq!(prune_at, 20);
+        System::set_block_number(prune_at);
+        BridgeCommon::on_initialize(prune_at);
+        assert_eq!(BridgeCommon::votes(src_id, (1, open)).unwrap().status, ProposalStatus::Expired);
+        assert!(BridgeCommon::votes(src_id, (2, stale)).is_none());
+        assert!(BridgeCommon::votes(src_id, (3, executed.clone())).is_none());
+        assert!(BridgeCommon::votes(src_id, (4, held)).is_some());
+        assert!(BridgeCommon::is_executed(src_id, 3));
+
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 3, src_id, r_id, Box::new(executed)),
+            Error::<Test>::ProposalAlreadyCompleted,
+        );
+    })
+}
+
+#[test]
+fn legacy_votes_should_be_indexed_in_batches() {
+    use frame_support::traits::{OnInitialize, OnRuntimeUpgrade};
+
+    let src_id = 2;
+    let r_id = derive_resource_id(src_id, b"remark");
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        StorageVersion::put(Releases::V2_0_0);
+        System::set_block_number(10);
+        let total = LEGACY_INDEX_BATCH as u64 + 5;
+        for nonce in 1..=total {
+            let votes = ProposalVotes { voted: vec![RELAYER_A], status: ProposalStatus::Executed, expiry: 5 };
+            <Votes<Test>>::insert(src_id, (nonce, make_proposal(nonce.encode())), votes);
+        }
+
+        BridgeCommon::on_runtime_upgrade();
+        BridgeCommon::on_initialize(10);
+        assert!(BridgeCommon::legacy_votes_cursor().is_some());
+        assert_eq!(BridgeCommon::prunable_proposals(20).len(), LEGACY_INDEX_BATCH as usize);
+
+        // a vote made meanwhile is indexed once
+        let fresh = make_proposal(vec![0]);
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), total + 1, src_id, r_id, Box::new(fresh.clone())));
+        let expiry = BridgeCommon::votes(src_id, (total + 1, fresh)).unwrap().expiry;
+
+        System::set_block_number(11);
+        BridgeCommon::on_initialize(11);
+        assert_eq!(BridgeCommon::legacy_votes_cursor(), None);
+        assert_eq!(BridgeCommon::prunable_proposals(21).len(), 5);
+        assert_eq!(BridgeCommon::expiring_proposals(expiry).len(), 1);
+        assert!((1..=total).all(|nonce| BridgeCommon::is_executed(src_id, nonce)));
+        assert!(!BridgeCommon::is_executed(src_id, total + 1));
+    })
+}
+
+#[test]
+fn genesis_built_chain_should_skip_migrations() {
+    use frame_support::traits::{OnInitialize, OnRuntimeUpgrade};
+
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    GenesisConfig::default().assimilate_storage::<Test>(&mut t).unwrap();
+    sp_io::TestExternalities::from(t).execute_with(|| {
+        assert_eq!(BridgeCommon::storage_version(), Releases::V3_0_0);
+        let src_id = 2;
+        let votes = ProposalVotes { voted: vec![RELAYER_A], status: ProposalStatus::Executed, expiry: 5 };
+        <Votes<Test>>::insert(src_id, (1, make_proposal(vec![1])), votes);
+
+        assert_eq!(BridgeCommon::on_runtime_upgrade(), 0);
+        BridgeCommon::on_initialize(1);
+        assert_eq!(BridgeCommon::legacy_votes_cursor(), None);
+        assert!(!BridgeCommon::is_executed(src_id, 1));
+    })
+}
+
+#[test]
+fn stale_proposals_should_expire_and_be_pruned() {
+    use frame_support::traits::OnInitialize;
+
+    let src_id = 2;
+    let r_id = derive_resource_id(src_id, b"remark");
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        let proposal = make_proposal(vec![10]);
+        let expiry = ProposalLifetime::get() as u64;
+
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(proposal.clone())));
+        assert_eq!(BridgeCommon::open_proposals(src_id).len(), 1);
+
+        System::set_block_number(expiry);
+        BridgeCommon::on_initialize(expiry);
+        assert_eq!(BridgeCommon::votes(src_id, (1, proposal.clone())).unwrap().status, ProposalStatus::Expired);
+        assert!(BridgeCommon::open_proposals(src_id).is_empty());
+        assert_eq!(BridgeCommon::executed_nonces(src_id, 1), false);
+
+        let prune_at = expiry + CompletedProposalRetention::get() as u64;
+        BridgeCommon::on_initialize(prune_at);
+        assert!(BridgeCommon::votes(src_id, (1, proposal.clone())).is_none());
+
+        // an expired deposit can be proposed again once pruned
+        System::set_block_number(prune_at);
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(proposal.clone())));
+        assert_eq!(BridgeCommon::open_proposals(src_id).len(), 1);
+    })
+}
+
+#[test]
+fn receipt_proof_fixture_should_verify() {
+    let (hash, transactions_root, receipts_root) = proof::decode_header(&fixtures::header()).unwrap();
+    assert_eq!(hash, fixtures::block_hash());
+    assert_eq!(transactions_root, fixtures::transactions_root());
+    assert_eq!(receipts_root, fixtures::receipts_root());
+
+    let key = proof::encode_index(fixtures::TX_INDEX);
+    let receipt = proof::verify_trie_proof(receipts_root, &key, &fixtures::receipt_nodes()).unwrap();
+    assert_eq!(receipt, fixtures::receipt());
+    let transaction = proof::verify_trie_proof(transactions_root, &key, &fixtures::tx_nodes()).unwrap();
+    assert_eq!(transaction, fixtures::transaction());
+
+    let deposit = proof::decode_deposit_log(&receipt, fixtures::LOG_INDEX).unwrap();
+    assert_eq!(deposit, proof::DepositLog {
+        contract: fixtures::bridge_contract(),
+        dest_id: U256::from(ChainIdentity::get()),
+        resource_id: remark_resource_id(),
+        nonce: U256::from(fixtures::DEPOSIT_NONCE),
+    });
+    // the first log is a token transfer
+    assert!(proof::decode_deposit_log(&receipt, 0).is_none());
+
+    let call = proof::decode_deposit_transaction(&transaction).unwrap();
+    assert_eq!(call, proof::DepositCall {
+        contract: fixtures::bridge_contract(),
+        dest_id: U256::from(ChainIdentity::get()),
+        resource_id: remark_resource_id(),
+        amount: U256::from(32),
+        recipient: fixtures::recipient(),
+    });
+    // a receipt is not a transaction
+    assert!(proof::decode_deposit_transaction(&receipt).is_none());
+
+    // wrong key or tampered nodes
+    assert!(proof::verify_trie_proof(receipts_root, &proof::encode_index(2), &fixtures::receipt_nodes()).is_none());
+    assert!(proof::verify_trie_proof(transactions_root, &proof::encode_index(2), &fixtures::tx_nodes()).is_none());
+    assert!(proof::verify_trie_proof(receipts_root, &key, &fixtures::tx_nodes()).is_none());
+    let mut nodes = fixtures::receipt_nodes();
+    let last = nodes.len() - 1;
+    let byte = nodes[last].len() - 1;
+    nodes[last][byte] ^= 1;
+    assert!(proof::verify_trie_proof(receipts_root, &key, &nodes).is_none());
+}
+
+#[test]
+fn proposal_should_need_receipt_proof() {
+    let src_id = 2;
+    let r_id = remark_resource_id();
+    let header_authority = 9;
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        let nonce = fixtures::DEPOSIT_NONCE;
+        let proposal = make_proposal(fixtures::recipient());
+        let receipt_proof = ReceiptProof {
+            block_hash: fixtures::block_hash(),
+            tx_index: fixtures::TX_INDEX,
+            log_index: fixtures::LOG_INDEX,
+            tx_nodes: fixtures::tx_nodes(),
+            receipt_nodes: fixtures::receipt_nodes(),
+        };
+
+        assert_ok!(BridgeCommon::set_receipt_proof_contract(Origin::root(), src_id, Some(fixtures::bridge_contract())));
+        assert_ok!(BridgeCommon::set_header_authority(Origin::root(), header_authority));
+        assert_noop!(
+            BridgeCommon::attest_header(Origin::signed(RELAYER_A), src_id, fixtures::header()),
+            Error::<Test>::MustBeHeaderAuthority,
+        );
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), nonce, src_id, r_id, Box::new(proposal.clone())),
+            Error::<Test>::ProposalNotVerified,
+        );
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal_with_proof(Origin::signed(RELAYER_A), nonce, src_id, r_id, Box::new(proposal.clone()), receipt_proof.clone()),
+            Error::<Test>::HeaderNotAttested,
+        );
+
+        assert_ok!(BridgeCommon::attest_header(Origin::signed(header_authority), src_id, fixtures::header()));
+        assert_eq!(BridgeCommon::attested_headers(src_id, fixtures::block_hash()), Some(AttestedHeader {
+            transactions_root: fixtures::transactions_root(),
+            receipts_root: fixtures::receipts_root(),
+        }));
+
+        // the deposit doesn't match another recipient, nonce or a tampered proof
+        let mut other_recipient = fixtures::recipient();
+        other_recipient[0] ^= 1;
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal_with_proof(Origin::signed(RELAYER_A), nonce, src_id, r_id, Box::new(make_proposal(other_recipient)), receipt_proof.clone()),
+            Error::<Test>::DepositMismatch,
+        );
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal_with_proof(Origin::signed(RELAYER_A), nonce + 1, src_id, r_id, Box::new(proposal.clone()), receipt_proof.clone()),
+            Error::<Test>::DepositMismatch,
+        );
+        let mut bad_proof = receipt_proof.clone();
+        bad_proof.tx_index = 1;
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal_with_proof(Origin::signed(RELAYER_A), nonce, src_id, r_id, Box::new(proposal.clone()), bad_proof),
+            Error::<Test>::InvalidReceiptProof,
+        );
+        // the receipt alone doesn't prove the amount and recipient
+        let mut bad_proof = receipt_proof.clone();
+        bad_proof.tx_nodes = vec![];
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal_with_proof(Origin::signed(RELAYER_A), nonce, src_id, r_id, Box::new(proposal.clone()), bad_proof),
+            Error::<Test>::InvalidReceiptProof,
+        );
+
+        assert_ok!(BridgeCommon::acknowledge_proposal_with_proof(Origin::signed(RELAYER_A), nonce, src_id, r_id, Box::new(proposal.clone()), receipt_proof));
+        assert!(BridgeCommon::verified_proposals(src_id, (nonce, proposal.clone())));
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), nonce, src_id, r_id, Box::new(proposal.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (nonce, proposal.clone())).unwrap().status, ProposalStatus::Executed);
+
+        // without a contract the chain falls back to votes only
+        assert_ok!(BridgeCommon::set_receipt_proof_contract(Origin::root(), src_id, None));
+        let proposal = make_proposal(vec![10]);
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 8, src_id, r_id, Box::new(proposal.clone())));
+    })
+}
+
+#[test]
+fn migration_should_be_finalized_once() {
+    new_test_ext().execute_with(|| {
+        let sym = RSymbol::RFIS;
+        let dest_id = 2;
+        assert_ok!(BridgeCommon::whitelist_chain(Origin::root(), dest_id));
+        assert_noop!(BridgeCommon::finalize_migration(Origin::root(), sym), Error::<Test>::MigrateTargetNotSet);
+        assert_ok!(BridgeCommon::set_migrate_target(Origin::root(), sym, dest_id));
+
+        assert_ok!(RBalances::mint(&1, sym, 100));
+        assert_noop!(
+            BridgeCommon::migrate_out(&1, sym, dest_id, 101),
+            rtoken_balances::Error::<Test>::InsufficientBalance,
+        );
+        assert_ok!(BridgeCommon::migrate_out(&1, sym, dest_id, 50));
+        assert_eq!(RBalances::total_issuance(sym), 50);
+        assert_noop!(BridgeCommon::migrate_back(&1, sym, dest_id, 51), Error::<Test>::MigratedSupplyExceeded);
+        assert_ok!(BridgeCommon::migrate_back(&1, sym, dest_id, 20));
+        assert_eq!(BridgeCommon::migrated_supply(sym, dest_id), 30);
+        assert_eq!(RBalances::total_issuance(sym), 70);
+        assert_eq!(BridgeCommon::rtoken_total_supply(sym), 100);
+        assert_eq!(BridgeCommon::migrated_part(sym, dest_id, 50), 30);
+
+        assert_noop!(BridgeCommon::finalize_migration(Origin::signed(1), sym), BadOrigin);
+        assert_ok!(BridgeCommon::finalize_migration(Origin::root(), sym));
+        assert_eq!(BridgeCommon::finalized_migrations(sym), Some(dest_id));
+        assert!(RBalances::mint_disabled(sym));
+        assert_noop!(BridgeCommon::set_migrate_target(Origin::root(), sym, dest_id), Error::<Test>::MigrationAlreadyFinalized);
+        assert_noop!(BridgeCommon::migrate_back(&1, sym, dest_id, 1), Error::<Test>::MigrationAlreadyFinalized);
+    })
+}
+
+fn setup_relayers(src_id: ChainId) {
+    assert_ok!(BridgeRelayers::set_threshold(Origin::root(), src_id, 2));
+    assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_A));
+    assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_B));
+    assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_C));
+}
+
+#[test]
+fn outbound_transfer_limit_should_work() {
+    let dest_id = 2;
+    let r_id = derive_resource_id(dest_id, b"token");
+
+    new_test_ext_initialized(dest_id, r_id, b"BridgeSwap.transfer".to_vec()).execute_with(|| {
+        let limit = TransferLimit { outbound: Some(100), inbound: None };
+        assert_noop!(
+            BridgeCommon::set_chain_resource_limit(Origin::signed(42), dest_id, r_id, limit.clone()),
+            BadOrigin,
+        );
+        assert_ok!(BridgeCommon::set_chain_resource_limit(Origin::root(), dest_id, r_id, limit.clone()));
+        assert_eq!(BridgeCommon::chain_resource_limits(dest_id, r_id), Some(limit));
+
+        assert_ok!(BridgeCommon::transfer_fungible(1, dest_id, r_id, vec![1], U256::from(60)));
+        assert_noop!(
+            BridgeCommon::transfer_fungible(1, dest_id, r_id, vec![1], U256::from(41)),
+            Error::<Test>::TransferLimitExceeded,
+        );
+        assert_ok!(BridgeCommon::transfer_fungible(1, dest_id, r_id, vec![1], U256::from(40)));
+        assert_eq!(BridgeCommon::chains(dest_id), Some(2));
+
+        // resource limit over all chains applies as well
+        assert_ok!(BridgeCommon::set_resource_limit(Origin::root(), r_id, TransferLimit { outbound: Some(150), inbound: None }));
+
+        // usage resets in a new period
+        System::set_block_number(LimitPeriod::get() as u64);
+        assert_ok!(BridgeCommon::transfer_fungible(1, dest_id, r_id, vec![1], U256::from(100)));
+        assert_eq!(BridgeCommon::resource_usage(r_id).outbound, 100);
+        assert_noop!(
+            BridgeCommon::transfer_fungible(1, dest_id, r_id, vec![1], U256::from(1)),
+            Error::<Test>::TransferLimitExceeded,
+        );
+
+        // removing the chain limit leaves the resource limit
+        assert_ok!(BridgeCommon::set_chain_resource_limit(Origin::root(), dest_id, r_id, TransferLimit::default()));
+        assert_eq!(BridgeCommon::chain_resource_limits(dest_id, r_id), None);
+        assert_ok!(BridgeCommon::transfer_fungible(1, dest_id, r_id, vec![1], U256::from(50)));
+        assert_noop!(
+            BridgeCommon::transfer_fungible(1, dest_id, r_id, vec![1], U256::from(1)),
+            Error::<Test>::TransferLimitExceeded,
+        );
+    })
+}
+
+#[test]
+fn inbound_proposal_over_limit_should_be_held() {
+    let src_id = 2;
+    let r_id = remark_resource_id();
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        assert_ok!(BridgeCommon::set_resource_limit(Origin::root(), r_id, TransferLimit { outbound: None, inbound: Some(3) }));
+        assert_ok!(BridgeCommon::set_hold_delay(Origin::root(), 10));
+
+        // within limit, executed immediately
+        let small = make_proposal(vec![1, 2]);
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(small.clone())));
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 1, src_id, r_id, Box::new(small.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (1, small.clone())).unwrap().status, ProposalStatus::Executed);
+        assert_eq!(BridgeCommon::resource_usage(r_id).inbound, 2);
+
+        // over limit, held
+        let big = make_proposal(vec![3, 4]);
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 2, src_id, r_id, Box::new(big.clone())));
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 2, src_id, r_id, Box::new(big.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (2, big.clone())).unwrap().status, ProposalStatus::Held);
+        assert_eq!(BridgeCommon::held_proposals(src_id, (2, big.clone())), Some(10));
+        assert_eq!(BridgeCommon::resource_usage(r_id).inbound, 2);
+
+        // further votes are rejected
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_C), 2, src_id, r_id, Box::new(big.clone())),
+            Error::<Test>::ProposalAlreadyCompleted,
+        );
+
+        assert_noop!(
+            BridgeCommon::execute_held_proposal(Origin::signed(RELAYER_A), 2, src_id, Box::new(big.clone())),
+            Error::<Test>::HoldDelayNotPassed,
+        );
+        System::set_block_number(10);
+        assert_noop!(
+            BridgeCommon::execute_held_proposal(Origin::signed(42), 2, src_id, Box::new(big.clone())),
+            bridge_relayers::Error::<Test>::MustBeRelayer,
+        );
+        assert_ok!(BridgeCommon::execute_held_proposal(Origin::signed(RELAYER_A), 2, src_id, Box::new(big.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (2, big.clone())).unwrap().status, ProposalStatus::Executed);
+        assert_eq!(BridgeCommon::held_proposals(src_id, (2, big.clone())), None);
+        // the released amount counts toward the limit of the period
+        assert_eq!(BridgeCommon::resource_usage(r_id).inbound, 4);
+        assert_noop!(
+            BridgeCommon::execute_held_proposal(Origin::signed(RELAYER_A), 2, src_id, Box::new(big)),
+            Error::<Test>::ProposalNotHeld,
+        );
+    })
+}
+
+#[test]
+fn failed_proposal_should_not_consume_inbound_limit() {
+    let src_id = 2;
+    let r_id = remark_resource_id();
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        assert_ok!(BridgeCommon::set_resource_limit(Origin::root(), r_id, TransferLimit { outbound: None, inbound: Some(3) }));
+
+        let failing = Call::System(system::Call::set_heap_pages(2));
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(failing.clone())));
+        assert_err!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 1, src_id, r_id, Box::new(failing)),
+            BadOrigin,
+        );
+        assert!(!BridgeCommon::is_executed(src_id, 1));
+        assert_eq!(BridgeCommon::resource_usage(r_id).inbound, 0);
+        assert_eq!(BridgeCommon::chain_resource_usage(src_id, r_id).inbound, 0);
+
+        // the whole limit is left for the next transfer
+        let remark = make_proposal(vec![1, 2, 3]);
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 2, src_id, r_id, Box::new(remark.clone())));
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 2, src_id, r_id, Box::new(remark.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (2, remark)).unwrap().status, ProposalStatus::Executed);
+        assert_eq!(BridgeCommon::resource_usage(r_id).inbound, 3);
+    })
+}
+
+#[test]
+fn held_proposal_can_be_approved_or_rejected() {
+    let src_id = 2;
+    let r_id = remark_resource_id();
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        assert_ok!(BridgeCommon::set_chain_resource_limit(Origin::root(), src_id, r_id, TransferLimit { outbound: None, inbound: Some(1) }));
+        assert_ok!(BridgeCommon::set_hold_delay(Origin::root(), 100));
+
+        let first = make_proposal(vec![1, 2]);
+        let second = make_proposal(vec![3, 4]);
+        for (nonce, prop) in vec![(1, first.clone()), (2, second.clone())] {
+            assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), nonce, src_id, r_id, Box::new(prop.clone())));
+            assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), nonce, src_id, r_id, Box::new(prop.clone())));
+            assert_eq!(BridgeCommon::votes(src_id, (nonce, prop)).unwrap().status, ProposalStatus::Held);
+        }
+
+        assert_noop!(
+            BridgeCommon::approve_held_proposal(Origin::signed(42), 1, src_id, Box::new(first.clone())),
+            BadOrigin,
+        );
+        assert_ok!(BridgeCommon::approve_held_proposal(Origin::root(), 1, src_id, Box::new(first.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (1, first)).unwrap().status, ProposalStatus::Executed);
+        assert_eq!(BridgeCommon::chain_resource_usage(src_id, r_id).inbound, 2);
+
+        assert_ok!(BridgeCommon::reject_held_proposal(Origin::root(), 2, src_id, Box::new(second.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (2, second.clone())).unwrap().status, ProposalStatus::Expired);
+        System::set_block_number(100);
+        assert_noop!(
+            BridgeCommon::execute_held_proposal(Origin::signed(RELAYER_A), 2, src_id, Box::new(second)),
+            Error::<Test>::ProposalNotHeld,
+        );
+    })
+}
+
+#[test]
+fn hold_delay_should_default_to_constant() {
+    let src_id = 2;
+    let r_id = remark_resource_id();
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        assert_eq!(BridgeCommon::hold_delay(), DefaultHoldDelay::get());
+        assert_noop!(BridgeCommon::set_hold_delay(Origin::root(), 0), Error::<Test>::InvalidHoldDelay);
+        assert_ok!(BridgeCommon::set_resource_limit(Origin::root(), r_id, TransferLimit { outbound: None, inbound: Some(1) }));
+
+        let prop = make_proposal(vec![1, 2]);
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(prop.clone())));
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 1, src_id, r_id, Box::new(prop.clone())));
+        assert_eq!(BridgeCommon::held_proposals(src_id, (1, prop.clone())), Some(DefaultHoldDelay::get()));
+        assert_noop!(
+            BridgeCommon::execute_held_proposal(Origin::signed(RELAYER_A), 1, src_id, Box::new(prop)),
+            Error::<Test>::HoldDelayNotPassed,
+        );
+    })
+}
+
+#[test]
+fn only_relayers_of_source_chain_can_vote() {
+    let src_id = 2;
+    let other_id = 3;
+    let r_id = derive_resource_id(src_id, b"remark");
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        let proposal = make_proposal(vec![10]);
+        assert_ok!(BridgeCommon::whitelist_chain(Origin::root(), other_id));
+        assert_ok!(BridgeRelayers::add_relayer(Origin::root(), other_id, RELAYER_A));
+
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(proposal.clone())),
+            bridge_relayers::Error::<Test>::MustBeRelayer,
+        );
+
+        // a relayer without threshold can not pass anything
+        assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_A));
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(proposal.clone())),
+            Error::<Test>::RelayerThresholdNotSet,
+        );
+    })
+}
+
+#[test]
+fn votes_of_removed_relayers_should_not_count() {
+    let src_id = 2;
+    let r_id = derive_resource_id(src_id, b"remark");
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        setup_relayers(src_id);
+        let proposal = make_proposal(vec![10]);
+
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(proposal.clone())));
+        assert_ok!(BridgeRelayers::remove_relayer(Origin::root(), src_id, RELAYER_A));
+
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 1, src_id, r_id, Box::new(proposal.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (1, proposal.clone())).unwrap().status, ProposalStatus::Active);
+
+        assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_C), 1, src_id, r_id, Box::new(proposal.clone())));
+        assert_eq!(BridgeCommon::votes(src_id, (1, proposal)).unwrap().status, ProposalStatus::Executed);
+    })
+}
+
+#[test]
+fn legacy_relayers_should_be_removed_on_upgrade() {
+    use frame_support::{storage::migration::{put_storage_value, get_storage_value}, traits::OnRuntimeUpgrade};
+
+    let src_id = 2;
+    let r_id = derive_resource_id(src_id, b"remark");
+
+    new_test_ext_initialized(src_id, r_id, b"System.remark".to_vec()).execute_with(|| {
+        // legacy global set, which never took part in voting
+        let mut key = blake2_128(&RELAYER_A.encode()).to_vec();
+        key.extend(RELAYER_A.encode());
+        put_storage_value(b"BridgeCommon", b"Relayers", &key, true);
+        put_storage_value(b"BridgeCommon", b"RelayerCount", &[], 1u32);
+        put_storage_value(b"BridgeCommon", b"RelayerThreshold", &[], 1u32);
+        assert_eq!(BridgeCommon::storage_version(), Releases::V1_0_0);
+
+        BridgeCommon::on_runtime_upgrade();
+
+        assert_eq!(BridgeCommon::storage_version(), Releases::V3_0_0);
+        assert_eq!(get_storage_value::<bool>(b"BridgeCommon", b"Relayers", &key), None);
+        assert_eq!(get_storage_value::<u32>(b"BridgeCommon", b"RelayerCount", &[]), None);
+        assert_eq!(get_storage_value::<u32>(b"BridgeCommon", b"RelayerThreshold", &[]), None);
+
+        // the legacy relayer has no say, the per chain set is the only one
+        assert_eq!(BridgeRelayers::is_relayer(src_id, &RELAYER_A), false);
+        assert_noop!(
+            BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, r_id, Box::new(make_proposal(vec![1]))),
+            bridge_relayers::Error::<Test>::MustBeRelayer,
+        );
+    })
+}
+
 // fn last_event() -> TestEvent {
 // 	system::Module::<Test>::events().pop().map(|e| e.event).expect("Event expected")
 // }
\ No newline at end of file
@@ -12,16 +12,19 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use sp_std::prelude::*;
-use bridge_common::{self as bridge, ResourceId};
+use codec::{Decode, Encode};
+use bridge_common::{self as bridge, ResourceId, ReserveStatus, TransferFee};
 use frame_support::{
-    decl_error, decl_module, dispatch::DispatchResult, ensure,
+    decl_error, decl_module, decl_storage, dispatch::DispatchResult, ensure,
+    storage::IterableStorageMap,
     traits::{
         Currency, EnsureOrigin, Get,
         ExistenceRequirement::{KeepAlive},
     },
+    weights::Weight,
 };
 use frame_system::{self as system, ensure_signed};
-use sp_runtime::{traits::{Zero, Saturating}};
+use sp_runtime::{RuntimeDebug, traits::{Zero, Saturating}};
 use sp_core::U256;
 use sp_arithmetic::traits::SaturatedConversion;
 use node_primitives::{ChainId, RSymbol, XSymbol};
@@ -46,6 +49,28 @@ pub trait Trait: system::Trait + bridge::Trait {
     type NativeTokenId: Get<ResourceId>;
 }
 
+/// Storage version of the pallet
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Releases {
+    /// locked reserves of bridge_common not tracked
+    V1_0_0,
+    /// locked reserves seeded from the bridge account balances
+    V2_0_0,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1_0_0
+    }
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as BridgeSwap {
+        /// Storage version of the pallet
+        StorageVersion get(fn storage_version) build(|_| Releases::V2_0_0): Releases;
+    }
+}
+
 decl_error! {
     pub enum Error for Module<T: Trait> {
         InsufficientRbalance,
@@ -62,13 +87,25 @@ decl_module! {
 
         const NativeTokenId: ResourceId = T::NativeTokenId::get();
 
+        fn on_runtime_upgrade() -> Weight {
+            if Self::storage_version() == Releases::V1_0_0 {
+                let weight = Self::seed_locked_reserves();
+                StorageVersion::put(Releases::V2_0_0);
+                weight
+            } else {
+                0
+            }
+        }
+
         /// Transfers some amount of the native token to some recipient on a (whitelisted) destination chain.
         #[weight = 195_000_000]
         pub fn transfer_native(origin, amount: BalanceOf<T>, recipient: Vec<u8>, dest_id: ChainId) -> DispatchResult {
             let source = ensure_signed(origin)?;
 
-            let (fee, receiver, bridger) = <bridge::Module<T>>::swapable(&recipient, dest_id)?;
-            let fee: BalanceOf<T> = fee.saturated_into();
+            let resource_id = T::NativeTokenId::get();
+            let (fee, receiver, bridger) = <bridge::Module<T>>::swapable(&recipient, dest_id, resource_id, amount.saturated_into())?;
+            // the transferred token is FIS, so both kinds of fee are paid in FIS
+            let fee: BalanceOf<T> = fee.amount().saturated_into();
 
             let total_amount = amount.saturating_add(fee);
             T::Currency::transfer(&source, &bridger, total_amount, KeepAlive)?;
@@ -76,8 +113,8 @@ decl_module! {
             if fee > Zero::zero() {
                 T::Currency::transfer(&bridger, &receiver, fee, KeepAlive)?;
             }
+            <bridge::Module<T>>::lock_reserve(resource_id, amount.saturated_into());
 
-            let resource_id = T::NativeTokenId::get();
             <bridge::Module<T>>::transfer_fungible(source, dest_id, resource_id, recipient, U256::from(amount.saturated_into::<u128>()))
         }
 
@@ -85,6 +122,7 @@ decl_module! {
         #[weight = 195_000_000]
         pub fn transfer_native_back(origin, recipient: T::AccountId, amount: BalanceOf<T>, _resource_id: ResourceId) -> DispatchResult {
             let bridge_id = T::BridgeOrigin::ensure_origin(origin)?;
+            <bridge::Module<T>>::release_reserve(T::NativeTokenId::get(), amount.saturated_into())?;
             T::Currency::transfer(&bridge_id, &recipient, amount, KeepAlive)?;
 
             Ok(())
@@ -95,24 +133,30 @@ decl_module! {
         pub fn transfer_rtoken(origin, symbol: RSymbol, amount: u128, recipient: Vec<u8>, dest_id: ChainId) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
-            let (fee, receiver, bridger) = <bridge::Module<T>>::swapable(&recipient, dest_id)?;
             let resource = <bridge::Module<T>>::rsymbol_resource(&symbol).ok_or(Error::<T>::RsymbolNotMapped)?;
-            let new_rbalance = T::RCurrency::free_balance(&who, symbol).checked_sub(amount)
+            let (fee, receiver, bridger) = <bridge::Module<T>>::swapable(&recipient, dest_id, resource, amount)?;
+            let token_fee = if let TransferFee::Token(f) = fee { f } else { 0 };
+            let total_amount = amount.checked_add(token_fee).ok_or(Error::<T>::InsufficientRbalance)?;
+            let new_rbalance = T::RCurrency::free_balance(&who, symbol).checked_sub(total_amount)
                 .ok_or(Error::<T>::InsufficientRbalance)?;
-            T::RCurrency::ensure_can_withdraw(&who, symbol, amount, new_rbalance)?;
+            T::RCurrency::ensure_can_withdraw(&who, symbol, total_amount, new_rbalance)?;
 
-            if fee > 0 {
-                T::Currency::transfer(&who, &receiver, fee.saturated_into(), KeepAlive)?;
+            match fee {
+                TransferFee::Native(f) if f > 0 => T::Currency::transfer(&who, &receiver, f.saturated_into(), KeepAlive)?,
+                TransferFee::Token(f) if f > 0 => T::RCurrency::transfer(&who, &receiver, symbol, f)?,
+                _ => (),
             }
 
             if symbol == RSymbol::RETH {
                 T::RCurrency::burn(&who, symbol, amount)?;
+                <bridge::Module<T>>::note_burnt(resource, amount);
             } else {
                 let op_migrate_target = <bridge::Module<T>>::migrate_target(symbol);
                 if op_migrate_target.is_some() && op_migrate_target.unwrap() == dest_id {
-                    T::RCurrency::burn(&who, symbol, amount)?;
+                    <bridge::Module<T>>::migrate_out(&who, symbol, dest_id, amount)?;
                 } else {
                     T::RCurrency::transfer(&who, &bridger, symbol, amount)?;
+                    <bridge::Module<T>>::lock_reserve(resource, amount);
                 }
             }
 
@@ -128,8 +172,21 @@ decl_module! {
             let sym = op_sym.unwrap();
             if sym == RSymbol::RETH {
                 T::RCurrency::mint(&recipient, sym, amount)?;
-            } else {
-                T::RCurrency::transfer(&bridge_id, &recipient, sym, amount)?;
+                <bridge::Module<T>>::note_minted(resource_id, amount);
+                return Ok(());
+            }
+
+            // rtokens migrated to the source chain are minted back, those locked before the migration are unlocked
+            let src_id = <bridge::Module<T>>::current_proposal_source();
+            let migrated = src_id.map(|id| <bridge::Module<T>>::migrated_part(sym, id, amount)).unwrap_or(0);
+            let locked = amount - migrated;
+            ensure!(<bridge::Module<T>>::resource_reserves(resource_id).locked >= locked, bridge::Error::<T>::InsufficientReserve);
+            if let Some(src_id) = src_id.filter(|_| migrated > 0) {
+                <bridge::Module<T>>::migrate_back(&recipient, sym, src_id, migrated)?;
+            }
+            if locked > 0 {
+                <bridge::Module<T>>::release_reserve(resource_id, locked)?;
+                T::RCurrency::transfer(&bridge_id, &recipient, sym, locked)?;
             }
             Ok(())
         }
@@ -139,16 +196,21 @@ decl_module! {
         pub fn transfer_xtoken(origin, symbol: XSymbol, amount: u128, recipient: Vec<u8>, dest_id: ChainId) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
-            let (fee, receiver, _) = <bridge::Module<T>>::swapable(&recipient, dest_id)?;
             let resource = <bridge::Module<T>>::xsymbol_resource(&symbol).ok_or(Error::<T>::XsymbolNotMapped)?;
-            let new_rbalance = T::XCurrency::free_balance(&who, symbol).checked_sub(amount)
+            let (fee, receiver, _) = <bridge::Module<T>>::swapable(&recipient, dest_id, resource, amount)?;
+            let token_fee = if let TransferFee::Token(f) = fee { f } else { 0 };
+            let total_amount = amount.checked_add(token_fee).ok_or(Error::<T>::InsufficientXbalance)?;
+            let new_rbalance = T::XCurrency::free_balance(&who, symbol).checked_sub(total_amount)
                 .ok_or(Error::<T>::InsufficientXbalance)?;
-            T::XCurrency::ensure_can_withdraw(&who, symbol, amount, new_rbalance)?;
+            T::XCurrency::ensure_can_withdraw(&who, symbol, total_amount, new_rbalance)?;
 
-            if fee > 0 {
-                T::Currency::transfer(&who, &receiver, fee.saturated_into(), KeepAlive)?;
+            match fee {
+                TransferFee::Native(f) if f > 0 => T::Currency::transfer(&who, &receiver, f.saturated_into(), KeepAlive)?,
+                TransferFee::Token(f) if f > 0 => T::XCurrency::transfer(&who, &receiver, symbol, f)?,
+                _ => (),
             }
             T::XCurrency::burn(&who, symbol, amount)?;
+            <bridge::Module<T>>::note_burnt(resource, amount);
 
             <bridge::Module<T>>::transfer_fungible(who, dest_id, resource, recipient, U256::from(amount))
         }
@@ -161,8 +223,51 @@ decl_module! {
             ensure!(op_sym.is_some(), Error::<T>::ResourceNotMapped);
             let sym = op_sym.unwrap();
             T::XCurrency::mint(&recipient, sym, amount)?;
+            <bridge::Module<T>>::note_minted(resource_id, amount);
             Ok(())
         }
     }
 }
 
+impl<T: Trait> Module<T> {
+    /// Seeds the locked reserve of the native token and of the rtokens locked by outbound transfers
+    /// with what the bridge account holds, which is all these transfers locked before reserves were tracked
+    fn seed_locked_reserves() -> Weight {
+        let bridger = <bridge::Module<T>>::account_id();
+        let native = T::Currency::free_balance(&bridger).saturated_into();
+        <bridge::ResourceReserves>::mutate(T::NativeTokenId::get(), |r| r.locked = native);
+
+        let mut count: Weight = 1;
+        for (resource_id, sym) in <bridge::ResourceRsymbol>::iter() {
+            // rETH is burnt and minted, never locked
+            if sym == RSymbol::RETH {
+                continue;
+            }
+            let held = T::RCurrency::free_balance(&bridger, sym);
+            <bridge::ResourceReserves>::mutate(resource_id, |r| r.locked = held);
+            count += 1;
+        }
+
+        T::DbWeight::get().reads_writes(2 * count + 1, count + 1)
+    }
+
+    /// Reserves of all bridged resources against the balances of the bridge account
+    pub fn reserve_statuses() -> Vec<ReserveStatus> {
+        let bridger = <bridge::Module<T>>::account_id();
+        <bridge::ResourceReserves>::iter()
+            .map(|(resource_id, reserve)| {
+                let held = if resource_id == T::NativeTokenId::get() {
+                    T::Currency::free_balance(&bridger).saturated_into()
+                } else if let Some(sym) = <bridge::Module<T>>::resource_rsymbol(&resource_id) {
+                    T::RCurrency::free_balance(&bridger, sym)
+                } else if let Some(sym) = <bridge::Module<T>>::resource_xsymbol(&resource_id) {
+                    T::XCurrency::free_balance(&bridger, sym)
+                } else {
+                    0
+                };
+                ReserveStatus { resource_id, reserve, held }
+            })
+            .collect()
+    }
+}
+
@@ -30,6 +30,7 @@ impl_outer_dispatch! {
 		balances::Balances,
 		rtoken_balances::RBalances,
 		xtoken_balances::XBalances,
+		bridge_relayers::BridgeRelayers,
 		bridge_common::BridgeCommon,
 		self::BridgeSwap,
 	}
@@ -105,9 +106,29 @@ impl rtoken_balances::Trait for Test {
 	type Event = ();
 }
 
+impl bridge_relayers::Trait for Test {
+	type Event = ();
+}
+
 parameter_types! {
 	pub const ChainIdentity: ChainId = 1;
 	pub const ProposalLifetime: BlockNumber = 50;
+	pub const LimitPeriod: BlockNumber = 100;
+	pub const DefaultHoldDelay: BlockNumber = 5;
+	pub const DepositRecordsRetained: u64 = 3;
+	pub const CompletedProposalRetention: BlockNumber = 10;
+}
+
+pub struct SwapProposalInspector;
+impl bridge_common::ProposalInspector<Call> for SwapProposalInspector {
+	fn transfer_of(proposal: &Call) -> Option<(bridge_common::ResourceId, u128)> {
+		match proposal {
+			Call::BridgeSwap(crate::Call::transfer_native_back(_, amount, rid)) => Some((*rid, *amount)),
+			Call::BridgeSwap(crate::Call::transfer_rtoken_back(_, amount, rid)) => Some((*rid, *amount)),
+			Call::BridgeSwap(crate::Call::transfer_xtoken_back(_, amount, rid)) => Some((*rid, *amount)),
+			_ => None,
+		}
+	}
 }
 
 impl bridge_common::Trait for Test {
@@ -116,6 +137,12 @@ impl bridge_common::Trait for Test {
 	type ChainIdentity = ChainIdentity;
 	type Proposal = Call;
 	type ProposalLifetime = ProposalLifetime;
+	type LimitPeriod = LimitPeriod;
+	type DefaultHoldDelay = DefaultHoldDelay;
+	type DepositRecordsRetained = DepositRecordsRetained;
+	type CompletedProposalRetention = CompletedProposalRetention;
+	type ProposalInspector = SwapProposalInspector;
+	type RTokenCurrency = RBalances;
 }
 
 parameter_types! {
@@ -146,6 +173,7 @@ pub type System = frame_system::Module<Test>;
 pub type Balances = pallet_balances::Module<Test>;
 pub type RBalances = rtoken_balances::Module<Test>;
 pub type XBalances = xtoken_balances::Module<Test>;
+pub type BridgeRelayers = bridge_relayers::Module<Test>;
 pub type BridgeCommon = bridge_common::Module<Test>;
 pub type BridgeSwap = Module<Test>;
 
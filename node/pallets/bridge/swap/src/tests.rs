@@ -15,7 +15,7 @@ use super::*;
 use super::mock::{*, Call};
 use frame_support::{assert_ok, assert_noop, assert_err};
 use node_primitives::{ETH_CHAIN_ID, RSymbol};
-use sp_runtime::traits::BadOrigin;
+use sp_runtime::{Perbill, traits::BadOrigin};
 
 #[test]
 fn transfer_native_should_work() {
@@ -78,6 +78,15 @@ fn transfer_native_should_work() {
 #[test]
 fn transfer_rtoken_should_work() {
 	new_test_ext().execute_with(|| {
+		assert_noop!(
+			BridgeSwap::transfer_rtoken(Origin::signed(42), RSymbol::RFIS, 100, vec![11, 21], ETH_CHAIN_ID),
+			Error::<Test>::RsymbolNotMapped,
+		);
+
+		let rid: ResourceId = [1; 32];
+		let sym: RSymbol = RSymbol::RFIS;
+		assert_ok!(BridgeCommon::map_resource_and_rsymbol(Origin::root(), rid, sym));
+
 		assert_noop!(
 			BridgeSwap::transfer_rtoken(Origin::signed(42), RSymbol::RFIS, 100, vec![11, 21], ETH_CHAIN_ID),
 			Error::<Test>::InvalidChainId,
@@ -112,14 +121,6 @@ fn transfer_rtoken_should_work() {
 
 		let recipient_account = 2;
 		assert_ok!(BridgeCommon::set_fees_recipient_account(Origin::root(), recipient_account));
-		assert_noop!(
-			BridgeSwap::transfer_rtoken(Origin::signed(42), RSymbol::RFIS, 100, eth_address.clone(), ETH_CHAIN_ID),
-			Error::<Test>::RsymbolNotMapped,
-        );
-
-        let rid: ResourceId = [1; 32];
-        let sym: RSymbol = RSymbol::RFIS;
-        assert_ok!(BridgeCommon::map_resource_and_rsymbol(Origin::root(), rid, sym));
 
 		assert_noop!(
 			BridgeSwap::transfer_rtoken(Origin::signed(42), RSymbol::RFIS, 100, eth_address.clone(), ETH_CHAIN_ID),
@@ -140,6 +141,57 @@ fn transfer_rtoken_should_work() {
 	});
 }
 
+#[test]
+fn transfer_rtoken_with_token_fee_should_work() {
+	new_test_ext().execute_with(|| {
+		let rid: ResourceId = [1; 32];
+		let sym: RSymbol = RSymbol::RFIS;
+		let recipient_account = 2;
+		let eth_address = vec![11, 21, 31, 43, 88, 120, 43, 54, 55, 99, 54, 98, 23, 24, 54, 64, 29, 94, 26, 75];
+		assert_ok!(BridgeCommon::map_resource_and_rsymbol(Origin::root(), rid, sym));
+		assert_ok!(BridgeCommon::whitelist_chain(Origin::root(), ETH_CHAIN_ID));
+		assert_ok!(BridgeCommon::set_fees_recipient_account(Origin::root(), recipient_account));
+		assert_ok!(BridgeCommon::set_proxy_accounts(Origin::root(), 40));
+
+		// percentage fees can only be paid in the token
+		let native_percent = bridge::ResourceFee { rate: bridge::FeeRate::Percent(Perbill::from_percent(1)), in_token: false };
+		assert_noop!(
+			BridgeCommon::set_resource_fee(Origin::signed(40), ETH_CHAIN_ID, rid, native_percent),
+			bridge::Error::<Test>::InvalidResourceFee,
+		);
+
+		let token_percent = bridge::ResourceFee { rate: bridge::FeeRate::Percent(Perbill::from_percent(1)), in_token: true };
+		assert_ok!(BridgeCommon::set_resource_fee(Origin::signed(40), ETH_CHAIN_ID, rid, token_percent));
+
+		// user holds no FIS at all
+		assert_ok!(RBalances::mint(&(42 as u64), sym, 1000));
+		assert_noop!(
+			BridgeSwap::transfer_rtoken(Origin::signed(42), sym, 991, eth_address.clone(), ETH_CHAIN_ID),
+			Error::<Test>::InsufficientRbalance,
+		);
+		assert_ok!(BridgeSwap::transfer_rtoken(Origin::signed(42), sym, 900, eth_address.clone(), ETH_CHAIN_ID));
+
+		assert_eq!(RBalances::free_balance(&42, sym), 91);
+		assert_eq!(RBalances::free_balance(&recipient_account, sym), 9);
+		assert_eq!(RBalances::free_balance(&BridgeCommon::account_id(), sym), 900);
+		assert_eq!(BridgeCommon::resource_reserves(rid).locked, 900);
+
+		// flat fee in token
+		let token_flat = bridge::ResourceFee { rate: bridge::FeeRate::Flat(10), in_token: true };
+		assert_ok!(BridgeCommon::set_resource_fee(Origin::signed(40), ETH_CHAIN_ID, rid, token_flat));
+		assert_ok!(BridgeSwap::transfer_rtoken(Origin::signed(42), sym, 81, eth_address.clone(), ETH_CHAIN_ID));
+		assert_eq!(RBalances::free_balance(&42, sym), 0);
+		assert_eq!(RBalances::free_balance(&recipient_account, sym), 19);
+
+		// without a resource fee the chain fee applies
+		assert_ok!(BridgeCommon::remove_resource_fee(Origin::signed(40), ETH_CHAIN_ID, rid));
+		assert_noop!(
+			BridgeSwap::transfer_rtoken(Origin::signed(42), sym, 1, eth_address.clone(), ETH_CHAIN_ID),
+			bridge::Error::<Test>::InvalidChainFee,
+		);
+	});
+}
+
 #[test]
 fn transfer_native_back_should_work() {
     new_test_ext().execute_with(|| {
@@ -154,8 +206,76 @@ fn transfer_native_back_should_work() {
 
 		assert_ok!(Balances::transfer(Origin::signed(1), bridge_id, 100));
 
+		// nothing has been locked by outbound transfers
+		assert_noop!(
+			BridgeSwap::transfer_native_back(Origin::signed(bridge_id), recipient, 100, rid),
+			bridge::Error::<Test>::InsufficientReserve,
+		);
+		let reserve = bridge::ResourceReserve { locked: 100, minted: 0 };
+		assert_ok!(BridgeCommon::set_resource_reserve(Origin::root(), NativeTokenId::get(), reserve));
+
         // transfer_native_back
         assert_ok!(BridgeSwap::transfer_native_back(Origin::signed(bridge_id), recipient, 100, rid));
+        assert_eq!(BridgeCommon::resource_reserves(NativeTokenId::get()).locked, 0);
+    })
+}
+
+#[test]
+fn locked_reserves_should_be_seeded_on_upgrade() {
+    use frame_support::traits::OnRuntimeUpgrade;
+
+    new_test_ext().execute_with(|| {
+        let bridge_id: u64 = BridgeCommon::account_id();
+        let rid: ResourceId = [1; 32];
+        let reth_rid: ResourceId = [2; 32];
+
+        // balances locked by transfers made before reserves were tracked
+        assert_ok!(Balances::transfer(Origin::signed(1), bridge_id, 60));
+        assert_ok!(BridgeCommon::map_resource_and_rsymbol(Origin::root(), rid, RSymbol::RFIS));
+        assert_ok!(BridgeCommon::map_resource_and_rsymbol(Origin::root(), reth_rid, RSymbol::RETH));
+        assert_ok!(RBalances::mint(&bridge_id, RSymbol::RFIS, 300));
+        assert_ok!(RBalances::mint(&bridge_id, RSymbol::RETH, 5));
+        assert_eq!(BridgeSwap::storage_version(), Releases::V1_0_0);
+
+        BridgeSwap::on_runtime_upgrade();
+        assert_eq!(BridgeSwap::storage_version(), Releases::V2_0_0);
+        assert_eq!(BridgeCommon::resource_reserves(NativeTokenId::get()).locked, 60);
+        assert_eq!(BridgeCommon::resource_reserves(rid).locked, 300);
+        assert_eq!(BridgeCommon::resource_reserves(reth_rid).locked, 0);
+
+        assert_ok!(BridgeSwap::transfer_native_back(Origin::signed(bridge_id), RELAYER_A, 60, rid));
+        assert_eq!(Balances::free_balance(RELAYER_A), 60);
+        assert_ok!(BridgeSwap::transfer_rtoken_back(Origin::signed(bridge_id), RELAYER_A, 300, rid));
+        assert_eq!(RBalances::free_balance(&RELAYER_A, RSymbol::RFIS), 300);
+        assert_noop!(
+            BridgeSwap::transfer_rtoken_back(Origin::signed(bridge_id), RELAYER_A, 1, rid),
+            bridge::Error::<Test>::InsufficientReserve,
+        );
+
+        // only seeded once
+        assert_ok!(Balances::transfer(Origin::signed(1), bridge_id, 10));
+        BridgeSwap::on_runtime_upgrade();
+        assert_eq!(BridgeCommon::resource_reserves(NativeTokenId::get()).locked, 0);
+    })
+}
+
+#[test]
+fn genesis_built_chain_should_not_reseed_reserves() {
+    use frame_support::traits::OnRuntimeUpgrade;
+
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    GenesisConfig::default().assimilate_storage::<Test>(&mut t).unwrap();
+    sp_io::TestExternalities::from(t).execute_with(|| {
+        assert_eq!(BridgeSwap::storage_version(), Releases::V2_0_0);
+        let bridge_id: u64 = BridgeCommon::account_id();
+        let rid: ResourceId = [1; 32];
+        assert_ok!(BridgeCommon::map_resource_and_rsymbol(Origin::root(), rid, RSymbol::RFIS));
+        BridgeCommon::lock_reserve(rid, 100);
+        // rtokens sent to the bridge account without a transfer are not locked reserves
+        assert_ok!(RBalances::mint(&bridge_id, RSymbol::RFIS, 300));
+
+        assert_eq!(BridgeSwap::on_runtime_upgrade(), 0);
+        assert_eq!(BridgeCommon::resource_reserves(rid).locked, 100);
     })
 }
 
@@ -174,11 +294,13 @@ fn transfer_native_back_proposal() {
 		let proposal = make_transfer_proposal(RELAYER_A, 10);
 		
 		assert_ok!(Balances::transfer(Origin::signed(1), BridgeCommon::account_id(), 100));
+		let reserve = bridge::ResourceReserve { locked: 100, minted: 0 };
+		assert_ok!(BridgeCommon::set_resource_reserve(Origin::root(), NativeTokenId::get(), reserve));
 
-        assert_ok!(BridgeCommon::set_threshold(Origin::root(), TEST_THRESHOLD));
-        assert_ok!(BridgeCommon::add_relayer(Origin::root(), RELAYER_A));
-        assert_ok!(BridgeCommon::add_relayer(Origin::root(), RELAYER_B));
-        assert_ok!(BridgeCommon::add_relayer(Origin::root(), RELAYER_C));
+        assert_ok!(BridgeRelayers::set_threshold(Origin::root(), src_id, TEST_THRESHOLD));
+        assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_A));
+        assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_B));
+        assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_C));
         assert_ok!(BridgeCommon::whitelist_chain(Origin::root(), src_id));
         assert_ok!(BridgeCommon::add_resource(Origin::root(), rid, resource));
 
@@ -236,11 +358,13 @@ fn transfer_rtoken_back_proposal() {
 		
 		let ac = BridgeCommon::account_id();
 		assert_ok!(RBalances::mint(&ac, sym, 100));
+		let reserve = bridge::ResourceReserve { locked: 100, minted: 0 };
+		assert_ok!(BridgeCommon::set_resource_reserve(Origin::root(), rid, reserve));
 
-        assert_ok!(BridgeCommon::set_threshold(Origin::root(), TEST_THRESHOLD));
-        assert_ok!(BridgeCommon::add_relayer(Origin::root(), RELAYER_A));
-        assert_ok!(BridgeCommon::add_relayer(Origin::root(), RELAYER_B));
-        assert_ok!(BridgeCommon::add_relayer(Origin::root(), RELAYER_C));
+        assert_ok!(BridgeRelayers::set_threshold(Origin::root(), src_id, TEST_THRESHOLD));
+        assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_A));
+        assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_B));
+        assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_C));
         assert_ok!(BridgeCommon::whitelist_chain(Origin::root(), src_id));
         assert_ok!(BridgeCommon::add_resource(Origin::root(), rid, resource));
 
@@ -290,5 +414,114 @@ fn transfer_rtoken_back_proposal() {
 
         assert_eq!(RBalances::free_balance(&RELAYER_A, sym), 10);
         assert_eq!(RBalances::free_balance(&ac, sym), 90);
+        assert_eq!(BridgeSwap::reserve_statuses(), vec![bridge::ReserveStatus {
+            resource_id: rid,
+            reserve: bridge::ResourceReserve { locked: 90, minted: 0 },
+            held: 90,
+        }]);
     })
 }
+
+#[test]
+fn rtoken_migration_should_keep_total_supply() {
+	new_test_ext().execute_with(|| {
+		let rid: ResourceId = [1; 32];
+		let sym: RSymbol = RSymbol::RFIS;
+		let src_id = ETH_CHAIN_ID;
+		let eth_address = vec![11, 21, 31, 43, 88, 120, 43, 54, 55, 99, 54, 98, 23, 24, 54, 64, 29, 94, 26, 75];
+		assert_ok!(BridgeCommon::map_resource_and_rsymbol(Origin::root(), rid, sym));
+		assert_ok!(BridgeCommon::whitelist_chain(Origin::root(), src_id));
+		assert_ok!(BridgeCommon::add_resource(Origin::root(), rid, b"BridgeSwap.transfer_rtoken_back".to_vec()));
+		assert_ok!(BridgeCommon::set_fees_recipient_account(Origin::root(), 2));
+		assert_ok!(BridgeCommon::set_proxy_accounts(Origin::root(), 40));
+		assert_ok!(BridgeCommon::set_chain_fees(Origin::signed(40), src_id, 0));
+		assert_ok!(BridgeRelayers::set_threshold(Origin::root(), src_id, TEST_THRESHOLD));
+		assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_A));
+		assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_B));
+		assert_ok!(BridgeCommon::set_migrate_target(Origin::root(), sym, src_id));
+
+		assert_ok!(RBalances::mint(&(42 as u64), sym, 1000));
+		assert_ok!(BridgeSwap::transfer_rtoken(Origin::signed(42), sym, 600, eth_address.clone(), src_id));
+		assert_eq!(RBalances::total_issuance(sym), 400);
+		assert_eq!(BridgeCommon::migrated_supply(sym, src_id), 600);
+		assert_eq!(BridgeCommon::rtoken_total_supply(sym), 1000);
+
+		// migrated back, minted instead of unlocked from the bridge account
+		let proposal = make_transfer_rtoken_proposal(RELAYER_A, 100);
+		assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, rid, Box::new(proposal.clone())));
+		assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 1, src_id, rid, Box::new(proposal.clone())));
+		assert_eq!(RBalances::free_balance(&RELAYER_A, sym), 100);
+		assert_eq!(BridgeCommon::migrated_supply(sym, src_id), 500);
+		assert_eq!(BridgeCommon::rtoken_total_supply(sym), 1000);
+		assert_eq!(BridgeCommon::current_proposal_source(), None);
+
+		// beyond the migrated supply it has to be unlocked, and nothing was locked
+		let proposal = make_transfer_rtoken_proposal(RELAYER_A, 501);
+		assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 2, src_id, rid, Box::new(proposal.clone())));
+		assert_err!(
+			BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 2, src_id, rid, Box::new(proposal.clone())),
+			bridge::Error::<Test>::InsufficientReserve,
+		);
+		assert_eq!(BridgeCommon::migrated_supply(sym, src_id), 500);
+
+		assert_ok!(BridgeCommon::finalize_migration(Origin::root(), sym));
+		assert_noop!(BridgeCommon::finalize_migration(Origin::root(), sym), bridge::Error::<Test>::MigrationAlreadyFinalized);
+		assert_noop!(RBalances::mint(&(42 as u64), sym, 1), rtoken_balances::Error::<Test>::MintDisabled);
+
+		// holders can still migrate, but nothing comes back
+		assert_ok!(BridgeSwap::transfer_rtoken(Origin::signed(42), sym, 300, eth_address, src_id));
+		assert_eq!(BridgeCommon::total_migrated(sym), 800);
+		assert_eq!(BridgeCommon::rtoken_total_supply(sym), 1000);
+		let proposal = make_transfer_rtoken_proposal(RELAYER_A, 10);
+		assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 3, src_id, rid, Box::new(proposal.clone())));
+		assert_err!(
+			BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 3, src_id, rid, Box::new(proposal.clone())),
+			bridge::Error::<Test>::MigrationAlreadyFinalized,
+		);
+	})
+}
+
+#[test]
+fn rtoken_locked_before_migration_should_be_unlocked() {
+	new_test_ext().execute_with(|| {
+		let rid: ResourceId = [1; 32];
+		let sym: RSymbol = RSymbol::RFIS;
+		let src_id = ETH_CHAIN_ID;
+		let eth_address = vec![11, 21, 31, 43, 88, 120, 43, 54, 55, 99, 54, 98, 23, 24, 54, 64, 29, 94, 26, 75];
+		let bridger = BridgeCommon::account_id();
+		assert_ok!(BridgeCommon::map_resource_and_rsymbol(Origin::root(), rid, sym));
+		assert_ok!(BridgeCommon::whitelist_chain(Origin::root(), src_id));
+		assert_ok!(BridgeCommon::add_resource(Origin::root(), rid, b"BridgeSwap.transfer_rtoken_back".to_vec()));
+		assert_ok!(BridgeCommon::set_fees_recipient_account(Origin::root(), 2));
+		assert_ok!(BridgeCommon::set_proxy_accounts(Origin::root(), 40));
+		assert_ok!(BridgeCommon::set_chain_fees(Origin::signed(40), src_id, 0));
+		assert_ok!(BridgeRelayers::set_threshold(Origin::root(), src_id, TEST_THRESHOLD));
+		assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_A));
+		assert_ok!(BridgeRelayers::add_relayer(Origin::root(), src_id, RELAYER_B));
+
+		// locked before the target is set, then migrated
+		assert_ok!(RBalances::mint(&(42 as u64), sym, 1000));
+		assert_ok!(BridgeSwap::transfer_rtoken(Origin::signed(42), sym, 300, eth_address.clone(), src_id));
+		assert_ok!(BridgeCommon::set_migrate_target(Origin::root(), sym, src_id));
+		let proposal = make_transfer_rtoken_proposal(RELAYER_A, 100);
+		assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 1, src_id, rid, Box::new(proposal.clone())));
+		assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 1, src_id, rid, Box::new(proposal.clone())));
+		assert_eq!(RBalances::free_balance(&RELAYER_A, sym), 100);
+		assert_eq!(RBalances::free_balance(&bridger, sym), 200);
+		assert_eq!(BridgeCommon::resource_reserves(rid).locked, 200);
+
+		assert_ok!(BridgeSwap::transfer_rtoken(Origin::signed(42), sym, 400, eth_address, src_id));
+		assert_eq!(BridgeCommon::migrated_supply(sym, src_id), 400);
+		assert_eq!(BridgeCommon::rtoken_total_supply(sym), 1000);
+
+		// the migrated supply is minted back first, the rest unlocked
+		let proposal = make_transfer_rtoken_proposal(RELAYER_B, 450);
+		assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_A), 2, src_id, rid, Box::new(proposal.clone())));
+		assert_ok!(BridgeCommon::acknowledge_proposal(Origin::signed(RELAYER_B), 2, src_id, rid, Box::new(proposal.clone())));
+		assert_eq!(RBalances::free_balance(&RELAYER_B, sym), 450);
+		assert_eq!(BridgeCommon::migrated_supply(sym, src_id), 0);
+		assert_eq!(BridgeCommon::resource_reserves(rid).locked, 150);
+		assert_eq!(RBalances::free_balance(&bridger, sym), 150);
+		assert_eq!(BridgeCommon::rtoken_total_supply(sym), 1000);
+	})
+}
@@ -13,23 +13,32 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use codec::Encode;
+use codec::{Decode, Encode};
 use frame_support::{
 	decl_error, decl_event, decl_module, decl_storage,
 	dispatch::DispatchResult,
 	ensure,
-	traits::{Currency, ExistenceRequirement::KeepAlive},
+	storage::migration::{put_storage_value, take_storage_value, StorageIterator},
+	traits::{Currency, ExistenceRequirement::KeepAlive, Get},
+	weights::Weight,
 };
 use frame_system::{self as system, ensure_root, ensure_signed};
-use general_signature::{ethereum_verify, to_ascii_hex, SigVerifyResult};
-use node_primitives::{Balance, BlockNumber, RSymbol};
+use general_signature::{eth_recover, ed25519_verify, substrate_verify, to_ascii_hex, SigVerifyResult};
+use node_primitives::{Balance, BlockNumber, ChainType, RSymbol};
 use sp_arithmetic::helpers_128bit::multiply_by_rational;
-use sp_runtime::traits::SaturatedConversion;
+use sp_core::H256;
+use sp_io::hashing::keccak_256;
+use sp_runtime::{traits::SaturatedConversion, RuntimeDebug};
 use sp_std::prelude::*;
 
 pub mod models;
 pub use models::*;
 
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
 /// Configuration trait.
 pub trait Trait: system::Trait {
 	/// The overarching event type.
@@ -39,33 +48,49 @@ pub trait Trait: system::Trait {
 }
 
 pub const RATEBASE: u128 = 1_000_000_000_000;
+/// max depth of a claim proof, enough for 2^32 leaves
+pub const MAX_PROOF_DEPTH: usize = 32;
+
+/// Storage version of the pallet
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Releases {
+	/// rtoken claims keyed by account, reth claims kept apart keyed by eth address
+	V1_0_0,
+	/// all claims keyed by claimant, reth acts merged into acts of RETH
+	V2_0_0,
+}
+
+impl Default for Releases {
+	fn default() -> Self {
+		Releases::V1_0_0
+	}
+}
+
 // This pallet's storage items.
 decl_storage! {
 	trait Store for Module<T: Trait> as RClaim {
-		/// claim infos (account, rsymbol, cycle, mint index)
-		pub ClaimInfos get(fn claim_infos): map hasher(blake2_128_concat) (T::AccountId, RSymbol, u32, u64) => Option<ClaimInfo>;
-		pub REthClaimInfos get(fn reth_claim_infos): map hasher(blake2_128_concat) (Vec<u8>, u32, u64) => Option<ClaimInfo>;
-		/// Proxy accounts for setting fees
-		pub REthRewarder get(fn reth_rewarder): Option<T::AccountId>;
+		/// claim infos (claimant, rsymbol, cycle, mint index)
+		pub ClaimInfos get(fn claim_infos): map hasher(blake2_128_concat) (Claimant<T::AccountId>, RSymbol, u32, u64) => Option<ClaimInfo>;
+		/// rewarder of rsymbol, pushes claim infos of pubkeys and commits claim roots
+		pub Rewarders get(fn rewarders): map hasher(blake2_128_concat) RSymbol => Option<T::AccountId>;
 		/// MintRewardActs
 		pub Acts get(fn acts): map hasher(blake2_128_concat) (RSymbol, u32) => Option<MintRewardAct<BlockNumber, Balance>>;
-		pub REthActs get(fn reth_acts): map hasher(blake2_128_concat) u32 => Option<MintRewardAct<BlockNumber, Balance>>;
 		/// fund address
 		pub FundAddress get(fn fund_address): Option<T::AccountId>;
 		/// act latest cycle
 		pub ActLatestCycle get(fn act_latest_cycle): map hasher(blake2_128_concat) RSymbol => u32;
-		pub REthActLatestCycle get(fn reth_act_latest_cycle): u32;
 		/// act current cycle
 		pub ActCurrentCycle get(fn act_current_cycle): map hasher(blake2_128_concat) RSymbol => u32;
-		pub REthActCurrentCycle get(fn reth_act_current_cycle): u32;
-		/// acts that user mint rtoken
-		pub UserActs get(fn user_acts): map hasher(blake2_128_concat) (T::AccountId, RSymbol) => Option<Vec<u32>>;
-		pub UserREthActs get(fn user_reth_acts): map hasher(blake2_128_concat) Vec<u8> => Option<Vec<u32>>;
-		/// user mint count (account, rsymbol, cycle)
-		pub UserMintsCount get(fn user_mints_count): map hasher(blake2_128_concat) (T::AccountId, RSymbol, u32) => u64;
-		pub UserREthMintsCount get(fn user_reth_mints_count): map hasher(blake2_128_concat) (Vec<u8>, u32) => u64;
+		/// acts that claimant mint rtoken
+		pub UserActs get(fn user_acts): map hasher(blake2_128_concat) (Claimant<T::AccountId>, RSymbol) => Option<Vec<u32>>;
+		/// claimant mint count (claimant, rsymbol, cycle)
+		pub UserMintsCount get(fn user_mints_count): map hasher(blake2_128_concat) (Claimant<T::AccountId>, RSymbol, u32) => u64;
 		/// user mint tx hash
 		pub MintTxHashExist get(fn mint_tx_hash_exist): map hasher(blake2_128_concat) Vec<u8> => bool = false;
+		/// committed claim root (rsymbol, cycle)
+		pub ClaimRoots get(fn claim_roots): map hasher(blake2_128_concat) (RSymbol, u32) => Option<ClaimRoot>;
+		/// storage version
+		StorageVersion get(fn storage_version) build(|_| Releases::V2_0_0): Releases;
 	}
 }
 
@@ -77,8 +102,10 @@ decl_event!(
 	{
 		/// Someone claimed some fis from mint rtoken
 		RTokenClaimed(AccountId, RSymbol, u128),
-		/// Someone claimed some fis from mint reth
-		REthClaimed(AccountId, u128),
+		/// claim root committed: symbol, cycle, root, reward reserved for its leaves
+		ClaimRootCommitted(RSymbol, u32, H256, u128),
+		/// mint proven against claim root: account, symbol, cycle, tx hash, total reward
+		MintProven(AccountId, RSymbol, u32, Vec<u8>, u128),
 	}
 );
 
@@ -91,20 +118,28 @@ decl_error! {
 		HasNoAct,
 		/// zero value
 		ValueZero,
-		/// invalid reth rewarder
-		InvalidREthRewarder,
+		/// invalid rewarder
+		InvalidRewarder,
 		/// Insufficient fis
 		InsufficientFis,
 		/// no fund address
 		NoFundAddress,
 		/// invalid Pubkey
 		InvalidPubkey,
-		/// eth signature failed
-		EthSigsFailed,
+		/// signature failed
+		SigsFailed,
+		/// claimant is another account
+		InvalidClaimant,
 		/// pubkey and mint value numnber not equal
 		PubkeyAndValueNumberErr,
 		/// mint tx hash exist
 		MintTxHashExistErr,
+		/// claim root already committed
+		ClaimRootExist,
+		/// has no claim root
+		HasNoClaimRoot,
+		/// invalid merkle proof
+		InvalidProof,
 	}
 }
 
@@ -118,11 +153,21 @@ decl_module! {
 		// Initializing events
 		fn deposit_event() = default;
 
-		/// Set reth rewarder.
+		fn on_runtime_upgrade() -> Weight {
+			if Self::storage_version() == Releases::V1_0_0 {
+				let weight = Self::migrate_claims();
+				StorageVersion::put(Releases::V2_0_0);
+				weight
+			} else {
+				0
+			}
+		}
+
+		/// Set rewarder of rsymbol.
 		#[weight = 100_000]
-		pub fn set_reth_rewarder(origin, account: T::AccountId) -> DispatchResult {
+		pub fn set_rewarder(origin, symbol: RSymbol, account: T::AccountId) -> DispatchResult {
 			ensure_root(origin)?;
-			<REthRewarder<T>>::put(account);
+			<Rewarders<T>>::insert(symbol, account);
 			Ok(())
 		}
 
@@ -225,262 +270,224 @@ decl_module! {
 			Ok(())
 		}
 
-		#[weight = 100_000]
-		pub fn add_reth_reward_act(
-			origin,
-			begin: BlockNumber,
-			end: BlockNumber,
-			total_reward: Balance,
-			user_limit: Balance,
-			locked_blocks: u32,
-			reward_rate: u128,
-		) -> DispatchResult {
-			ensure_root(origin)?;
-
-			ensure!(begin > 0, "Begin block number must be greater than 0");
-			ensure!(end > begin, "End block number must be greater than begin block nubmer");
-			ensure!(total_reward > 0, "total amount must be greater than 0");
-			ensure!(total_reward > user_limit, "total amount must be greater than User limit");
-			ensure!(locked_blocks > 0, "locked blocks must be greater than 0");
-			ensure!(reward_rate > 0, "reward rate must be greater than 0");
-
-			let current_block_num = system::Module::<T>::block_number().saturated_into::<u32>();
-			ensure!(end > current_block_num, "End block number must be greater than current block nubmer");
-
-			let cycle = Self::reth_act_latest_cycle();
-			if cycle > 0 {
-				let last_act = Self::reth_acts(cycle).ok_or(Error::<T>::HasNoAct)?;
-				ensure!(begin > last_act.end, "Begin block number must be greater than end block nubmer of the last act");
-			}
-			let new_cycle = cycle + 1;
-			<REthActLatestCycle>::put(new_cycle);
-
-			let act = MintRewardAct {
-				begin: begin,
-				end: end,
-				cycle: new_cycle,
-				reward_rate: reward_rate,
-				total_reward: total_reward,
-				left_amount: total_reward,
-				user_limit: user_limit,
-				locked_blocks: locked_blocks,
-				total_rtoken_amount: 0,
-				total_native_token_amount: 0,
-			};
-			<REthActs>::insert(new_cycle, act);
-
-			Ok(())
-		}
-
-		#[weight = 100_000]
-		pub fn update_reth_reward_act(
+		/// Make a claim, a pubkey claimant is proven by its signature over the ascii hex of the encoded account
+		#[weight = 10_000_000_000]
+		pub fn claim_reward(
 			origin,
+			claimant: Claimant<T::AccountId>,
+			signature: Vec<u8>,
+			symbol: RSymbol,
 			cycle: u32,
-			begin: BlockNumber,
-			end: BlockNumber,
-			total_reward: Balance,
-			user_limit: Balance,
-			locked_blocks: u32,
-			reward_rate: u128,
+			index: u64,
 		) -> DispatchResult {
-			ensure_root(origin)?;
-
-			ensure!(begin > 0, "Begin block number must be greater than 0");
-			ensure!(end > begin, "End block number must be greater than begin block nubmer");
-			ensure!(total_reward > 0, "total amount must be greater than 0");
-			ensure!(total_reward > user_limit, "total amount must be greater than User limit");
-			ensure!(locked_blocks > 0, "locked blocks must be greater than 0");
-			ensure!(reward_rate > 0, "reward rate must be greater than 0");
-			let mut act = Self::reth_acts(cycle).ok_or(Error::<T>::HasNoAct)?;
-
-			let current_block_num = system::Module::<T>::block_number().saturated_into::<u32>();
-			ensure!(end > current_block_num, "End block number must be greater than current block nubmer");
-
-			if total_reward > act.total_reward {
-				act.left_amount = act.left_amount.saturating_add(total_reward.saturating_sub(act.total_reward));
-			} else {
-				act.left_amount = act.left_amount.saturating_sub(act.total_reward.saturating_sub(total_reward));
-			}
-
-			act.begin = begin;
-			act.end = end;
-			act.cycle = cycle;
-			act.reward_rate = reward_rate;
-			act.total_reward = total_reward;
-			act.user_limit = user_limit;
-			act.locked_blocks = locked_blocks;
-
-			<REthActs>::insert(cycle, act);
-			Ok(())
-		}
-
-
-		/// Make a rtoken claim
-		#[weight = 10_000_000_000]
-		pub fn claim_rtoken_reward(origin, symbol: RSymbol, cycle: u32, index: u64) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			let mut claim_info = Self::claim_infos((&who, symbol, cycle, index)).ok_or(Error::<T>::HasNoClaimInfo)?;
+			Self::ensure_claimant(&who, &claimant, symbol, &signature)?;
+
+			let mut claim_info = Self::claim_infos((&claimant, symbol, cycle, index)).ok_or(Error::<T>::HasNoClaimInfo)?;
 			let act = Self::acts((symbol, cycle)).ok_or(Error::<T>::HasNoAct)?;
 			let fund_addr = Self::fund_address().ok_or(Error::<T>::NoFundAddress)?;
 			let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
-			let final_block = claim_info.mint_block.saturating_add(act.locked_blocks);
 
 			let left_claim_amount = claim_info.total_reward.saturating_sub(claim_info.total_claimed);
 			ensure!(left_claim_amount > 0, Error::<T>::ValueZero);
-			let should_claim_amount: u128;
-			if now_block < final_block {
-			   let du_blocks = now_block.saturating_sub(claim_info.latest_claimed_block) as u128;
-			   let locked_du_blocks = final_block.saturating_sub(claim_info.latest_claimed_block) as u128;
-			   should_claim_amount = multiply_by_rational(left_claim_amount, du_blocks, locked_du_blocks).unwrap_or(u128::MIN) as u128;
-			} else {
-			   should_claim_amount = left_claim_amount;
-			}
+			let should_claim_amount = Self::cal_should_claim_amount(&claim_info, act.locked_blocks, now_block);
 			ensure!(should_claim_amount > 0, Error::<T>::ValueZero);
 
 			//update state
 			T::Currency::transfer(&fund_addr, &who, should_claim_amount.saturated_into(), KeepAlive)?;
 			claim_info.total_claimed = claim_info.total_claimed.saturating_add(should_claim_amount);
 			claim_info.latest_claimed_block = now_block;
-			<ClaimInfos<T>>::insert((who.clone(), symbol, cycle, index), claim_info);
+			<ClaimInfos<T>>::insert((&claimant, symbol, cycle, index), claim_info);
 
 			Self::deposit_event(RawEvent::RTokenClaimed(who.clone(), symbol, should_claim_amount));
 			Ok(())
 		}
 
-		/// Make a reth claim
-		#[weight = 10_000_000_000]
-		pub fn claim_reth_reward(origin, pubkey: Vec<u8>, sigs: Vec<u8>, cycle: u32, index: u64) -> DispatchResult {
-			let who = ensure_signed(origin)?;
-			let use_who = who.using_encoded(to_ascii_hex);
-			ensure!(pubkey.len() == 20, Error::<T>::InvalidPubkey);
-			ensure!(ethereum_verify(&pubkey, &sigs, &use_who) == SigVerifyResult::Pass, Error::<T>::EthSigsFailed);
-
-			let mut claim_info = Self::reth_claim_infos((pubkey.clone(), cycle, index)).ok_or(Error::<T>::HasNoClaimInfo)?;
-			let act = Self::reth_acts(cycle).ok_or(Error::<T>::HasNoAct)?;
-			let fund_addr = Self::fund_address().ok_or(Error::<T>::NoFundAddress)?;
-			let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
-			let final_block = claim_info.mint_block.saturating_add(act.locked_blocks);
-
-			let left_claim_amount = claim_info.total_reward.saturating_sub(claim_info.total_claimed);
-			ensure!(left_claim_amount > 0, Error::<T>::ValueZero);
-			let should_claim_amount: u128;
-			if now_block < final_block {
-			   let du_blocks = now_block.saturating_sub(claim_info.latest_claimed_block) as u128;
-			   let locked_du_blocks = final_block.saturating_sub(claim_info.latest_claimed_block) as u128;
-			   should_claim_amount = multiply_by_rational(left_claim_amount, du_blocks, locked_du_blocks).unwrap_or(u128::MIN) as u128;
-			} else {
-			   should_claim_amount = left_claim_amount;
-			}
-			ensure!(should_claim_amount > 0, Error::<T>::ValueZero);
-
-			//update state
-			T::Currency::transfer(&fund_addr, &who, should_claim_amount.saturated_into(), KeepAlive)?;
-			claim_info.total_claimed = claim_info.total_claimed.saturating_add(should_claim_amount);
-			claim_info.latest_claimed_block = now_block;
-			<REthClaimInfos>::insert((pubkey.clone(), cycle, index), claim_info);
-
-			Self::deposit_event(RawEvent::REthClaimed(who.clone(), should_claim_amount));
-			Ok(())
-		}
-
+		/// Push claim infos of pubkeys minting rsymbol, callable by the rewarder of rsymbol
 		#[weight = 100_000]
-		pub fn update_reth_claim_info(origin, tx_hashs: Vec<Vec<u8>>, pubkeys: Vec<Vec<u8>>, mint_values: Vec<u128>, native_token_values: Vec<u128>) -> DispatchResult {
+		pub fn update_claim_infos(
+			origin,
+			symbol: RSymbol,
+			tx_hashs: Vec<Vec<u8>>,
+			pubkeys: Vec<Vec<u8>>,
+			mint_values: Vec<u128>,
+			native_token_values: Vec<u128>,
+		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			ensure!(Self::is_rewarder(&who), Error::<T>::InvalidREthRewarder);
+			ensure!(Self::is_rewarder(symbol, &who), Error::<T>::InvalidRewarder);
 			ensure!(tx_hashs.len() == pubkeys.len() && pubkeys.len() == mint_values.len() && pubkeys.len() == native_token_values.len() && pubkeys.len() < 200, Error::<T>::PubkeyAndValueNumberErr);
-
-			let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
-			let mut cycle = Self::reth_act_current_cycle();
-			if cycle == 0 {
-				Self::update_reth_act_current_cycle(now_block);
-				cycle = Self::reth_act_current_cycle();
-				if cycle == 0 {
-					return Ok(());
-				}
-			}
-			let act_op = Self::reth_acts(cycle);
-			if act_op.is_none() {
-				return Ok(());
-			}
-			let mut act = act_op.unwrap();
-			if act.end < now_block {
-				Self::update_reth_act_current_cycle(now_block);
-				cycle = Self::reth_act_current_cycle();
-				let act_op = Self::reth_acts(cycle);
-				if act_op.is_none() {
-					return Ok(());
-				}
-				act = act_op.unwrap();
-			}
-			if act.begin > now_block || act.end < now_block {
-				return Ok(());
-			}
-			if act.left_amount == 0 {
-				return Ok(());
-			}
 			for j in 0..pubkeys.len() {
 				ensure!(!Self::mint_tx_hash_exist(tx_hashs[j].clone()), Error::<T>::MintTxHashExistErr);
-				ensure!(pubkeys[j].len() == 20, Error::<T>::InvalidPubkey);
+				ensure!(Self::check_pubkey(symbol, &pubkeys[j]), Error::<T>::InvalidPubkey);
 				ensure!(mint_values[j] > 0, Error::<T>::ValueZero);
 				ensure!(native_token_values[j] > 0, Error::<T>::ValueZero);
 			}
+
+			let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
+			let (cycle, mut act) = match Self::current_act(symbol, now_block) {
+				Some(current) => current,
+				None => return Ok(()),
+			};
 			//update state
 			for k in 0..pubkeys.len() {
 				if act.left_amount == 0 {
 					break;
 				}
-				let mint_value = mint_values[k];
-				let native_token_value = native_token_values[k];
-				let pubkey = &pubkeys[k];
-				let mut should_reward_amount = multiply_by_rational(native_token_value, act.reward_rate, RATEBASE)
-				.unwrap_or(u128::MIN) as u128;
-				if should_reward_amount > act.left_amount {
-					should_reward_amount = act.left_amount;
-				}
-				if act.user_limit > 0 && should_reward_amount > act.user_limit {
-					should_reward_amount = act.user_limit;
-				}
-				act.left_amount = act.left_amount.saturating_sub(should_reward_amount);
-				act.total_rtoken_amount = act.total_rtoken_amount.saturating_add(mint_value);
-				act.total_native_token_amount = act.total_native_token_amount.saturating_add(native_token_value);
-
-				let claim_info = ClaimInfo {
-					mint_amount: mint_value,
-					native_token_amount: native_token_value,
-					total_reward: should_reward_amount,
-					total_claimed: 0,
-					latest_claimed_block: now_block,
-					mint_block: now_block,
-				};
-				let mints_count = Self::user_reth_mints_count((pubkey.clone(), cycle));
-				//update state
-				<REthClaimInfos>::insert((pubkey.clone(), cycle, mints_count), claim_info);
-				let mut acts = Self::user_reth_acts(pubkey.clone()).unwrap_or(vec![]);
-				if !acts.contains(&cycle) {
-					acts.push(cycle);
-					<UserREthActs>::insert(pubkey.clone(), acts);
-				}
-				<UserREthMintsCount>::insert((pubkey.clone(), cycle), mints_count + 1);
-				
+				let claim_info = Self::new_claim_info(&mut act, mint_values[k], native_token_values[k], now_block);
+				Self::insert_claim_info(&Claimant::Pubkey(pubkeys[k].clone()), symbol, cycle, claim_info);
 				<MintTxHashExist>::insert(tx_hashs[k].clone(), true);
 			}
-			<REthActs>::insert(cycle, act);
+			<Acts>::insert((symbol, cycle), act);
 			Ok(())
 		}
 
+		/// Commit the claim root of an act, leaves are mints of the cycle with their rewards.
+		/// The sum of the leaf rewards is reserved from the act, so every leaf stays claimable.
+		/// Callable by root or the rewarder of rsymbol.
+		#[weight = 100_000]
+		pub fn commit_claim_root(origin, symbol: RSymbol, cycle: u32, root: H256, total_reward: Balance) -> DispatchResult {
+			if ensure_root(origin.clone()).is_err() {
+				let who = ensure_signed(origin)?;
+				ensure!(Self::is_rewarder(symbol, &who), Error::<T>::InvalidRewarder);
+			}
+			ensure!(total_reward > 0, Error::<T>::ValueZero);
+			let mut act = Self::acts((symbol, cycle)).ok_or(Error::<T>::HasNoAct)?;
+			ensure!(Self::claim_roots((symbol, cycle)).is_none(), Error::<T>::ClaimRootExist);
+			ensure!(act.left_amount >= total_reward, Error::<T>::InsufficientFis);
+
+			let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
+			act.left_amount = act.left_amount - total_reward;
+			<Acts>::insert((symbol, cycle), act);
+			<ClaimRoots>::insert((symbol, cycle), ClaimRoot {
+				root,
+				commit_block: now_block,
+				total_reward,
+				left_amount: total_reward,
+			});
+
+			Self::deposit_event(RawEvent::ClaimRootCommitted(symbol, cycle, root, total_reward));
+			Ok(())
+		}
+
+		/// Prove a mint of claimant and its reward against the claim root of the cycle, the reward is paid from
+		/// the reserve of the root, vests from the commit block and then it is claimed through claim_reward.
+		#[weight = 10_000_000_000]
+		pub fn claim_with_proof(
+			origin,
+			claimant: Claimant<T::AccountId>,
+			signature: Vec<u8>,
+			symbol: RSymbol,
+			cycle: u32,
+			tx_hash: Vec<u8>,
+			mint_value: u128,
+			native_token_value: u128,
+			reward: Balance,
+			proof: Vec<H256>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_claimant(&who, &claimant, symbol, &signature)?;
+			ensure!(mint_value > 0, Error::<T>::ValueZero);
+			ensure!(native_token_value > 0, Error::<T>::ValueZero);
+			ensure!(reward > 0, Error::<T>::ValueZero);
+			ensure!(proof.len() <= MAX_PROOF_DEPTH, Error::<T>::InvalidProof);
+			ensure!(!Self::mint_tx_hash_exist(tx_hash.clone()), Error::<T>::MintTxHashExistErr);
+			let mut claim_root = Self::claim_roots((symbol, cycle)).ok_or(Error::<T>::HasNoClaimRoot)?;
+			let leaf = Self::claim_leaf(&claimant, symbol, cycle, &tx_hash, mint_value, native_token_value, reward);
+			ensure!(Self::verify_proof(claim_root.root, leaf, &proof), Error::<T>::InvalidProof);
+
+			let mut act = Self::acts((symbol, cycle)).ok_or(Error::<T>::HasNoAct)?;
+			let fund_addr = Self::fund_address().ok_or(Error::<T>::NoFundAddress)?;
+			let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
+			// only a root whose leaf rewards sum above its committed total runs out here
+			ensure!(claim_root.left_amount >= reward, Error::<T>::InsufficientFis);
+			claim_root.left_amount = claim_root.left_amount - reward;
+
+			let mut claim_info = Self::mint_claim_info(&mut act, mint_value, native_token_value, reward, claim_root.commit_block);
+			let should_claim_amount = Self::cal_should_claim_amount(&claim_info, act.locked_blocks, now_block);
+			if should_claim_amount > 0 {
+				T::Currency::transfer(&fund_addr, &who, should_claim_amount.saturated_into(), KeepAlive)?;
+				claim_info.total_claimed = should_claim_amount;
+				claim_info.latest_claimed_block = now_block;
+			}
 
+			//update state
+			let total_reward = claim_info.total_reward;
+			Self::insert_claim_info(&claimant, symbol, cycle, claim_info);
+			<Acts>::insert((symbol, cycle), act);
+			<ClaimRoots>::insert((symbol, cycle), claim_root);
+			<MintTxHashExist>::insert(tx_hash.clone(), true);
+
+			Self::deposit_event(RawEvent::MintProven(who.clone(), symbol, cycle, tx_hash, total_reward));
+			if should_claim_amount > 0 {
+				Self::deposit_event(RawEvent::RTokenClaimed(who.clone(), symbol, should_claim_amount));
+			}
+			Ok(())
+		}
 	}
 }
 
 impl<T: Trait> Module<T> {
-	/// Checks if who is a rewarder
-	pub fn is_rewarder(who: &T::AccountId) -> bool {
-		let rewarder_op = Self::reth_rewarder();
-		if rewarder_op.is_none() {
-			return false;
+	/// Checks if who is the rewarder of symbol
+	pub fn is_rewarder(symbol: RSymbol, who: &T::AccountId) -> bool {
+		Self::rewarders(symbol).as_ref() == Some(who)
+	}
+
+	/// Checks that who may claim for claimant: its own account, or a pubkey that signed who
+	pub fn ensure_claimant(
+		who: &T::AccountId,
+		claimant: &Claimant<T::AccountId>,
+		symbol: RSymbol,
+		signature: &Vec<u8>,
+	) -> DispatchResult {
+		match claimant {
+			Claimant::Account(account) => {
+				ensure!(account == who, Error::<T>::InvalidClaimant);
+			},
+			Claimant::Pubkey(pubkey) => {
+				ensure!(Self::check_pubkey(symbol, pubkey), Error::<T>::InvalidPubkey);
+				let use_who = who.using_encoded(to_ascii_hex);
+				ensure!(Self::verify(symbol.chain_type(), pubkey, signature, &use_who), Error::<T>::SigsFailed);
+			},
+		}
+		Ok(())
+	}
+
+	/// Checks pubkey of a foreign claimant, tendermint pubkeys can't be proven so they are not supported
+	pub fn check_pubkey(symbol: RSymbol, pubkey: &Vec<u8>) -> bool {
+		match symbol.chain_type() {
+			ChainType::Ethereum => pubkey.len() == 20,
+			ChainType::Substrate | ChainType::Solana => pubkey.len() == 32,
+			ChainType::Tendermint => false,
 		}
-		return &rewarder_op.unwrap() == who;
 	}
+
+	/// Verifies signature of pubkey over message by chain type
+	pub fn verify(chain_type: ChainType, pubkey: &Vec<u8>, signature: &Vec<u8>, message: &Vec<u8>) -> bool {
+		match chain_type {
+			ChainType::Substrate => {
+				if signature.len() != 64 {
+					return false;
+				}
+				let mut pre = b"<Bytes>".to_vec();
+				pre.extend_from_slice(message);
+				pre.extend_from_slice(b"</Bytes>");
+				substrate_verify(pubkey, signature, &pre) == SigVerifyResult::Pass
+			},
+			ChainType::Tendermint => false,
+			ChainType::Solana => {
+				signature.len() == 64 && ed25519_verify(pubkey, signature, message) == SigVerifyResult::Pass
+			},
+			ChainType::Ethereum => {
+				if signature.len() != 65 {
+					return false;
+				}
+				let mut sig = [0u8; 65];
+				sig.copy_from_slice(signature);
+				eth_recover(&sig, message).map(|signer| signer.to_vec()) == Some(pubkey.clone())
+			},
+		}
+	}
+
 	/// update user claim info when user mint rtoken
 	pub fn update_claim_info(
 		who: &T::AccountId,
@@ -488,37 +495,93 @@ impl<T: Trait> Module<T> {
 		mint_value: u128,
 		native_token_value: u128,
 	) {
-		let mut cycle = Self::act_current_cycle(symbol);
 		let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
+		let (cycle, mut act) = match Self::current_act(symbol, now_block) {
+			Some(current) => current,
+			None => return,
+		};
+		let claim_info = Self::new_claim_info(&mut act, mint_value, native_token_value, now_block);
+
+		//update state
+		Self::insert_claim_info(&Claimant::Account(who.clone()), symbol, cycle, claim_info);
+		<Acts>::insert((symbol, cycle), act);
+	}
+
+	/// current act of symbol, if it is running and has reward left
+	fn current_act(symbol: RSymbol, now_block: BlockNumber) -> Option<(u32, MintRewardAct<BlockNumber, Balance>)> {
+		let mut cycle = Self::act_current_cycle(symbol);
 		if cycle == 0 {
 			Self::update_act_current_cycle(now_block, symbol);
 			cycle = Self::act_current_cycle(symbol);
 			if cycle == 0 {
-				return;
+				return None;
 			}
 		}
-		let act_op = Self::acts((symbol, cycle));
-		if act_op.is_none() {
-			return;
-		}
-		let mut act = act_op.unwrap();
+		let mut act = Self::acts((symbol, cycle))?;
 		if act.end < now_block {
 			Self::update_act_current_cycle(now_block, symbol);
 			cycle = Self::act_current_cycle(symbol);
-			let act_op = Self::acts((symbol, cycle));
-			if act_op.is_none() {
-				return;
-			}
-			act = act_op.unwrap();
+			act = Self::acts((symbol, cycle))?;
 		}
 
 		if act.begin > now_block || act.end < now_block {
-			return;
+			return None;
 		}
 		if act.left_amount == 0 {
-			return;
+			return None;
+		}
+		Some((cycle, act))
+	}
+
+	/// claim info of a mint in act, its reward is taken from act
+	fn new_claim_info(
+		act: &mut MintRewardAct<BlockNumber, Balance>,
+		mint_value: u128,
+		native_token_value: u128,
+		mint_block: BlockNumber,
+	) -> ClaimInfo {
+		let should_reward_amount = Self::cal_reward_amount(act, native_token_value);
+		act.left_amount = act.left_amount.saturating_sub(should_reward_amount);
+		Self::mint_claim_info(act, mint_value, native_token_value, should_reward_amount, mint_block)
+	}
+
+	/// claim info of a mint in act with the given reward, the mint is added to the act totals
+	fn mint_claim_info(
+		act: &mut MintRewardAct<BlockNumber, Balance>,
+		mint_value: u128,
+		native_token_value: u128,
+		reward: Balance,
+		mint_block: BlockNumber,
+	) -> ClaimInfo {
+		act.total_rtoken_amount = act.total_rtoken_amount.saturating_add(mint_value);
+		act.total_native_token_amount = act
+			.total_native_token_amount
+			.saturating_add(native_token_value);
+
+		ClaimInfo {
+			mint_amount: mint_value,
+			native_token_amount: native_token_value,
+			total_reward: reward,
+			total_claimed: 0,
+			latest_claimed_block: mint_block,
+			mint_block,
 		}
+	}
 
+	/// stores claim info as the next mint of claimant in cycle
+	fn insert_claim_info(claimant: &Claimant<T::AccountId>, symbol: RSymbol, cycle: u32, claim_info: ClaimInfo) {
+		let mints_count = Self::user_mints_count((claimant, symbol, cycle));
+		<ClaimInfos<T>>::insert((claimant, symbol, cycle, mints_count), claim_info);
+		let mut acts = Self::user_acts((claimant, symbol)).unwrap_or(vec![]);
+		if !acts.contains(&cycle) {
+			acts.push(cycle);
+			<UserActs<T>>::insert((claimant, symbol), acts);
+		}
+		<UserMintsCount<T>>::insert((claimant, symbol, cycle), mints_count + 1);
+	}
+
+	/// reward of a mint in act, capped by left amount and user limit
+	pub fn cal_reward_amount(act: &MintRewardAct<BlockNumber, Balance>, native_token_value: u128) -> Balance {
 		let mut should_reward_amount = multiply_by_rational(
 			native_token_value,
 			act.reward_rate,
@@ -531,32 +594,43 @@ impl<T: Trait> Module<T> {
 		if act.user_limit > 0 && should_reward_amount > act.user_limit {
 			should_reward_amount = act.user_limit;
 		}
+		should_reward_amount
+	}
 
-		act.left_amount = act.left_amount.saturating_sub(should_reward_amount);
-		act.total_rtoken_amount = act.total_rtoken_amount.saturating_add(mint_value);
-		act.total_native_token_amount = act
-			.total_native_token_amount
-			.saturating_add(native_token_value);
+	/// reward vested since latest claimed block, linearly over locked blocks from mint block
+	pub fn cal_should_claim_amount(claim_info: &ClaimInfo, locked_blocks: u32, now_block: BlockNumber) -> Balance {
+		let final_block = claim_info.mint_block.saturating_add(locked_blocks);
+		let left_claim_amount = claim_info.total_reward.saturating_sub(claim_info.total_claimed);
+		if now_block < final_block {
+			let du_blocks = now_block.saturating_sub(claim_info.latest_claimed_block) as u128;
+			let locked_du_blocks = final_block.saturating_sub(claim_info.latest_claimed_block) as u128;
+			multiply_by_rational(left_claim_amount, du_blocks, locked_du_blocks).unwrap_or(u128::MIN) as u128
+		} else {
+			left_claim_amount
+		}
+	}
 
-		let claim_info = ClaimInfo {
-			mint_amount: mint_value,
-			native_token_amount: native_token_value,
-			total_reward: should_reward_amount,
-			total_claimed: 0,
-			latest_claimed_block: now_block,
-			mint_block: now_block,
-		};
-		let mints_count = Self::user_mints_count((who, symbol, cycle));
+	/// leaf of a mint in the claim root
+	pub fn claim_leaf(
+		claimant: &Claimant<T::AccountId>,
+		symbol: RSymbol,
+		cycle: u32,
+		tx_hash: &Vec<u8>,
+		mint_value: u128,
+		native_token_value: u128,
+		reward: Balance,
+	) -> H256 {
+		let encoded = (claimant, symbol, cycle, tx_hash, mint_value, native_token_value, reward).encode();
+		H256::from(keccak_256(&encoded))
+	}
 
-		//update state
-		<ClaimInfos<T>>::insert((who, symbol, cycle, mints_count), claim_info);
-		let mut acts = Self::user_acts((who, symbol)).unwrap_or(vec![]);
-		if !acts.contains(&cycle) {
-			acts.push(cycle);
-			<UserActs<T>>::insert((who, symbol), acts);
-		}
-		<UserMintsCount<T>>::insert((who, symbol, cycle), mints_count + 1);
-		<Acts>::insert((symbol, cycle), act);
+	/// verify leaf against root, pairs are hashed in sorted order
+	pub fn verify_proof(root: H256, leaf: H256, proof: &[H256]) -> bool {
+		let computed = proof.iter().fold(leaf, |node, sibling| {
+			let (left, right) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+			H256::from(keccak_256(&[left.as_bytes(), right.as_bytes()].concat()))
+		});
+		computed == root
 	}
 
 	/// update current act cycle
@@ -588,32 +662,99 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
-	/// update current act cycle
-	fn update_reth_act_current_cycle(now: BlockNumber) {
-		let cycle = Self::reth_act_latest_cycle();
-		if cycle > 0 {
-			let last_current_cycle = Self::reth_act_current_cycle();
-			if cycle == last_current_cycle {
-				return;
+	/// Rekeys account claims by claimant and moves the reth claims of eth addresses into the acts of RETH,
+	/// reth cycles are shifted past the acts of RETH already added.
+	fn migrate_claims() -> Weight {
+		let module: &[u8] = b"RClaim";
+		let mut reads: Weight = 0;
+		let mut writes: Weight = 0;
+
+		// account keyed entries are moved out of the way first, as rekeyed ones are written under
+		// the same prefix and would be iterated again
+		reads += Self::move_storage::<ClaimInfo>(module, b"ClaimInfos", b"LegacyClaimInfos");
+		reads += Self::move_storage::<Vec<u32>>(module, b"UserActs", b"LegacyUserActs");
+		reads += Self::move_storage::<u64>(module, b"UserMintsCount", b"LegacyUserMintsCount");
+		writes += reads * 2;
+		for (key, info) in StorageIterator::<ClaimInfo>::new(module, b"LegacyClaimInfos").drain() {
+			if let Ok((who, symbol, cycle, index)) = <(T::AccountId, RSymbol, u32, u64)>::decode(&mut &key[16..]) {
+				<ClaimInfos<T>>::insert((Claimant::Account(who), symbol, cycle, index), info);
+			}
+			reads += 1;
+			writes += 2;
+		}
+		for (key, acts) in StorageIterator::<Vec<u32>>::new(module, b"LegacyUserActs").drain() {
+			if let Ok((who, symbol)) = <(T::AccountId, RSymbol)>::decode(&mut &key[16..]) {
+				<UserActs<T>>::insert((Claimant::Account(who), symbol), acts);
+			}
+			reads += 1;
+			writes += 2;
+		}
+		for (key, count) in StorageIterator::<u64>::new(module, b"LegacyUserMintsCount").drain() {
+			if let Ok((who, symbol, cycle)) = <(T::AccountId, RSymbol, u32)>::decode(&mut &key[16..]) {
+				<UserMintsCount<T>>::insert((Claimant::Account(who), symbol, cycle), count);
 			}
+			reads += 1;
+			writes += 2;
+		}
 
-			let begin = last_current_cycle + 1;
-			for i in begin..(cycle + 1) {
-				let act_op = Self::reth_acts(i);
-				if act_op.is_none() {
-					continue;
-				}
-				let act = act_op.unwrap();
-				if now < act.begin {
-					break;
-				}
-				if act.begin <= now && act.end >= now {
-					if i != last_current_cycle {
-						<REthActCurrentCycle>::put(i);
-					}
-					break;
-				}
+		let offset = Self::act_latest_cycle(RSymbol::RETH);
+		for (key, mut act) in StorageIterator::<MintRewardAct<BlockNumber, Balance>>::new(module, b"REthActs").drain() {
+			if let Ok(cycle) = u32::decode(&mut &key[16..]) {
+				act.cycle = cycle + offset;
+				<Acts>::insert((RSymbol::RETH, cycle + offset), act);
+			}
+			reads += 1;
+			writes += 2;
+		}
+		if let Some(latest) = take_storage_value::<u32>(module, b"REthActLatestCycle", &[]) {
+			if latest > 0 {
+				<ActLatestCycle>::insert(RSymbol::RETH, latest + offset);
+			}
+		}
+		if let Some(current) = take_storage_value::<u32>(module, b"REthActCurrentCycle", &[]) {
+			if current > 0 {
+				<ActCurrentCycle>::insert(RSymbol::RETH, current + offset);
+			}
+		}
+		if let Some(rewarder) = take_storage_value::<T::AccountId>(module, b"REthRewarder", &[]) {
+			<Rewarders<T>>::insert(RSymbol::RETH, rewarder);
+		}
+		reads += 3;
+		writes += 6;
+
+		for (key, info) in StorageIterator::<ClaimInfo>::new(module, b"REthClaimInfos").drain() {
+			if let Ok((pubkey, cycle, index)) = <(Vec<u8>, u32, u64)>::decode(&mut &key[16..]) {
+				<ClaimInfos<T>>::insert((Claimant::Pubkey(pubkey), RSymbol::RETH, cycle + offset, index), info);
 			}
+			reads += 1;
+			writes += 2;
+		}
+		for (key, acts) in StorageIterator::<Vec<u32>>::new(module, b"UserREthActs").drain() {
+			if let Ok(pubkey) = <Vec<u8>>::decode(&mut &key[16..]) {
+				let acts = acts.into_iter().map(|cycle| cycle + offset).collect::<Vec<u32>>();
+				<UserActs<T>>::insert((Claimant::Pubkey(pubkey), RSymbol::RETH), acts);
+			}
+			reads += 1;
+			writes += 2;
+		}
+		for (key, count) in StorageIterator::<u64>::new(module, b"UserREthMintsCount").drain() {
+			if let Ok((pubkey, cycle)) = <(Vec<u8>, u32)>::decode(&mut &key[16..]) {
+				<UserMintsCount<T>>::insert((Claimant::Pubkey(pubkey), RSymbol::RETH, cycle + offset), count);
+			}
+			reads += 1;
+			writes += 2;
+		}
+
+		T::DbWeight::get().reads_writes(reads + 1, writes + 1)
+	}
+
+	/// Moves the entries of a storage item to another one under the same keys, returning how many were moved
+	fn move_storage<V: Decode + Encode>(module: &[u8], from: &[u8], to: &[u8]) -> Weight {
+		let mut moved: Weight = 0;
+		for (key, value) in StorageIterator::<V>::new(module, from).drain() {
+			put_storage_value(module, to, &key, value);
+			moved += 1;
 		}
+		moved
 	}
 }
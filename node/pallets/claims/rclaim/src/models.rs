@@ -1,8 +1,18 @@
 use codec::{Decode, Encode};
 use node_primitives::{Balance, BlockNumber};
+use sp_core::H256;
 use sp_runtime::RuntimeDebug;
 use sp_std::prelude::*;
 
+/// identity owning a claim
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub enum Claimant<AccountId> {
+    /// native account, claims with its own origin
+    Account(AccountId),
+    /// pubkey of the chain of the rsymbol, claims with a signature over the claiming account
+    Pubkey(Vec<u8>),
+}
+
 #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
 pub struct ClaimInfo {
     /// rtoken amount
@@ -42,3 +52,16 @@ pub struct MintRewardAct<BlockNumber, Balance> {
     /// total native token amount in this act
     pub total_native_token_amount: u128,
 }
+
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
+pub struct ClaimRoot {
+    /// merkle root of keccak256(encode(claimant, symbol, cycle, tx hash, mint amount, native token amount, reward))
+    /// leaves, see `Module::claim_leaf`
+    pub root: H256,
+    /// block when the root was committed, vesting of proof claims starts here
+    pub commit_block: BlockNumber,
+    /// reward reserved from the act for the leaves
+    pub total_reward: Balance,
+    /// reserved reward not proven yet
+    pub left_amount: Balance,
+}
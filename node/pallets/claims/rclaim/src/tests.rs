@@ -0,0 +1,349 @@
+use super::mock::*;
+use super::*;
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::migration::{get_storage_value, put_storage_value},
+	traits::OnRuntimeUpgrade,
+	Hashable, StorageValue,
+};
+use hex_literal::hex;
+
+const MODULE: &[u8] = b"RClaim";
+
+fn eth_address() -> Vec<u8> {
+	hex!("80379e6507d9de50ce45f3365693b1943da8f260").to_vec()
+}
+
+/// personal_sign of eth_address over the ascii hex of the encoded BOB
+fn bob_signature() -> Vec<u8> {
+	hex!("e375157ab907b60c1fed960d5596d7892948465025efbe9ec77d367b3418e3467634a7b19e9e0aecf735d4b75c428d12518215a931ccf859d4265c4a405af5db01").to_vec()
+}
+
+fn claim_info(total_reward: u128, mint_block: BlockNumber) -> ClaimInfo {
+	ClaimInfo {
+		mint_amount: 10,
+		native_token_amount: 10,
+		total_reward,
+		total_claimed: 0,
+		latest_claimed_block: mint_block,
+		mint_block,
+	}
+}
+
+fn reth_act(cycle: u32) -> MintRewardAct<BlockNumber, u128> {
+	MintRewardAct {
+		begin: 20,
+		end: 30,
+		cycle,
+		reward_rate: 1,
+		total_reward: 100,
+		left_amount: 70,
+		user_limit: 10,
+		locked_blocks: 5,
+		total_rtoken_amount: 10,
+		total_native_token_amount: 10,
+	}
+}
+
+fn put_legacy_claims() {
+	for (who, reward) in vec![(ALICE, 50u128), (BOB, 40)] {
+		put_storage_value(MODULE, b"ClaimInfos", &(who, RSymbol::RDOT, 1u32, 0u64).blake2_128_concat(), claim_info(reward, 1));
+		put_storage_value(MODULE, b"UserActs", &(who, RSymbol::RDOT).blake2_128_concat(), vec![1u32]);
+		put_storage_value(MODULE, b"UserMintsCount", &(who, RSymbol::RDOT, 1u32).blake2_128_concat(), 1u64);
+	}
+
+	put_storage_value(MODULE, b"REthActs", &1u32.blake2_128_concat(), reth_act(1));
+	put_storage_value(MODULE, b"REthActLatestCycle", &[], 1u32);
+	put_storage_value(MODULE, b"REthActCurrentCycle", &[], 1u32);
+	put_storage_value(MODULE, b"REthRewarder", &[], REWARDER);
+	put_storage_value(MODULE, b"REthClaimInfos", &(eth_address(), 1u32, 0u64).blake2_128_concat(), claim_info(30, 20));
+	put_storage_value(MODULE, b"UserREthActs", &eth_address().blake2_128_concat(), vec![1u32]);
+	put_storage_value(MODULE, b"UserREthMintsCount", &(eth_address(), 1u32).blake2_128_concat(), 1u64);
+}
+
+#[test]
+fn migrate_claims_should_rekey_accounts_and_shift_reth_cycles() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RClaim::add_rtoken_reward_act(Origin::root(), 1, 10, RSymbol::RDOT, 100, 10, 5, 1));
+		// reth act added before the upgrade, legacy reth cycles go after it
+		assert_ok!(RClaim::add_rtoken_reward_act(Origin::root(), 1, 10, RSymbol::RETH, 100, 10, 5, 1));
+		put_legacy_claims();
+
+		<RClaim as OnRuntimeUpgrade>::on_runtime_upgrade();
+		assert_eq!(RClaim::storage_version(), Releases::V2_0_0);
+
+		let alice = Claimant::Account(ALICE);
+		assert_eq!(RClaim::claim_infos((&alice, RSymbol::RDOT, 1, 0)), Some(claim_info(50, 1)));
+		assert_eq!(RClaim::user_acts((&alice, RSymbol::RDOT)), Some(vec![1]));
+		assert_eq!(RClaim::user_mints_count((&alice, RSymbol::RDOT, 1)), 1);
+		assert_eq!(RClaim::claim_infos((Claimant::Account(BOB), RSymbol::RDOT, 1, 0)), Some(claim_info(40, 1)));
+
+		let pubkey = Claimant::Pubkey(eth_address());
+		assert_eq!(RClaim::acts((RSymbol::RETH, 1)).map(|act| act.begin), Some(1));
+		assert_eq!(RClaim::acts((RSymbol::RETH, 2)), Some(reth_act(2)));
+		assert_eq!(RClaim::act_latest_cycle(RSymbol::RETH), 2);
+		assert_eq!(RClaim::act_current_cycle(RSymbol::RETH), 2);
+		assert_eq!(RClaim::rewarders(RSymbol::RETH), Some(REWARDER));
+		assert_eq!(RClaim::claim_infos((&pubkey, RSymbol::RETH, 2, 0)), Some(claim_info(30, 20)));
+		assert_eq!(RClaim::claim_infos((&pubkey, RSymbol::RETH, 1, 0)), None);
+		assert_eq!(RClaim::user_acts((&pubkey, RSymbol::RETH)), Some(vec![2]));
+		assert_eq!(RClaim::user_mints_count((&pubkey, RSymbol::RETH, 2)), 1);
+
+		// nothing is left under the legacy keys
+		assert_eq!(StorageIterator::<ClaimInfo>::new(MODULE, b"LegacyClaimInfos").count(), 0);
+		assert_eq!(StorageIterator::<ClaimInfo>::new(MODULE, b"REthClaimInfos").count(), 0);
+		assert_eq!(StorageIterator::<ClaimInfo>::new(MODULE, b"ClaimInfos").count(), 3);
+		assert_eq!(get_storage_value::<u32>(MODULE, b"REthActLatestCycle", &[]), None);
+	});
+}
+
+#[test]
+fn migrated_claims_should_be_claimable() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RClaim::add_rtoken_reward_act(Origin::root(), 1, 10, RSymbol::RDOT, 100, 10, 5, 1));
+		assert_ok!(RClaim::add_rtoken_reward_act(Origin::root(), 1, 10, RSymbol::RETH, 100, 10, 5, 1));
+		put_legacy_claims();
+		<RClaim as OnRuntimeUpgrade>::on_runtime_upgrade();
+		assert_ok!(RClaim::set_fund_address(Origin::root(), FUND));
+		System::set_block_number(30);
+
+		assert_ok!(RClaim::claim_reward(Origin::signed(ALICE), Claimant::Account(ALICE), vec![], RSymbol::RDOT, 1, 0));
+		assert_eq!(Balances::free_balance(ALICE), 50);
+
+		let pubkey = Claimant::Pubkey(eth_address());
+		// the signature is over BOB, so only BOB may claim for the eth address
+		assert_noop!(
+			RClaim::claim_reward(Origin::signed(ALICE), pubkey.clone(), bob_signature(), RSymbol::RETH, 2, 0),
+			Error::<Test>::SigsFailed
+		);
+		assert_noop!(
+			RClaim::claim_reward(Origin::signed(BOB), pubkey.clone(), bob_signature(), RSymbol::RETH, 1, 0),
+			Error::<Test>::HasNoClaimInfo
+		);
+		assert_ok!(RClaim::claim_reward(Origin::signed(BOB), pubkey.clone(), bob_signature(), RSymbol::RETH, 2, 0));
+		assert_eq!(Balances::free_balance(BOB), 30);
+		assert_eq!(RClaim::claim_infos((&pubkey, RSymbol::RETH, 2, 0)).map(|info| info.total_claimed), Some(30));
+		assert_eq!(Balances::free_balance(FUND), 1_000 - 80);
+	});
+}
+
+#[test]
+fn migrate_claims_should_run_once() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::put(Releases::V2_0_0);
+		put_legacy_claims();
+		<RClaim as OnRuntimeUpgrade>::on_runtime_upgrade();
+		assert_eq!(RClaim::act_latest_cycle(RSymbol::RETH), 0);
+		assert_eq!(get_storage_value::<u32>(MODULE, b"REthActLatestCycle", &[]), Some(1));
+	});
+}
+
+#[test]
+fn genesis_built_chain_should_skip_claims_migration() {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	GenesisConfig::default().assimilate_storage::<Test>(&mut t).unwrap();
+	sp_io::TestExternalities::from(t).execute_with(|| {
+		assert_eq!(RClaim::storage_version(), Releases::V2_0_0);
+		put_legacy_claims();
+
+		assert_eq!(<RClaim as OnRuntimeUpgrade>::on_runtime_upgrade(), 0);
+		assert_eq!(RClaim::act_latest_cycle(RSymbol::RETH), 0);
+		assert_eq!(get_storage_value::<u32>(MODULE, b"REthActLatestCycle", &[]), Some(1));
+		assert_eq!(StorageIterator::<ClaimInfo>::new(MODULE, b"LegacyClaimInfos").count(), 0);
+	});
+}
+
+const PROOF_MINTER: u64 = 5;
+
+fn hash_pair(a: H256, b: H256) -> H256 {
+	let (left, right) = if a <= b { (a, b) } else { (b, a) };
+	H256::from(keccak_256(&[left.as_bytes(), right.as_bytes()].concat()))
+}
+
+/// root and proofs of a tree over four leaves
+fn tree_of(leaves: &[H256]) -> (H256, Vec<Vec<H256>>) {
+	let left = hash_pair(leaves[0], leaves[1]);
+	let right = hash_pair(leaves[2], leaves[3]);
+	let proofs = vec![
+		vec![leaves[1], right],
+		vec![leaves[0], right],
+		vec![leaves[3], left],
+		vec![leaves[2], left],
+	];
+	(hash_pair(left, right), proofs)
+}
+
+/// mints of cycle 1 with a value of 10 and their rewards
+fn proof_mints() -> Vec<(Claimant<u64>, Vec<u8>, u128)> {
+	vec![
+		(Claimant::Account(ALICE), vec![1], 20),
+		(Claimant::Pubkey(eth_address()), vec![2], 20),
+		(Claimant::Account(ALICE), vec![3], 10),
+		(Claimant::Account(BOB), vec![4], 10),
+	]
+}
+
+fn proof_tree(symbol: RSymbol) -> (H256, Vec<Vec<H256>>) {
+	let leaves = proof_mints()
+		.iter()
+		.map(|(claimant, tx_hash, reward)| RClaim::claim_leaf(claimant, symbol, 1, tx_hash, 10, 10, *reward))
+		.collect::<Vec<H256>>();
+	tree_of(&leaves)
+}
+
+/// act of 100 paying one fis per native token, vesting over 10 blocks
+fn setup_proof_act(symbol: RSymbol) {
+	assert_ok!(RClaim::add_rtoken_reward_act(Origin::root(), 1, 100, symbol, 100, 50, 10, RATEBASE));
+	assert_ok!(RClaim::set_rewarder(Origin::root(), symbol, REWARDER));
+	assert_ok!(RClaim::set_fund_address(Origin::root(), FUND));
+}
+
+fn prove(who: u64, index: usize, symbol: RSymbol, proof: Vec<H256>) -> DispatchResult {
+	let (claimant, tx_hash, reward) = proof_mints()[index].clone();
+	let signature = if who == BOB { bob_signature() } else { vec![] };
+	RClaim::claim_with_proof(Origin::signed(who), claimant, signature, symbol, 1, tx_hash, 10, 10, reward, proof)
+}
+
+#[test]
+fn commit_claim_root_should_reserve_reward_of_the_root() {
+	new_test_ext().execute_with(|| {
+		setup_proof_act(RSymbol::RETH);
+		let (root, _) = proof_tree(RSymbol::RETH);
+
+		assert_noop!(
+			RClaim::commit_claim_root(Origin::signed(ALICE), RSymbol::RETH, 1, root, 60),
+			Error::<Test>::InvalidRewarder
+		);
+		assert_noop!(
+			RClaim::commit_claim_root(Origin::signed(REWARDER), RSymbol::RETH, 2, root, 60),
+			Error::<Test>::HasNoAct
+		);
+		assert_noop!(
+			RClaim::commit_claim_root(Origin::signed(REWARDER), RSymbol::RETH, 1, root, 0),
+			Error::<Test>::ValueZero
+		);
+		assert_noop!(
+			RClaim::commit_claim_root(Origin::signed(REWARDER), RSymbol::RETH, 1, root, 101),
+			Error::<Test>::InsufficientFis
+		);
+
+		assert_ok!(RClaim::commit_claim_root(Origin::signed(REWARDER), RSymbol::RETH, 1, root, 60));
+		assert_eq!(
+			RClaim::claim_roots((RSymbol::RETH, 1)),
+			Some(ClaimRoot { root, commit_block: 1, total_reward: 60, left_amount: 60 })
+		);
+		assert_eq!(RClaim::acts((RSymbol::RETH, 1)).map(|act| act.left_amount), Some(40));
+
+		assert_noop!(
+			RClaim::commit_claim_root(Origin::root(), RSymbol::RETH, 1, H256::repeat_byte(1), 10),
+			Error::<Test>::ClaimRootExist
+		);
+	});
+}
+
+#[test]
+fn claim_with_proof_should_vest_rewards_from_commit_block() {
+	new_test_ext().execute_with(|| {
+		setup_proof_act(RSymbol::RETH);
+		let (root, proofs) = proof_tree(RSymbol::RETH);
+		assert_ok!(RClaim::commit_claim_root(Origin::root(), RSymbol::RETH, 1, root, 60));
+		// mints rewarded on chain take what is left of the act, not the reserve of the root
+		RClaim::update_claim_info(&PROOF_MINTER, RSymbol::RETH, 40, 40);
+		assert_eq!(RClaim::acts((RSymbol::RETH, 1)).map(|act| act.left_amount), Some(0));
+
+		// half of the vesting from block 1 to 11 has passed
+		System::set_block_number(6);
+		assert_ok!(prove(ALICE, 0, RSymbol::RETH, proofs[0].clone()));
+		assert_eq!(Balances::free_balance(ALICE), 10);
+		assert_eq!(
+			RClaim::claim_infos((Claimant::Account(ALICE), RSymbol::RETH, 1, 0)),
+			Some(ClaimInfo {
+				mint_amount: 10,
+				native_token_amount: 10,
+				total_reward: 20,
+				total_claimed: 10,
+				latest_claimed_block: 6,
+				mint_block: 1,
+			})
+		);
+
+		// the eth address signed BOB, so BOB proves its mint
+		let pubkey = Claimant::Pubkey(eth_address());
+		assert_ok!(prove(BOB, 1, RSymbol::RETH, proofs[1].clone()));
+		assert_eq!(Balances::free_balance(BOB), 10);
+		assert_eq!(RClaim::claim_infos((&pubkey, RSymbol::RETH, 1, 0)).map(|info| info.total_claimed), Some(10));
+		assert_eq!(RClaim::user_acts((&pubkey, RSymbol::RETH)), Some(vec![1]));
+
+		System::set_block_number(11);
+		assert_ok!(RClaim::claim_reward(Origin::signed(ALICE), Claimant::Account(ALICE), vec![], RSymbol::RETH, 1, 0));
+		assert_ok!(prove(ALICE, 2, RSymbol::RETH, proofs[2].clone()));
+		assert_ok!(prove(BOB, 3, RSymbol::RETH, proofs[3].clone()));
+		assert_eq!(Balances::free_balance(ALICE), 30);
+		assert_eq!(Balances::free_balance(BOB), 20);
+		assert_eq!(RClaim::user_mints_count((Claimant::Account(ALICE), RSymbol::RETH, 1)), 2);
+		assert_eq!(Balances::free_balance(FUND), 1_000 - 50);
+
+		assert_eq!(RClaim::claim_roots((RSymbol::RETH, 1)).map(|claim_root| claim_root.left_amount), Some(0));
+		let act = RClaim::acts((RSymbol::RETH, 1)).unwrap();
+		assert_eq!(act.left_amount, 0);
+		assert_eq!(act.total_rtoken_amount, 40 + 4 * 10);
+		assert_eq!(act.total_native_token_amount, 40 + 4 * 10);
+	});
+}
+
+#[test]
+fn claim_with_proof_should_reject_invalid_proofs() {
+	new_test_ext().execute_with(|| {
+		setup_proof_act(RSymbol::RETH);
+		let (root, proofs) = proof_tree(RSymbol::RETH);
+		assert_noop!(prove(ALICE, 0, RSymbol::RETH, proofs[0].clone()), Error::<Test>::HasNoClaimRoot);
+		assert_ok!(RClaim::commit_claim_root(Origin::root(), RSymbol::RETH, 1, root, 60));
+
+		// proof of another leaf
+		assert_noop!(prove(ALICE, 0, RSymbol::RETH, proofs[1].clone()), Error::<Test>::InvalidProof);
+		// a field of the leaf changed
+		let alice = Claimant::Account(ALICE);
+		assert_noop!(
+			RClaim::claim_with_proof(Origin::signed(ALICE), alice.clone(), vec![], RSymbol::RETH, 1, vec![1], 10, 10, 21, proofs[0].clone()),
+			Error::<Test>::InvalidProof
+		);
+		assert_noop!(
+			RClaim::claim_with_proof(Origin::signed(ALICE), alice.clone(), vec![], RSymbol::RETH, 1, vec![1], 11, 10, 20, proofs[0].clone()),
+			Error::<Test>::InvalidProof
+		);
+		assert_noop!(
+			RClaim::claim_with_proof(Origin::signed(ALICE), alice.clone(), vec![], RSymbol::RETH, 1, vec![9], 10, 10, 20, proofs[0].clone()),
+			Error::<Test>::InvalidProof
+		);
+		// deeper than any tree the root may have
+		let mut deep_proof = proofs[0].clone();
+		deep_proof.resize(MAX_PROOF_DEPTH + 1, H256::zero());
+		assert_noop!(prove(ALICE, 0, RSymbol::RETH, deep_proof), Error::<Test>::InvalidProof);
+		// leaf of another claimant
+		assert_noop!(prove(BOB, 0, RSymbol::RETH, proofs[0].clone()), Error::<Test>::InvalidClaimant);
+
+		assert_ok!(prove(ALICE, 0, RSymbol::RETH, proofs[0].clone()));
+		assert_noop!(prove(ALICE, 0, RSymbol::RETH, proofs[0].clone()), Error::<Test>::MintTxHashExistErr);
+	});
+}
+
+#[test]
+fn claim_with_proof_should_work_for_non_reth_symbols() {
+	new_test_ext().execute_with(|| {
+		setup_proof_act(RSymbol::RDOT);
+		let (root, proofs) = proof_tree(RSymbol::RDOT);
+		assert_ok!(RClaim::commit_claim_root(Origin::signed(REWARDER), RSymbol::RDOT, 1, root, 60));
+
+		// leaves are bound to their symbol
+		assert_noop!(prove(ALICE, 0, RSymbol::RETH, proofs[0].clone()), Error::<Test>::HasNoClaimRoot);
+		// an eth address is no pubkey of rdot
+		assert_noop!(prove(BOB, 1, RSymbol::RDOT, proofs[1].clone()), Error::<Test>::InvalidPubkey);
+
+		System::set_block_number(11);
+		assert_ok!(prove(ALICE, 0, RSymbol::RDOT, proofs[0].clone()));
+		assert_ok!(prove(BOB, 3, RSymbol::RDOT, proofs[3].clone()));
+		assert_eq!(Balances::free_balance(ALICE), 20);
+		assert_eq!(Balances::free_balance(BOB), 10);
+		assert_eq!(RClaim::claim_roots((RSymbol::RDOT, 1)).map(|claim_root| claim_root.left_amount), Some(30));
+	});
+}
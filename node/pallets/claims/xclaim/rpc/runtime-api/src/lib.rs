@@ -0,0 +1,27 @@
+// Copyright 2019-2021 Stafi Protocol.
+// This file is part of Stafi.
+
+// Stafi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Stafi.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime API definition for the xclaim pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use node_primitives::XSymbol;
+pub use xclaim::ClaimStatus;
+
+sp_api::decl_runtime_apis! {
+	pub trait XClaimApi<AccountId> where
+		AccountId: Codec,
+	{
+		/// Claimable, claimed and expired amounts of `symbol` of `who`
+		fn claim_status(who: AccountId, symbol: XSymbol) -> ClaimStatus;
+	}
+}
@@ -0,0 +1,80 @@
+// Copyright 2019-2021 Stafi Protocol.
+// This file is part of Stafi.
+
+// Stafi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Stafi.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC interface for the xclaim pallet.
+
+use std::sync::Arc;
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use node_primitives::XSymbol;
+pub use xclaim_rpc_runtime_api::{XClaimApi as XClaimRuntimeApi, ClaimStatus};
+
+#[rpc]
+pub trait XClaimApi<BlockHash, AccountId> {
+	/// Claimable, claimed and expired amounts of `symbol` of `who`
+	#[rpc(name = "xclaim_claimStatus")]
+	fn claim_status(&self, who: AccountId, symbol: XSymbol, at: Option<BlockHash>) -> Result<ClaimStatus>;
+}
+
+/// A struct that implements the [`XClaimApi`].
+pub struct XClaim<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> XClaim<C, B> {
+	/// Create new `XClaim` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+impl<C, Block, AccountId> XClaimApi<<Block as BlockT>::Hash, AccountId> for XClaim<C, Block>
+where
+	Block: BlockT,
+	C: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: XClaimRuntimeApi<Block, AccountId>,
+	AccountId: Codec,
+{
+	fn claim_status(
+		&self,
+		who: AccountId,
+		symbol: XSymbol,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<ClaimStatus> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.claim_status(&at, who, symbol).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(Error::RuntimeError.into()),
+			message: "Unable to query claim status.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})
+	}
+}
@@ -27,6 +27,14 @@ use sp_runtime::{
 use node_primitives::{XSymbol};
 use xtoken_balances::{traits::{Currency as XCurrency}};
 
+pub mod models;
+pub use models::*;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
 const MODULE_ID: ModuleId = ModuleId(*b"xsym/clm");
 
 /// Configuration trait.
@@ -35,6 +43,8 @@ pub trait Trait: system::Trait {
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
     /// Currency mechanism of xtoken
     type XCurrency: XCurrency<Self::AccountId>;
+    /// Treasury receiving the unclaimed XSymbol tokens of closed claim windows
+    type TreasuryModuleId: Get<ModuleId>;
 }
 
 // This pallet's storage items.
@@ -45,16 +55,29 @@ decl_storage! {
 		pub Total get(fn total): map hasher(blake2_128_concat) XSymbol => u128;
 		/// Proxy accounts for setting fees
         ProxyAccounts get(fn proxy_accounts): map hasher(blake2_128_concat) T::AccountId => Option<u8>;
+		/// Last block claims of XSymbol can be minted and claimed, no window means claims never expire
+		pub ClaimWindows get(fn claim_windows): map hasher(blake2_128_concat) XSymbol => Option<T::BlockNumber>;
+		/// Unclaimed amount of XSymbol swept to the treasury after its claim window closed
+		pub Swept get(fn swept): map hasher(blake2_128_concat) XSymbol => Option<u128>;
+		/// Round of the claims of XSymbol, bumped by each sweep
+		pub ClaimRound get(fn claim_round): map hasher(blake2_128_concat) XSymbol => u32;
+		/// Round the claim of an account was last minted in, claims of earlier rounds were swept
+		pub AccountRound get(fn account_round): double_map hasher(blake2_128_concat) T::AccountId, hasher(blake2_128_concat) XSymbol => u32;
 	}
 }
 
 // The pallet's events
 decl_event!(
 	pub enum Event<T> where
-        AccountId = <T as system::Trait>::AccountId
+        AccountId = <T as system::Trait>::AccountId,
+        BlockNumber = <T as system::Trait>::BlockNumber
     {
         /// Someone claimed some XSymbol tokens.
 		Claimed(AccountId, XSymbol, u128),
+		/// Claim window of XSymbol set: symbol, last block
+		ClaimWindowSet(XSymbol, BlockNumber),
+		/// Unclaimed XSymbol tokens swept: symbol, treasury, amount
+		UnclaimedSwept(XSymbol, AccountId, u128),
 	}
 );
 
@@ -74,6 +97,14 @@ decl_error! {
         OverFlow,
 		/// Insufficient Xbalance
 		InsufficientXbalance,
+		/// claim window of XSymbol closed
+		ClaimWindowClosed,
+		/// claim window of XSymbol still open
+		ClaimWindowOpen,
+		/// XSymbol has no claim window
+		NoClaimWindow,
+		/// unclaimed XSymbol tokens already swept
+		AlreadySwept,
 	}
 }
 
@@ -105,10 +136,47 @@ decl_module! {
             Ok(())
         }
 
+        /// Set the last block claims of XSymbol can be minted and claimed.
+        /// A window set after a sweep starts a new round, claims swept before stay expired.
+        #[weight = 1_000_000]
+        pub fn set_claim_window(origin, symbol: XSymbol, end: T::BlockNumber) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(end >= system::Module::<T>::block_number(), Error::<T>::ClaimWindowClosed);
+            <ClaimWindows<T>>::insert(symbol, end);
+            Swept::remove(symbol);
+
+            Self::deposit_event(RawEvent::ClaimWindowSet(symbol, end));
+            Ok(())
+        }
+
+        /// Sweep the unclaimed XSymbol tokens to the treasury once the claim window closed.
+        #[weight = T::DbWeight::get().reads_writes(4, 4) + 50_000_000]
+        pub fn sweep_unclaimed(origin, symbol: XSymbol) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Self::claim_windows(symbol).is_some(), Error::<T>::NoClaimWindow);
+            ensure!(!Self::is_window_open(symbol), Error::<T>::ClaimWindowOpen);
+            ensure!(Self::swept(symbol).is_none(), Error::<T>::AlreadySwept);
+
+            let total = Self::total(symbol);
+            let treasury = Self::treasury_account_id();
+            if total > 0 {
+                T::XCurrency::transfer(&Self::account_id(), &treasury, symbol, total)?;
+            }
+
+            Total::insert(symbol, 0);
+            Swept::insert(symbol, total);
+            ClaimRound::mutate(symbol, |round| *round += 1);
+
+            Self::deposit_event(RawEvent::UnclaimedSwept(symbol, treasury, total));
+            Ok(())
+        }
+
         /// Make a claim
-		#[weight = T::DbWeight::get().reads_writes(5, 4) + 50_000_000]
+		#[weight = T::DbWeight::get().reads_writes(6, 4) + 50_000_000]
 		pub fn claim(origin, symbol: XSymbol) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			ensure!(Self::is_window_open(symbol), Error::<T>::ClaimWindowClosed);
+            ensure!(!Self::is_swept_claim(&who, symbol), Error::<T>::HasNoClaim);
 
             let balance_claim = <Claims<T>>::get(&who, symbol).ok_or(Error::<T>::HasNoClaim)?;
 			let balance_claimed = <Claimed<T>>::get(&who, symbol).unwrap_or(0);
@@ -134,14 +202,21 @@ decl_module! {
 
 			ensure!(value > 0, Error::<T>::ValueZero);
             ensure!(<ProxyAccounts<T>>::contains_key(&who), Error::<T>::InvalidProxyAccount);
+            ensure!(Self::is_window_open(symbol), Error::<T>::ClaimWindowClosed);
 
-            let old_balance = <Claims<T>>::get(&dest, symbol).unwrap_or(0);
+            // the unclaimed part of a swept claim is dropped
+            let old_balance = if Self::is_swept_claim(&dest, symbol) {
+                <Claimed<T>>::get(&dest, symbol).unwrap_or(0)
+            } else {
+                <Claims<T>>::get(&dest, symbol).unwrap_or(0)
+            };
 			let balance_due = value.checked_add(old_balance).ok_or(Error::<T>::OverFlow)?;
 			let new_total = Self::total(symbol).checked_add(value).ok_or(Error::<T>::OverFlow)?;
 
 			ensure!(T::XCurrency::free_balance(&Self::account_id(), symbol) >= new_total, Error::<T>::InsufficientXbalance);
 
             Total::insert(symbol, new_total);
+            <AccountRound<T>>::insert(&dest, symbol, Self::claim_round(symbol));
             <Claims<T>>::insert(dest, symbol, balance_due);
 
             Ok(())
@@ -155,4 +230,33 @@ impl<T: Trait> Module<T> {
     pub fn account_id() -> T::AccountId {
         MODULE_ID.into_account()
     }
+
+    /// Account of the treasury receiving swept XSymbol tokens
+    pub fn treasury_account_id() -> T::AccountId {
+        T::TreasuryModuleId::get().into_account()
+    }
+
+    /// Whether claims of XSymbol can still be minted and claimed
+    pub fn is_window_open(symbol: XSymbol) -> bool {
+        match Self::claim_windows(symbol) {
+            Some(end) => system::Module::<T>::block_number() <= end,
+            None => true,
+        }
+    }
+
+    /// Whether the claim of an account was minted before the last sweep of XSymbol
+    pub fn is_swept_claim(who: &T::AccountId, symbol: XSymbol) -> bool {
+        Self::account_round(who, symbol) < Self::claim_round(symbol)
+    }
+
+    /// Claimable, claimed and expired amounts of XSymbol of an account
+    pub fn claim_status(who: &T::AccountId, symbol: XSymbol) -> ClaimStatus {
+        let claimed = <Claimed<T>>::get(who, symbol).unwrap_or(0);
+        let unclaimed = <Claims<T>>::get(who, symbol).unwrap_or(0).saturating_sub(claimed);
+        if Self::is_window_open(symbol) && !Self::is_swept_claim(who, symbol) {
+            ClaimStatus { claimable: unclaimed, claimed, expired: 0 }
+        } else {
+            ClaimStatus { claimable: 0, claimed, expired: unclaimed }
+        }
+    }
 }
\ No newline at end of file
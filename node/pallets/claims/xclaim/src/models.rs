@@ -0,0 +1,16 @@
+use codec::{Decode, Encode};
+use sp_runtime::RuntimeDebug;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// Claim amounts of an account for an XSymbol
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Default, Encode, Decode, RuntimeDebug)]
+pub struct ClaimStatus {
+    /// minted but not yet claimed while the claim window is open
+    pub claimable: u128,
+    /// already claimed
+    pub claimed: u128,
+    /// left unclaimed when the claim window closed
+    pub expired: u128,
+}
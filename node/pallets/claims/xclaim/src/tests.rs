@@ -0,0 +1,105 @@
+use super::mock::*;
+use super::*;
+use frame_support::{assert_noop, assert_ok};
+
+const SYMBOL: XSymbol = XSymbol::WRA;
+
+fn xbalance(who: &u64) -> u128 {
+	<XBalances as XCurrency<u64>>::free_balance(who, SYMBOL)
+}
+
+fn status(claimable: u128, claimed: u128, expired: u128) -> ClaimStatus {
+	ClaimStatus { claimable, claimed, expired }
+}
+
+/// pot of 100, claims of 30 for ALICE and 20 for BOB, window closing after block 10
+fn setup_claims() {
+	assert_ok!(<XBalances as XCurrency<u64>>::mint(&XClaim::account_id(), SYMBOL, 100));
+	assert_ok!(XClaim::set_proxy_accounts(Origin::root(), PROXY));
+	assert_ok!(XClaim::mint_claim(Origin::signed(PROXY), ALICE, SYMBOL, 30));
+	assert_ok!(XClaim::mint_claim(Origin::signed(PROXY), BOB, SYMBOL, 20));
+	assert_ok!(XClaim::set_claim_window(Origin::root(), SYMBOL, 10));
+}
+
+#[test]
+fn claims_should_expire_with_window() {
+	new_test_ext().execute_with(|| {
+		setup_claims();
+		System::set_block_number(5);
+		assert_ok!(XClaim::claim(Origin::signed(ALICE), SYMBOL));
+		assert_eq!(xbalance(&ALICE), 30);
+		assert_eq!(XClaim::claim_status(&ALICE, SYMBOL), status(0, 30, 0));
+		assert_eq!(XClaim::claim_status(&BOB, SYMBOL), status(20, 0, 0));
+
+		System::set_block_number(10);
+		assert!(XClaim::is_window_open(SYMBOL));
+		System::set_block_number(11);
+		assert!(!XClaim::is_window_open(SYMBOL));
+		assert_noop!(XClaim::claim(Origin::signed(BOB), SYMBOL), Error::<Test>::ClaimWindowClosed);
+		assert_noop!(
+			XClaim::mint_claim(Origin::signed(PROXY), BOB, SYMBOL, 1),
+			Error::<Test>::ClaimWindowClosed
+		);
+		assert_eq!(XClaim::claim_status(&ALICE, SYMBOL), status(0, 30, 0));
+		assert_eq!(XClaim::claim_status(&BOB, SYMBOL), status(0, 0, 20));
+	});
+}
+
+#[test]
+fn sweep_unclaimed_should_move_unclaimed_total() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(XClaim::sweep_unclaimed(Origin::root(), SYMBOL), Error::<Test>::NoClaimWindow);
+		setup_claims();
+		System::set_block_number(5);
+		assert_ok!(XClaim::claim(Origin::signed(ALICE), SYMBOL));
+		assert_noop!(XClaim::sweep_unclaimed(Origin::root(), SYMBOL), Error::<Test>::ClaimWindowOpen);
+
+		System::set_block_number(11);
+		assert_ok!(XClaim::sweep_unclaimed(Origin::root(), SYMBOL));
+		// only the unclaimed claims are swept, the rest of the pot stays
+		assert_eq!(xbalance(&XClaim::treasury_account_id()), 20);
+		assert_eq!(xbalance(&XClaim::account_id()), 50);
+		assert_eq!(XClaim::total(SYMBOL), 0);
+		assert_eq!(XClaim::swept(SYMBOL), Some(20));
+		assert_eq!(XClaim::claim_round(SYMBOL), 1);
+		assert_eq!(XClaim::claim_status(&BOB, SYMBOL), status(0, 0, 20));
+		assert_noop!(XClaim::sweep_unclaimed(Origin::root(), SYMBOL), Error::<Test>::AlreadySwept);
+		assert_noop!(
+			XClaim::set_claim_window(Origin::root(), SYMBOL, 10),
+			Error::<Test>::ClaimWindowClosed
+		);
+	});
+}
+
+#[test]
+fn new_window_after_sweep_should_keep_swept_claims_expired() {
+	new_test_ext().execute_with(|| {
+		setup_claims();
+		System::set_block_number(5);
+		assert_ok!(XClaim::claim(Origin::signed(ALICE), SYMBOL));
+		System::set_block_number(11);
+		assert_ok!(XClaim::sweep_unclaimed(Origin::root(), SYMBOL));
+
+		assert_ok!(XClaim::set_claim_window(Origin::root(), SYMBOL, 20));
+		assert_eq!(XClaim::swept(SYMBOL), None);
+		assert_eq!(XClaim::claim_status(&BOB, SYMBOL), status(0, 0, 20));
+		assert_noop!(XClaim::claim(Origin::signed(BOB), SYMBOL), Error::<Test>::HasNoClaim);
+
+		assert_ok!(XClaim::mint_claim(Origin::signed(PROXY), BOB, SYMBOL, 5));
+		assert_ok!(XClaim::mint_claim(Origin::signed(PROXY), ALICE, SYMBOL, 10));
+		assert_eq!(XClaim::total(SYMBOL), 15);
+		assert_eq!(XClaim::claim_status(&BOB, SYMBOL), status(5, 0, 0));
+		assert_eq!(XClaim::claim_status(&ALICE, SYMBOL), status(10, 30, 0));
+
+		assert_ok!(XClaim::claim(Origin::signed(BOB), SYMBOL));
+		assert_ok!(XClaim::claim(Origin::signed(ALICE), SYMBOL));
+		assert_eq!(xbalance(&BOB), 5);
+		assert_eq!(xbalance(&ALICE), 40);
+		assert_eq!(XClaim::total(SYMBOL), 0);
+
+		System::set_block_number(21);
+		assert_ok!(XClaim::sweep_unclaimed(Origin::root(), SYMBOL));
+		assert_eq!(XClaim::swept(SYMBOL), Some(0));
+		assert_eq!(XClaim::claim_round(SYMBOL), 2);
+	});
+}
@@ -37,11 +37,19 @@ pub fn verify_signature(symbol: RSymbol, pubkey: &Vec<u8>, signature: &Vec<u8>,
 }
 
 pub fn verify_recipient(symbol: RSymbol, recipient: &Vec<u8>) -> bool {
-    match symbol.chain_type() {
+    verify_chain_recipient(symbol.chain_type(), recipient)
+}
+
+/// check that `recipient` is a valid address of a chain of `chain_type`
+pub fn verify_chain_recipient(chain_type: ChainType, recipient: &Vec<u8>) -> bool {
+    match chain_type {
         ChainType::Substrate => {
             let re_public = <Sr25519Public as TryFrom<_>>::try_from(&recipient[..]);
             return re_public.is_ok();
         },
+        // tendermint recipients are the raw 20 byte account hash, as for rtoken unbond
+        // recipients, relayers bech32 encode them with the hrp of the target chain,
+        // so there is no hrp or checksum to check on chain
         ChainType::Tendermint | ChainType::Ethereum => {
             return recipient.len() == 20;
         },
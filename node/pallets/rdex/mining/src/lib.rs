@@ -4,13 +4,16 @@ use frame_support::{
     decl_error, decl_event, decl_module, decl_storage,
     dispatch::DispatchResult,
     ensure,
-    traits::{Currency, ExistenceRequirement::KeepAlive},
+    traits::{Currency, ExistenceRequirement::KeepAlive, Get},
 };
 use sp_std::prelude::*;
 
 use frame_system::{self as system, ensure_root, ensure_signed};
 use node_primitives::RSymbol;
 use rdex_balances::traits::Currency as LpCurrency;
+use rdex_swap::{LiquidityProvider, PriceOracle, PRICE_BASE};
+use rtoken_balances::traits::Currency as RCurrency;
+use xtoken_balances::traits::Currency as XCurrency;
 use sp_runtime::{
     traits::{AccountIdConversion, SaturatedConversion},
     ModuleId,
@@ -21,6 +24,16 @@ pub trait Trait: system::Trait {
     type Currency: Currency<Self::AccountId>;
     /// currency of lp
     type LpCurrency: LpCurrency<Self::AccountId>;
+    /// currency of rtoken rewards
+    type RCurrency: RCurrency<Self::AccountId>;
+    /// currency of xtoken rewards
+    type XCurrency: XCurrency<Self::AccountId>;
+    /// turns compounded rewards into lp
+    type LiquidityProvider: LiquidityProvider<Self::AccountId>;
+    /// average rToken prices the guarded impermanent loss is valued at
+    type PriceOracle: PriceOracle;
+    /// blocks the rToken price is averaged over when valuing the guarded impermanent loss
+    type GuardPriceWindow: Get<u32>;
 }
 
 pub mod models;
@@ -34,6 +47,10 @@ mod tests;
 
 const MODULE_ID: ModuleId = ModuleId(*b"rdx/mine");
 const REWARD_FACTOR: u128 = 1_000_000_000_000;
+/// max extra rewards of a stake pool
+pub const MAX_EXTRA_REWARDS: usize = 8;
+/// scale of `LockBoost::max_boost`
+pub const BOOST_BASE: u128 = 1_000_000;
 decl_event! {
     pub enum Event<T> where
         AccountId = <T as system::Trait>::AccountId
@@ -50,6 +67,22 @@ decl_event! {
         AddPool(RSymbol, u32, u32, u32, u32, u128, u128, bool),
         /// RmPool: symbol, pool index, grade index
         RmPool(RSymbol, u32, u32),
+        /// AddExtraReward: symbol, pool index, grade index, reward index, asset, reward per block, total reward
+        AddExtraReward(RSymbol, u32, u32, u32, RewardAsset, u128, u128),
+        /// ClaimExtraReward: account, symbol, pool index, grade index, stake index, asset, reward
+        ClaimExtraReward(AccountId, RSymbol, u32, u32, u32, RewardAsset, u128),
+        /// SetRewardVesting: symbol, pool index, vesting blocks
+        SetRewardVesting(RSymbol, u32, u32),
+        /// VestReward: account, asset, amount, end block
+        VestReward(AccountId, RewardAsset, u128, u32),
+        /// ClaimVestedReward: account, asset, amount
+        ClaimVestedReward(AccountId, RewardAsset, u128),
+        /// Compound: account, symbol, pool index, grade index, stake index, compounded reward, added lp amount
+        Compound(AccountId, RSymbol, u32, u32, u32, u128, u128),
+        /// SetLockBoost: symbol, pool index, max boost, max lock blocks
+        SetLockBoost(RSymbol, u32, u128, u32),
+        /// MigrateStake: account, symbol, pool index, stake index, to pool index, to grade index, to stake index, lp amount, reward
+        MigrateStake(AccountId, RSymbol, u32, u32, u32, u32, u32, u128, u128),
     }
 }
 
@@ -70,6 +103,14 @@ decl_error! {
         GradeIndexOverflow,
         LpBalanceNotEmpty,
         DepositNotAllowed,
+        ExtraRewardNotExist,
+        ExtraRewardsOverflow,
+        NoVestedReward,
+        CompoundWhileVesting,
+        NotCompoundKeeper,
+        AutoCompoundNotEnabled,
+        MigrateToSameGrade,
+        LockShortened,
     }
 }
 
@@ -91,11 +132,31 @@ decl_storage! {
         pub LpWhitelist get(fn lp_whitelist): map hasher(blake2_128_concat) (RSymbol, T::AccountId) => bool = false;
         /// lp switch
         pub LpSwitch get(fn lp_switch): map hasher(blake2_128_concat)  RSymbol => bool = false;
+        /// extra rewards: (symbol, pool index, grade index) => extra rewards
+        pub ExtraRewards get(fn extra_rewards): map hasher(blake2_128_concat) (RSymbol, u32, u32) => Vec<ExtraReward>;
+        /// reward debts of stake users in the extra rewards of their pool, in the same order: (symbol, pool index, account, stake index) => debts
+        pub ExtraRewardDebts get(fn extra_reward_debts): map hasher(blake2_128_concat) (RSymbol, u32, T::AccountId, u32) => Vec<u128>;
+        /// blocks over which claimed rewards vest, paid at once if 0: (symbol, pool index) => blocks
+        pub RewardVestingBlocks get(fn reward_vesting_blocks): map hasher(blake2_128_concat) (RSymbol, u32) => u32;
+        /// vesting rewards: (account, asset) => vesting reward
+        pub VestingRewards get(fn vesting_rewards): map hasher(blake2_128_concat) (T::AccountId, RewardAsset) => Option<VestingReward>;
+        /// stakes keepers may compound: (symbol, pool index, account, stake index) => enabled
+        pub AutoCompound get(fn auto_compound): map hasher(blake2_128_concat) (RSymbol, u32, T::AccountId, u32) => bool = false;
+        /// accounts allowed to compound stakes that opted in
+        pub CompoundKeepers get(fn compound_keepers): map hasher(blake2_128_concat) T::AccountId => bool = false;
+        /// reward boost of remaining lock time: (symbol, pool index) => lock boost
+        pub LockBoosts get(fn lock_boosts): map hasher(blake2_128_concat) (RSymbol, u32) => Option<LockBoost>;
+        /// reward weights of stakes, their lp amount if not set: (symbol, pool index, account, stake index) => weight
+        pub StakeWeights get(fn stake_weights): map hasher(blake2_128_concat) (RSymbol, u32, T::AccountId, u32) => Option<u128>;
+        /// total reward weights of stake pools, their total stake lp if not set: (symbol, pool index, grade index) => weight
+        pub TotalStakeWeights get(fn total_stake_weights): map hasher(blake2_128_concat) (RSymbol, u32, u32) => Option<u128>;
     }
 }
 
 decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        const GuardPriceWindow: u32 = T::GuardPriceWindow::get();
+
         fn deposit_event() = default;
 
         /// deposit
@@ -118,12 +179,16 @@ decl_module! {
 
             T::LpCurrency::transfer(&who, &Self::account_id(), symbol, lp_amount)?;
             stake_pool = Self::update_pool(symbol, pool_index, grade_index);
+            let total_weight = Self::total_weight(symbol, pool_index, grade_index, &stake_pool);
+            let weight = Self::cal_stake_weight(symbol, pool_index, lp_amount, now_block.saturating_add(stake_pool.lp_locked_blocks));
+            let extra_rewards = Self::update_extra_rewards(symbol, pool_index, grade_index, total_weight);
+            let extra_reward_debts = extra_rewards.iter().map(|extra| Self::cal_reward(weight, extra.reward_per_share)).collect::<Vec<u128>>();
             stake_pool.total_stake_lp = stake_pool.total_stake_lp.saturating_add(lp_amount);
             stake_pool_vec[grade_index as usize] = stake_pool;
             let new_stake_user = StakeUser {
                 account: who.clone(),
                 lp_amount: lp_amount,
-                reward_debt: lp_amount.saturating_mul(stake_pool.reward_per_share).checked_div(REWARD_FACTOR).unwrap_or(0),
+                reward_debt: Self::cal_reward(weight, stake_pool.reward_per_share),
                 reserved_lp_reward: 0,
                 total_fis_value: Self::cal_share_amount(swap_pool.total_unit, lp_amount, swap_pool.fis_balance),
                 total_rtoken_value: Self::cal_share_amount(swap_pool.total_unit, lp_amount, swap_pool.rtoken_balance),
@@ -133,6 +198,10 @@ decl_module! {
             };
 
             <StakeUsers<T>>::insert((symbol, pool_index, &who, user_stake_count), new_stake_user);
+            <ExtraRewardDebts<T>>::insert((symbol, pool_index, &who, user_stake_count), extra_reward_debts);
+            <StakeWeights<T>>::insert((symbol, pool_index, &who, user_stake_count), weight);
+            <TotalStakeWeights>::insert((symbol, pool_index, grade_index), total_weight.saturating_add(weight));
+            <ExtraRewards>::insert((symbol, pool_index, grade_index), extra_rewards);
             <StakePools>::insert((symbol, pool_index), stake_pool_vec);
             <UserStakeCount<T>>::insert((symbol, pool_index, &who), user_stake_count + 1);
             Self::deposit_event(RawEvent::Deposit(who, symbol, pool_index, grade_index, user_stake_count, lp_amount));
@@ -155,34 +224,50 @@ decl_module! {
             ensure!(stake_pool.total_stake_lp >= stake_user.lp_amount, Error::<T>::LpBalanceNotEnough);
 
             stake_pool = Self::update_pool(symbol, pool_index, stake_user.grade_index);
+            let total_weight = Self::total_weight(symbol, pool_index, stake_user.grade_index, &stake_pool);
+            let weight = Self::stake_weight(symbol, pool_index, &who, stake_index, &stake_user);
+            let extra_rewards = Self::update_extra_rewards(symbol, pool_index, stake_user.grade_index, total_weight);
             stake_pool.total_stake_lp = stake_pool.total_stake_lp.saturating_sub(lp_amount);
             stake_pool_vec[stake_user.grade_index as usize] = stake_pool;
 
-            let withdraw_reward = stake_user.lp_amount.saturating_mul(stake_pool.reward_per_share).
-                checked_div(REWARD_FACTOR).unwrap_or(0).
-                saturating_sub(stake_user.reward_debt);
+            let withdraw_reward = Self::cal_reward(weight, stake_pool.reward_per_share).saturating_sub(stake_user.reward_debt);
             let reserved_lp_total_reward = stake_user.reserved_lp_reward.saturating_add(withdraw_reward);
 
-            let guard_amount: u128 = 0;
+            let mut guard_amount = if stake_pool.guard_impermanent_loss && now_block >= stake_user.deposit_height.saturating_add(Self::guard_line((symbol, pool_index))) {
+                Self::cal_guard_amount(symbol, &stake_user, lp_amount)
+            } else {
+                0
+            };
 
             let reserved_lp = stake_user.lp_amount.saturating_sub(lp_amount);
+            let new_weight = Self::cal_stake_weight(symbol, pool_index, reserved_lp, stake_user.deposit_height.saturating_add(stake_pool.lp_locked_blocks));
+            let (extra_reward_pays, extra_reward_debts) = Self::cal_extra_rewards(&extra_rewards, &Self::extra_reward_debts((symbol, pool_index, &who, stake_index)), weight, new_weight);
             stake_user.total_fis_value = Self::cal_share_amount(stake_user.lp_amount, reserved_lp, stake_user.total_fis_value);
             stake_user.total_rtoken_value = Self::cal_share_amount(stake_user.lp_amount, reserved_lp, stake_user.total_rtoken_value);
             stake_user.reserved_lp_reward = Self::cal_share_amount(stake_user.lp_amount, reserved_lp, reserved_lp_total_reward);
 
             stake_user.lp_amount = reserved_lp;
-            stake_user.reward_debt = stake_user.lp_amount.
-                saturating_mul(stake_pool.reward_per_share).
-                checked_div(REWARD_FACTOR).unwrap_or(0);
+            stake_user.reward_debt = Self::cal_reward(new_weight, stake_pool.reward_per_share);
             stake_user.claimed_reward = stake_user.claimed_reward.saturating_add(withdraw_reward);
 
-            let total_transfer_amount = withdraw_reward.saturating_add(guard_amount);
-            if total_transfer_amount > 0 {
-                T::Currency::transfer(&Self::account_id(), &who, total_transfer_amount.saturated_into(), KeepAlive)?;
+            let vesting_blocks = Self::reward_vesting_blocks((symbol, pool_index));
+            if withdraw_reward > 0 {
+                Self::pay_reward(&who, RewardAsset::Fis, withdraw_reward, vesting_blocks)?;
+            }
+            let module_free_balance = T::Currency::free_balance(&Self::account_id()).saturated_into::<u128>();
+            guard_amount = guard_amount.min(module_free_balance);
+            if guard_amount > 0 {
+                T::Currency::transfer(&Self::account_id(), &who, guard_amount.saturated_into(), KeepAlive)?;
+                <GuardReserve>::mutate(symbol, |reserve| *reserve = reserve.saturating_sub(guard_amount));
             }
+            Self::pay_extra_rewards(&who, symbol, pool_index, stake_user.grade_index, stake_index, extra_reward_pays, vesting_blocks)?;
 
             T::LpCurrency::transfer(&Self::account_id(), &who, symbol, lp_amount)?;
             <StakeUsers<T>>::insert((symbol, pool_index, &who, stake_index), stake_user.clone());
+            <ExtraRewardDebts<T>>::insert((symbol, pool_index, &who, stake_index), extra_reward_debts);
+            <StakeWeights<T>>::insert((symbol, pool_index, &who, stake_index), new_weight);
+            <TotalStakeWeights>::insert((symbol, pool_index, stake_user.grade_index), total_weight.saturating_sub(weight).saturating_add(new_weight));
+            <ExtraRewards>::insert((symbol, pool_index, stake_user.grade_index), extra_rewards);
             <StakePools>::insert((symbol, pool_index), stake_pool_vec);
             Self::deposit_event(RawEvent::Withdraw(who, symbol, pool_index, stake_user.grade_index, stake_index, lp_amount, withdraw_reward, guard_amount));
             Ok(())
@@ -192,30 +277,64 @@ decl_module! {
         #[weight = 10_000_000_000]
         pub fn claim_reward(origin, symbol: RSymbol, pool_index: u32, stake_index: u32) -> DispatchResult {
             let who = ensure_signed(origin)?;
-            let mut stake_user = Self::stake_users((symbol, pool_index, &who, stake_index)).ok_or(Error::<T>::StakeUserNotExist)?;
-            let mut stake_pool_vec = Self::stake_pools((symbol, pool_index)).ok_or(Error::<T>::StakePoolNotExist)?;
-            let mut stake_pool = *stake_pool_vec.get(stake_user.grade_index as usize).ok_or(Error::<T>::GradeIndexOverflow)?;
+            Self::do_claim_reward(&who, symbol, pool_index, stake_index)
+        }
 
-            ensure!(!stake_pool.emergency_switch, Error::<T>::EmergencySwitchIsOpen);
+        /// claim the reward of a stake for its owner, which brings its reward weight down to its
+        /// remaining lock time
+        #[weight = 10_000_000_000]
+        pub fn kick(origin, who: T::AccountId, symbol: RSymbol, pool_index: u32, stake_index: u32) -> DispatchResult {
+            ensure_signed(origin)?;
+            Self::do_claim_reward(&who, symbol, pool_index, stake_index)
+        }
 
-            stake_pool = Self::update_pool(symbol, pool_index, stake_user.grade_index);
-            stake_pool_vec[stake_user.grade_index as usize] = stake_pool;
+        /// move a stake to another grade or pool index, its rewards are claimed and its lock may
+        /// not end earlier
+        #[weight = 30_000_000_000]
+        pub fn migrate_stake(origin, symbol: RSymbol, pool_index: u32, stake_index: u32, to_pool_index: u32, to_grade_index: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::do_migrate_stake(&who, symbol, pool_index, stake_index, to_pool_index, to_grade_index)
+        }
 
-            let withdraw_reward = stake_user.lp_amount.saturating_mul(stake_pool.reward_per_share).
-                checked_div(REWARD_FACTOR).unwrap_or(0).
-                saturating_sub(stake_user.reward_debt);
-            stake_user.reserved_lp_reward = stake_user.reserved_lp_reward.saturating_add(withdraw_reward);
-            stake_user.claimed_reward = stake_user.claimed_reward.saturating_add(withdraw_reward);
-            stake_user.reward_debt = stake_user.lp_amount.
-                saturating_mul(stake_pool.reward_per_share).
-                checked_div(REWARD_FACTOR).unwrap_or(0);
+        /// compound the fis reward of a stake into lp added to the same stake, the zap fails if it
+        /// mints less than `min_units` lp
+        #[weight = 30_000_000_000]
+        pub fn compound(origin, symbol: RSymbol, pool_index: u32, stake_index: u32, min_units: u128) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::do_compound(&who, symbol, pool_index, stake_index, min_units)
+        }
 
-            if withdraw_reward > 0 {
-                T::Currency::transfer(&Self::account_id(), &who, withdraw_reward.saturated_into(), KeepAlive)?;
+        /// compound a stake that opted in on behalf of its owner
+        #[weight = 30_000_000_000]
+        pub fn compound_for(origin, who: T::AccountId, symbol: RSymbol, pool_index: u32, stake_index: u32, min_units: u128) -> DispatchResult {
+            let keeper = ensure_signed(origin)?;
+            ensure!(Self::compound_keepers(&keeper), Error::<T>::NotCompoundKeeper);
+            ensure!(Self::auto_compound((symbol, pool_index, &who, stake_index)), Error::<T>::AutoCompoundNotEnabled);
+            Self::do_compound(&who, symbol, pool_index, stake_index, min_units)
+        }
+
+        /// allow or forbid keepers to compound a stake
+        #[weight = 10_000]
+        pub fn set_auto_compound(origin, symbol: RSymbol, pool_index: u32, stake_index: u32, enable: bool) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::stake_users((symbol, pool_index, &who, stake_index)).is_some(), Error::<T>::StakeUserNotExist);
+            if enable {
+                <AutoCompound<T>>::insert((symbol, pool_index, &who, stake_index), true);
+            } else {
+                <AutoCompound<T>>::remove((symbol, pool_index, &who, stake_index));
+            }
+            Ok(())
+        }
+
+        /// add or remove a compound keeper
+        #[weight = 10_000]
+        pub fn set_compound_keeper(origin, keeper: T::AccountId, enable: bool) -> DispatchResult {
+            ensure_root(origin)?;
+            if enable {
+                <CompoundKeepers<T>>::insert(keeper, true);
+            } else {
+                <CompoundKeepers<T>>::remove(keeper);
             }
-            <StakeUsers<T>>::insert((symbol, pool_index, &who, stake_index), stake_user.clone());
-            <StakePools>::insert((symbol, pool_index), stake_pool_vec);
-            Self::deposit_event(RawEvent::ClaimReward(who, symbol, pool_index, stake_user.grade_index, stake_index, withdraw_reward));
             Ok(())
         }
 
@@ -231,6 +350,8 @@ decl_module! {
              ensure!(stake_user.lp_amount > 0, Error::<T>::AmountZero);
 
              let lp_amount = stake_user.lp_amount;
+             let total_weight = Self::total_weight(symbol, pool_index, stake_user.grade_index, &stake_pool);
+             let weight = Self::stake_weight(symbol, pool_index, &who, stake_index, &stake_user);
              stake_pool.total_stake_lp = stake_pool.total_stake_lp.saturating_sub(lp_amount);
              stake_pool_vec[stake_user.grade_index as usize] = stake_pool;
 
@@ -242,6 +363,9 @@ decl_module! {
 
              T::LpCurrency::transfer(&Self::account_id(), &who, symbol, lp_amount)?;
              <StakeUsers<T>>::insert((symbol, pool_index, &who, stake_index), stake_user.clone());
+             <ExtraRewardDebts<T>>::remove((symbol, pool_index, &who, stake_index));
+             <StakeWeights<T>>::remove((symbol, pool_index, &who, stake_index));
+             <TotalStakeWeights>::insert((symbol, pool_index, stake_user.grade_index), total_weight.saturating_sub(weight));
              <StakePools>::insert((symbol, pool_index), stake_pool_vec);
              Self::deposit_event(RawEvent::EmergencyWithdraw(who, symbol, pool_index, stake_user.grade_index, stake_index, lp_amount));
              Ok(())
@@ -300,6 +424,101 @@ decl_module! {
             Ok(())
         }
 
+        /// add a reward paid on top of the fis reward of a stake pool
+        #[weight = 10_000]
+        pub fn add_extra_reward(origin, symbol: RSymbol, pool_index: u32, grade_index: u32, asset: RewardAsset, reward_per_block: u128, total_reward: u128) -> DispatchResult {
+            ensure_root(origin.clone())?;
+            let stake_pool_vec = Self::stake_pools((symbol, pool_index)).ok_or(Error::<T>::StakePoolNotExist)?;
+            let stake_pool = *stake_pool_vec.get(grade_index as usize).ok_or(Error::<T>::GradeIndexOverflow)?;
+            let mut extra_rewards = Self::extra_rewards((symbol, pool_index, grade_index));
+            ensure!(extra_rewards.len() < MAX_EXTRA_REWARDS, Error::<T>::ExtraRewardsOverflow);
+
+            let current_block_num = system::Module::<T>::block_number().saturated_into::<u32>();
+            extra_rewards.push(ExtraReward {
+                asset,
+                reward_per_block,
+                total_reward,
+                left_reward: total_reward,
+                last_reward_block: current_block_num.max(stake_pool.start_block),
+                reward_per_share: 0,
+            });
+            let reward_index = extra_rewards.len() as u32 - 1;
+            <ExtraRewards>::insert((symbol, pool_index, grade_index), extra_rewards);
+            Self::deposit_event(RawEvent::AddExtraReward(symbol, pool_index, grade_index, reward_index, asset, reward_per_block, total_reward));
+            Ok(())
+        }
+
+        /// update params of an extra reward, what is earned at the old params is kept
+        #[weight = 10_000]
+        pub fn update_extra_reward(origin, symbol: RSymbol, pool_index: u32, grade_index: u32, reward_index: u32, reward_per_block: u128, total_reward: u128) -> DispatchResult {
+            ensure_root(origin.clone())?;
+            let stake_pool_vec = Self::stake_pools((symbol, pool_index)).ok_or(Error::<T>::StakePoolNotExist)?;
+            let stake_pool = *stake_pool_vec.get(grade_index as usize).ok_or(Error::<T>::GradeIndexOverflow)?;
+            ensure!((reward_index as usize) < Self::extra_rewards((symbol, pool_index, grade_index)).len(), Error::<T>::ExtraRewardNotExist);
+
+            let total_weight = Self::total_weight(symbol, pool_index, grade_index, &stake_pool);
+            let mut extra_rewards = Self::update_extra_rewards(symbol, pool_index, grade_index, total_weight);
+            let extra = &mut extra_rewards[reward_index as usize];
+            extra.left_reward = if total_reward > extra.total_reward {
+                total_reward.saturating_sub(extra.total_reward).saturating_add(extra.left_reward)
+            } else {
+                extra.left_reward.saturating_sub(extra.total_reward.saturating_sub(total_reward))
+            };
+            extra.reward_per_block = reward_per_block;
+            extra.total_reward = total_reward;
+
+            <ExtraRewards>::insert((symbol, pool_index, grade_index), extra_rewards);
+            Ok(())
+        }
+
+        /// set blocks over which claimed rewards of a pool vest
+        #[weight = 10_000]
+        pub fn set_reward_vesting(origin, symbol: RSymbol, pool_index: u32, blocks: u32) -> DispatchResult {
+            ensure_root(origin.clone())?;
+            ensure!(Self::stake_pools((symbol, pool_index)).is_some(), Error::<T>::StakePoolNotExist);
+
+            <RewardVestingBlocks>::insert((symbol, pool_index), blocks);
+            Self::deposit_event(RawEvent::SetRewardVesting(symbol, pool_index, blocks));
+            Ok(())
+        }
+
+        /// set the reward boost of remaining lock time of a pool index, stakes take it on when
+        /// they are next touched
+        #[weight = 10_000]
+        pub fn set_lock_boost(origin, symbol: RSymbol, pool_index: u32, max_boost: u128, max_lock_blocks: u32) -> DispatchResult {
+            ensure_root(origin.clone())?;
+            ensure!(Self::stake_pools((symbol, pool_index)).is_some(), Error::<T>::StakePoolNotExist);
+
+            if max_boost == 0 || max_lock_blocks == 0 {
+                <LockBoosts>::remove((symbol, pool_index));
+            } else {
+                <LockBoosts>::insert((symbol, pool_index), LockBoost { max_boost, max_lock_blocks });
+            }
+            Self::deposit_event(RawEvent::SetLockBoost(symbol, pool_index, max_boost, max_lock_blocks));
+            Ok(())
+        }
+
+        /// claim vested rewards
+        #[weight = 10_000_000_000]
+        pub fn claim_vested_reward(origin, asset: RewardAsset) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let mut vesting = Self::vesting_rewards((&who, asset)).ok_or(Error::<T>::NoVestedReward)?;
+            let current_block_num = system::Module::<T>::block_number().saturated_into::<u32>();
+            vesting.vest(current_block_num);
+            let amount = vesting.vested;
+            ensure!(amount > 0, Error::<T>::NoVestedReward);
+
+            Self::transfer_reward(&who, asset, amount)?;
+            vesting.vested = 0;
+            if vesting.locked == 0 {
+                <VestingRewards<T>>::remove((&who, asset));
+            } else {
+                <VestingRewards<T>>::insert((&who, asset), vesting);
+            }
+            Self::deposit_event(RawEvent::ClaimVestedReward(who, asset, amount));
+            Ok(())
+        }
+
         /// remove pool
         #[weight = 10_000]
         pub fn rm_pool(origin, symbol: RSymbol, pool_index: u32, grade_index: u32) -> DispatchResult {
@@ -309,6 +528,17 @@ decl_module! {
             ensure!(stake_pool.total_stake_lp == 0, Error::<T>::LpBalanceNotEmpty);
 
             stake_pool_vec.remove(grade_index as usize);
+            // extra rewards follow the grades moved down
+            for index in grade_index..stake_pool_vec.len() as u32 {
+                let extra_rewards = <ExtraRewards>::take((symbol, pool_index, index + 1));
+                <ExtraRewards>::insert((symbol, pool_index, index), extra_rewards);
+                match <TotalStakeWeights>::take((symbol, pool_index, index + 1)) {
+                    Some(total_weight) => <TotalStakeWeights>::insert((symbol, pool_index, index), total_weight),
+                    None => <TotalStakeWeights>::remove((symbol, pool_index, index)),
+                }
+            }
+            <ExtraRewards>::remove((symbol, pool_index, stake_pool_vec.len() as u32));
+            <TotalStakeWeights>::remove((symbol, pool_index, stake_pool_vec.len() as u32));
             <StakePools>::insert((symbol, pool_index), stake_pool_vec);
             Self::deposit_event(RawEvent::RmPool(symbol, pool_index, grade_index));
             Ok(())
@@ -415,7 +645,8 @@ impl<T: Trait> Module<T> {
         if current_block_num <= stake_pool.last_reward_block {
             return stake_pool;
         }
-        if stake_pool.total_stake_lp == 0 {
+        let total_weight = Self::total_weight(symbol, index, grade_index, &stake_pool);
+        if total_weight == 0 {
             stake_pool.last_reward_block = current_block_num;
             return stake_pool;
         }
@@ -430,7 +661,7 @@ impl<T: Trait> Module<T> {
             stake_pool.left_reward = stake_pool.left_reward.saturating_sub(reward);
             let add_reward_per_share = reward
                 .saturating_mul(REWARD_FACTOR)
-                .checked_div(stake_pool.total_stake_lp)
+                .checked_div(total_weight)
                 .unwrap_or(0);
             stake_pool.reward_per_share = stake_pool
                 .reward_per_share
@@ -441,6 +672,334 @@ impl<T: Trait> Module<T> {
         stake_pool
     }
 
+    fn do_claim_reward(who: &T::AccountId, symbol: RSymbol, pool_index: u32, stake_index: u32) -> DispatchResult {
+        let mut stake_user = Self::stake_users((symbol, pool_index, who, stake_index)).ok_or(Error::<T>::StakeUserNotExist)?;
+        let mut stake_pool_vec = Self::stake_pools((symbol, pool_index)).ok_or(Error::<T>::StakePoolNotExist)?;
+        let mut stake_pool = *stake_pool_vec.get(stake_user.grade_index as usize).ok_or(Error::<T>::GradeIndexOverflow)?;
+
+        ensure!(!stake_pool.emergency_switch, Error::<T>::EmergencySwitchIsOpen);
+
+        stake_pool = Self::update_pool(symbol, pool_index, stake_user.grade_index);
+        stake_pool_vec[stake_user.grade_index as usize] = stake_pool;
+        let total_weight = Self::total_weight(symbol, pool_index, stake_user.grade_index, &stake_pool);
+        let weight = Self::stake_weight(symbol, pool_index, who, stake_index, &stake_user);
+        let new_weight = Self::cal_stake_weight(symbol, pool_index, stake_user.lp_amount, stake_user.deposit_height.saturating_add(stake_pool.lp_locked_blocks));
+        let extra_rewards = Self::update_extra_rewards(symbol, pool_index, stake_user.grade_index, total_weight);
+        let (extra_reward_pays, extra_reward_debts) = Self::cal_extra_rewards(&extra_rewards, &Self::extra_reward_debts((symbol, pool_index, who, stake_index)), weight, new_weight);
+
+        let withdraw_reward = Self::cal_reward(weight, stake_pool.reward_per_share).saturating_sub(stake_user.reward_debt);
+        stake_user.reserved_lp_reward = stake_user.reserved_lp_reward.saturating_add(withdraw_reward);
+        stake_user.claimed_reward = stake_user.claimed_reward.saturating_add(withdraw_reward);
+        stake_user.reward_debt = Self::cal_reward(new_weight, stake_pool.reward_per_share);
+
+        let vesting_blocks = Self::reward_vesting_blocks((symbol, pool_index));
+        if withdraw_reward > 0 {
+            Self::pay_reward(who, RewardAsset::Fis, withdraw_reward, vesting_blocks)?;
+        }
+        Self::pay_extra_rewards(who, symbol, pool_index, stake_user.grade_index, stake_index, extra_reward_pays, vesting_blocks)?;
+        <StakeUsers<T>>::insert((symbol, pool_index, who, stake_index), stake_user.clone());
+        <ExtraRewardDebts<T>>::insert((symbol, pool_index, who, stake_index), extra_reward_debts);
+        <StakeWeights<T>>::insert((symbol, pool_index, who, stake_index), new_weight);
+        <TotalStakeWeights>::insert((symbol, pool_index, stake_user.grade_index), total_weight.saturating_sub(weight).saturating_add(new_weight));
+        <ExtraRewards>::insert((symbol, pool_index, stake_user.grade_index), extra_rewards);
+        <StakePools>::insert((symbol, pool_index), stake_pool_vec);
+        Self::deposit_event(RawEvent::ClaimReward(who.clone(), symbol, pool_index, stake_user.grade_index, stake_index, withdraw_reward));
+        Ok(())
+    }
+
+    /// Moves a stake out of its grade as a full withdraw paying its rewards, and into the target
+    /// grade as a deposit keeping its deposit height, which must be allowed. A stake moved to another pool index takes the
+    /// next stake index there.
+    fn do_migrate_stake(who: &T::AccountId, symbol: RSymbol, pool_index: u32, stake_index: u32, to_pool_index: u32, to_grade_index: u32) -> DispatchResult {
+        // moving into a grade deposits there, so it is gated as deposits are
+        ensure!(Self::lp_whitelist((symbol, who.clone())) || Self::lp_switch(symbol), Error::<T>::DepositNotAllowed);
+        let mut stake_user = Self::stake_users((symbol, pool_index, who, stake_index)).ok_or(Error::<T>::StakeUserNotExist)?;
+        let mut stake_pool_vec = Self::stake_pools((symbol, pool_index)).ok_or(Error::<T>::StakePoolNotExist)?;
+        let mut stake_pool = *stake_pool_vec.get(stake_user.grade_index as usize).ok_or(Error::<T>::GradeIndexOverflow)?;
+        let to_stake_pool = *Self::stake_pools((symbol, to_pool_index)).ok_or(Error::<T>::StakePoolNotExist)?
+            .get(to_grade_index as usize).ok_or(Error::<T>::GradeIndexOverflow)?;
+        let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
+        let lp_amount = stake_user.lp_amount;
+        let unlock_block = stake_user.deposit_height.saturating_add(stake_pool.lp_locked_blocks);
+        let to_unlock_block = stake_user.deposit_height.saturating_add(to_stake_pool.lp_locked_blocks);
+        let to_pool_du_block = to_stake_pool.total_reward.checked_div(to_stake_pool.reward_per_block).ok_or(Error::<T>::CalPoolDuBlockErr)?;
+
+        ensure!(to_pool_index != pool_index || to_grade_index != stake_user.grade_index, Error::<T>::MigrateToSameGrade);
+        ensure!(lp_amount > 0, Error::<T>::AmountZero);
+        ensure!(!stake_pool.emergency_switch && !to_stake_pool.emergency_switch, Error::<T>::EmergencySwitchIsOpen);
+        ensure!(to_unlock_block >= unlock_block || now_block > unlock_block, Error::<T>::LockShortened);
+        ensure!(to_unlock_block < to_stake_pool.start_block.saturating_add(to_pool_du_block as u32), Error::<T>::UnLockWillAfterEndErr);
+
+        stake_pool = Self::update_pool(symbol, pool_index, stake_user.grade_index);
+        let total_weight = Self::total_weight(symbol, pool_index, stake_user.grade_index, &stake_pool);
+        let weight = Self::stake_weight(symbol, pool_index, who, stake_index, &stake_user);
+        let extra_rewards = Self::update_extra_rewards(symbol, pool_index, stake_user.grade_index, total_weight);
+        let (extra_reward_pays, _) = Self::cal_extra_rewards(&extra_rewards, &Self::extra_reward_debts((symbol, pool_index, who, stake_index)), weight, 0);
+        let reward = Self::cal_reward(weight, stake_pool.reward_per_share).saturating_sub(stake_user.reward_debt);
+        stake_pool.total_stake_lp = stake_pool.total_stake_lp.saturating_sub(lp_amount);
+        stake_pool_vec[stake_user.grade_index as usize] = stake_pool;
+
+        let vesting_blocks = Self::reward_vesting_blocks((symbol, pool_index));
+        if reward > 0 {
+            Self::pay_reward(who, RewardAsset::Fis, reward, vesting_blocks)?;
+        }
+        Self::pay_extra_rewards(who, symbol, pool_index, stake_user.grade_index, stake_index, extra_reward_pays, vesting_blocks)?;
+        <TotalStakeWeights>::insert((symbol, pool_index, stake_user.grade_index), total_weight.saturating_sub(weight));
+        <ExtraRewards>::insert((symbol, pool_index, stake_user.grade_index), extra_rewards);
+        <StakePools>::insert((symbol, pool_index), stake_pool_vec);
+
+        // the source grade is stored, so the target reads it when both are in one pool index
+        let mut to_stake_pool_vec = Self::stake_pools((symbol, to_pool_index)).ok_or(Error::<T>::StakePoolNotExist)?;
+        let mut to_stake_pool = Self::update_pool(symbol, to_pool_index, to_grade_index);
+        let to_total_weight = Self::total_weight(symbol, to_pool_index, to_grade_index, &to_stake_pool);
+        let new_weight = Self::cal_stake_weight(symbol, to_pool_index, lp_amount, to_unlock_block);
+        let to_extra_rewards = Self::update_extra_rewards(symbol, to_pool_index, to_grade_index, to_total_weight);
+        let extra_reward_debts = to_extra_rewards.iter().map(|extra| Self::cal_reward(new_weight, extra.reward_per_share)).collect::<Vec<u128>>();
+        to_stake_pool.total_stake_lp = to_stake_pool.total_stake_lp.saturating_add(lp_amount);
+        to_stake_pool_vec[to_grade_index as usize] = to_stake_pool;
+
+        stake_user.grade_index = to_grade_index;
+        stake_user.reserved_lp_reward = stake_user.reserved_lp_reward.saturating_add(reward);
+        stake_user.claimed_reward = stake_user.claimed_reward.saturating_add(reward);
+        stake_user.reward_debt = Self::cal_reward(new_weight, to_stake_pool.reward_per_share);
+
+        let to_stake_index = if to_pool_index == pool_index {
+            stake_index
+        } else {
+            let auto_compound = <AutoCompound<T>>::take((symbol, pool_index, who, stake_index));
+            <StakeUsers<T>>::remove((symbol, pool_index, who, stake_index));
+            <ExtraRewardDebts<T>>::remove((symbol, pool_index, who, stake_index));
+            <StakeWeights<T>>::remove((symbol, pool_index, who, stake_index));
+            let to_stake_index = Self::user_stake_count((symbol, to_pool_index, who));
+            <UserStakeCount<T>>::insert((symbol, to_pool_index, who), to_stake_index + 1);
+            if auto_compound {
+                <AutoCompound<T>>::insert((symbol, to_pool_index, who, to_stake_index), true);
+            }
+            to_stake_index
+        };
+        <StakeUsers<T>>::insert((symbol, to_pool_index, who, to_stake_index), stake_user);
+        <ExtraRewardDebts<T>>::insert((symbol, to_pool_index, who, to_stake_index), extra_reward_debts);
+        <StakeWeights<T>>::insert((symbol, to_pool_index, who, to_stake_index), new_weight);
+        <TotalStakeWeights>::insert((symbol, to_pool_index, to_grade_index), to_total_weight.saturating_add(new_weight));
+        <ExtraRewards>::insert((symbol, to_pool_index, to_grade_index), to_extra_rewards);
+        <StakePools>::insert((symbol, to_pool_index), to_stake_pool_vec);
+        Self::deposit_event(RawEvent::MigrateStake(who.clone(), symbol, pool_index, stake_index, to_pool_index, to_grade_index, to_stake_index, lp_amount, reward));
+        Ok(())
+    }
+
+    /// reward weight of a stake, its lp amount if it was never weighted
+    pub fn stake_weight(symbol: RSymbol, pool_index: u32, who: &T::AccountId, stake_index: u32, stake_user: &StakeUser<T::AccountId>) -> u128 {
+        Self::stake_weights((symbol, pool_index, who, stake_index)).unwrap_or(stake_user.lp_amount)
+    }
+
+    /// total reward weight of a stake pool, its total stake lp if it was never weighted
+    pub fn total_weight(symbol: RSymbol, pool_index: u32, grade_index: u32, stake_pool: &StakePool) -> u128 {
+        Self::total_stake_weights((symbol, pool_index, grade_index)).unwrap_or(stake_pool.total_stake_lp)
+    }
+
+    /// reward weight of `lp_amount` locked until `unlock_block` under the lock boost of a pool index
+    pub fn cal_stake_weight(symbol: RSymbol, pool_index: u32, lp_amount: u128, unlock_block: u32) -> u128 {
+        match Self::lock_boosts((symbol, pool_index)) {
+            Some(boost) => {
+                let now_block = system::Module::<T>::block_number().saturated_into::<u32>();
+                Self::cal_boosted_weight(lp_amount, unlock_block.saturating_sub(now_block), &boost)
+            }
+            None => lp_amount,
+        }
+    }
+
+    // weight = lp_amount * (1 + max_boost / BOOST_BASE * min(remaining_blocks, max_lock_blocks) / max_lock_blocks)
+    pub fn cal_boosted_weight(lp_amount: u128, remaining_blocks: u32, boost: &LockBoost) -> u128 {
+        let remaining_blocks = remaining_blocks.min(boost.max_lock_blocks);
+        let extra = U512::from(lp_amount)
+            .saturating_mul(U512::from(boost.max_boost))
+            .saturating_mul(U512::from(remaining_blocks))
+            .checked_div(U512::from(BOOST_BASE).saturating_mul(U512::from(boost.max_lock_blocks)))
+            .unwrap_or(U512::zero());
+
+        lp_amount.saturating_add(Self::safe_to_u128(extra))
+    }
+
+    /// Compounds the fis reward of a stake: the module account adds it as liquidity and the lp
+    /// minted joins the stake. Extra rewards are paid as in `claim_reward`.
+    fn do_compound(who: &T::AccountId, symbol: RSymbol, pool_index: u32, stake_index: u32, min_units: u128) -> DispatchResult {
+        let mut stake_user = Self::stake_users((symbol, pool_index, who, stake_index)).ok_or(Error::<T>::StakeUserNotExist)?;
+        let mut stake_pool_vec = Self::stake_pools((symbol, pool_index)).ok_or(Error::<T>::StakePoolNotExist)?;
+        let mut stake_pool = *stake_pool_vec.get(stake_user.grade_index as usize).ok_or(Error::<T>::GradeIndexOverflow)?;
+
+        ensure!(!stake_pool.emergency_switch, Error::<T>::EmergencySwitchIsOpen);
+        // compounded lp leaves with the stake, which would skip the vesting of the reward
+        ensure!(Self::reward_vesting_blocks((symbol, pool_index)) == 0, Error::<T>::CompoundWhileVesting);
+
+        stake_pool = Self::update_pool(symbol, pool_index, stake_user.grade_index);
+        let total_weight = Self::total_weight(symbol, pool_index, stake_user.grade_index, &stake_pool);
+        let weight = Self::stake_weight(symbol, pool_index, who, stake_index, &stake_user);
+        let extra_rewards = Self::update_extra_rewards(symbol, pool_index, stake_user.grade_index, total_weight);
+        let reward = Self::cal_reward(weight, stake_pool.reward_per_share).saturating_sub(stake_user.reward_debt);
+        ensure!(reward > 0, Error::<T>::AmountZero);
+
+        let add_lp = T::LiquidityProvider::add_single_sided_liquidity(&Self::account_id(), symbol, reward, true, min_units)?;
+        let swap_pool = rdex_swap::SwapPools::get(symbol).ok_or(Error::<T>::SwapPoolNotExist)?;
+        let new_lp_amount = stake_user.lp_amount.saturating_add(add_lp);
+        let new_weight = Self::cal_stake_weight(symbol, pool_index, new_lp_amount, stake_user.deposit_height.saturating_add(stake_pool.lp_locked_blocks));
+        let (extra_reward_pays, extra_reward_debts) = Self::cal_extra_rewards(&extra_rewards, &Self::extra_reward_debts((symbol, pool_index, who, stake_index)), weight, new_weight);
+
+        stake_user.total_fis_value = stake_user.total_fis_value.saturating_add(Self::cal_share_amount(swap_pool.total_unit, add_lp, swap_pool.fis_balance));
+        stake_user.total_rtoken_value = stake_user.total_rtoken_value.saturating_add(Self::cal_share_amount(swap_pool.total_unit, add_lp, swap_pool.rtoken_balance));
+        stake_user.reserved_lp_reward = stake_user.reserved_lp_reward.saturating_add(reward);
+        stake_user.claimed_reward = stake_user.claimed_reward.saturating_add(reward);
+        stake_user.lp_amount = new_lp_amount;
+        stake_user.reward_debt = Self::cal_reward(new_weight, stake_pool.reward_per_share);
+        stake_pool.total_stake_lp = stake_pool.total_stake_lp.saturating_add(add_lp);
+        stake_pool_vec[stake_user.grade_index as usize] = stake_pool;
+
+        Self::pay_extra_rewards(who, symbol, pool_index, stake_user.grade_index, stake_index, extra_reward_pays, 0)?;
+        <StakeUsers<T>>::insert((symbol, pool_index, who, stake_index), stake_user.clone());
+        <ExtraRewardDebts<T>>::insert((symbol, pool_index, who, stake_index), extra_reward_debts);
+        <StakeWeights<T>>::insert((symbol, pool_index, who, stake_index), new_weight);
+        <TotalStakeWeights>::insert((symbol, pool_index, stake_user.grade_index), total_weight.saturating_sub(weight).saturating_add(new_weight));
+        <ExtraRewards>::insert((symbol, pool_index, stake_user.grade_index), extra_rewards);
+        <StakePools>::insert((symbol, pool_index), stake_pool_vec);
+        Self::deposit_event(RawEvent::Compound(who.clone(), symbol, pool_index, stake_user.grade_index, stake_index, reward, add_lp));
+        Ok(())
+    }
+
+    /// extra rewards of a stake pool brought up to the current block, `total_weight` must be the
+    /// reward weight staked since their last update
+    pub fn update_extra_rewards(symbol: RSymbol, index: u32, grade_index: u32, total_weight: u128) -> Vec<ExtraReward> {
+        let current_block_num = system::Module::<T>::block_number().saturated_into::<u32>();
+        let mut extra_rewards = Self::extra_rewards((symbol, index, grade_index));
+        for extra in extra_rewards.iter_mut() {
+            if current_block_num <= extra.last_reward_block {
+                continue;
+            }
+            if total_weight > 0 {
+                let reward = Self::get_pool_reward(extra.last_reward_block, current_block_num, extra.reward_per_block, extra.left_reward);
+                if reward > 0 {
+                    extra.left_reward = extra.left_reward.saturating_sub(reward);
+                    let add_reward_per_share = reward
+                        .saturating_mul(REWARD_FACTOR)
+                        .checked_div(total_weight)
+                        .unwrap_or(0);
+                    extra.reward_per_share = extra.reward_per_share.saturating_add(add_reward_per_share);
+                }
+            }
+            extra.last_reward_block = current_block_num;
+        }
+        extra_rewards
+    }
+
+    pub fn cal_reward(lp_amount: u128, reward_per_share: u128) -> u128 {
+        lp_amount.saturating_mul(reward_per_share).checked_div(REWARD_FACTOR).unwrap_or(0)
+    }
+
+    /// extra rewards earned by `lp_amount` since `debts`, and the debts of `new_lp_amount` afterwards.
+    /// debts missing for rewards added after the stake are 0, those rewards started at 0 per share
+    pub fn cal_extra_rewards(extra_rewards: &[ExtraReward], debts: &[u128], lp_amount: u128, new_lp_amount: u128) -> (Vec<(RewardAsset, u128)>, Vec<u128>) {
+        let mut pays = Vec::new();
+        let mut new_debts = Vec::new();
+        for (i, extra) in extra_rewards.iter().enumerate() {
+            let debt = debts.get(i).copied().unwrap_or(0);
+            pays.push((extra.asset, Self::cal_reward(lp_amount, extra.reward_per_share).saturating_sub(debt)));
+            new_debts.push(Self::cal_reward(new_lp_amount, extra.reward_per_share));
+        }
+        (pays, new_debts)
+    }
+
+    fn pay_extra_rewards(who: &T::AccountId, symbol: RSymbol, pool_index: u32, grade_index: u32, stake_index: u32, pays: Vec<(RewardAsset, u128)>, vesting_blocks: u32) -> DispatchResult {
+        for (asset, amount) in pays {
+            if amount > 0 {
+                Self::pay_reward(who, asset, amount, vesting_blocks)?;
+                Self::deposit_event(RawEvent::ClaimExtraReward(who.clone(), symbol, pool_index, grade_index, stake_index, asset, amount));
+            }
+        }
+        Ok(())
+    }
+
+    /// pays a claimed reward at once, or adds it to the vesting reward of the account in that asset.
+    /// the end of the vesting reward moves to the latest end of what it holds
+    pub fn pay_reward(who: &T::AccountId, asset: RewardAsset, amount: u128, vesting_blocks: u32) -> DispatchResult {
+        if vesting_blocks == 0 {
+            return Self::transfer_reward(who, asset, amount);
+        }
+        let current_block_num = system::Module::<T>::block_number().saturated_into::<u32>();
+        let mut vesting = Self::vesting_rewards((who, asset)).unwrap_or(VestingReward {
+            locked: 0,
+            vested: 0,
+            last_block: current_block_num,
+            end_block: current_block_num,
+        });
+        vesting.vest(current_block_num);
+        vesting.locked = vesting.locked.saturating_add(amount);
+        vesting.end_block = vesting.end_block.max(current_block_num.saturating_add(vesting_blocks));
+
+        let end_block = vesting.end_block;
+        <VestingRewards<T>>::insert((who, asset), vesting);
+        Self::deposit_event(RawEvent::VestReward(who.clone(), asset, amount, end_block));
+        Ok(())
+    }
+
+    fn transfer_reward(who: &T::AccountId, asset: RewardAsset, amount: u128) -> DispatchResult {
+        match asset {
+            RewardAsset::Fis => T::Currency::transfer(&Self::account_id(), who, amount.saturated_into(), KeepAlive),
+            RewardAsset::RToken(symbol) => T::RCurrency::transfer(&Self::account_id(), who, symbol, amount),
+            RewardAsset::XToken(symbol) => T::XCurrency::transfer(&Self::account_id(), who, symbol, amount),
+        }
+    }
+
+    /// fis paid from the guard reserve for the impermanent loss of withdrawing `lp_amount` of a stake,
+    /// valued at the rToken price averaged over `GuardPriceWindow` blocks so that it can not be
+    /// raised by moving the pool within a block. nothing is paid without such a price history.
+    pub fn cal_guard_amount(symbol: RSymbol, stake_user: &StakeUser<T::AccountId>, lp_amount: u128) -> u128 {
+        let swap_pool = match rdex_swap::SwapPools::get(symbol) {
+            Some(swap_pool) => swap_pool,
+            None => return 0,
+        };
+        let rtoken_price = match T::PriceOracle::rtoken_price(symbol, T::GuardPriceWindow::get()) {
+            Some(price) => price,
+            None => return 0,
+        };
+        let deposit_fis = Self::cal_share_amount(stake_user.lp_amount, lp_amount, stake_user.total_fis_value);
+        let deposit_rtoken = Self::cal_share_amount(stake_user.lp_amount, lp_amount, stake_user.total_rtoken_value);
+        let share_fis = Self::cal_share_amount(swap_pool.total_unit, lp_amount, swap_pool.fis_balance);
+        let share_rtoken = Self::cal_share_amount(swap_pool.total_unit, lp_amount, swap_pool.rtoken_balance);
+
+        // the price is rtoken_price / PRICE_BASE fis per rToken, same as a pool of those balances
+        let loss = Self::cal_impermanent_loss(deposit_fis, deposit_rtoken, share_fis, share_rtoken, rtoken_price, PRICE_BASE);
+        loss.min(Self::guard_reserve(symbol))
+    }
+
+    // F = fis balance of the pool, R = rToken balance of the pool, valued in fis at F / R
+    // hold = deposit_fis + deposit_rtoken * F / R
+    // lp = share_fis + share_rtoken * F / R
+    // loss = hold - lp, 0 if the lp is worth more
+    pub fn cal_impermanent_loss(
+        deposit_fis: u128,
+        deposit_rtoken: u128,
+        share_fis: u128,
+        share_rtoken: u128,
+        fis_balance: u128,
+        rtoken_balance: u128,
+    ) -> u128 {
+        if fis_balance == 0 || rtoken_balance == 0 {
+            return 0;
+        }
+        let f_capital = U512::from(fis_balance);
+        let r_capital = U512::from(rtoken_balance);
+        let hold = U512::from(deposit_fis)
+            .saturating_mul(r_capital)
+            .saturating_add(U512::from(deposit_rtoken).saturating_mul(f_capital));
+        let lp = U512::from(share_fis)
+            .saturating_mul(r_capital)
+            .saturating_add(U512::from(share_rtoken).saturating_mul(f_capital));
+        let loss = hold
+            .saturating_sub(lp)
+            .checked_div(r_capital)
+            .unwrap_or(U512::zero());
+
+        Self::safe_to_u128(loss)
+    }
+
     pub fn get_pool_reward(from: u32, to: u32, reward_per_block: u128, left_reward: u128) -> u128 {
         let duration = to.saturating_sub(from) as u128;
         let reward = duration.saturating_mul(reward_per_block);
@@ -42,6 +42,7 @@ parameter_types! {
     pub const MaximumBlockWeight: Weight = 1024;
     pub const MaximumBlockLength: u32 = 2 * 1024;
     pub const AvailableBlockRatio: Perbill = Perbill::one();
+    pub const GuardPriceWindow: u32 = 10;
 }
 // For testing the pallet, we construct most of a mock runtime. This means
 // first constructing a configuration type (`Test`) which `impl`s each of the
@@ -81,6 +82,11 @@ impl Trait for Test {
     type Event = ();
     type Currency = Balances;
     type LpCurrency = LpBalances;
+    type RCurrency = RBalances;
+    type XCurrency = XBalances;
+    type LiquidityProvider = RDexSwap;
+    type PriceOracle = RDexSwap;
+    type GuardPriceWindow = GuardPriceWindow;
 }
 
 impl rdex_swap::Trait for Test {
@@ -108,6 +114,14 @@ impl rtoken_balances::Trait for Test {
     type Event = ();
 }
 
+impl xtoken_balances::Trait for Test {
+    type Event = ();
+}
+
+impl rtoken_rate::Trait for Test {
+    type Event = ();
+}
+
 pub fn new_test_ext() -> sp_io::TestExternalities {
     let mut t = frame_system::GenesisConfig::default()
         .build_storage::<Test>()
@@ -135,6 +149,7 @@ pub type Balances = pallet_balances::Module<Test>;
 pub type LpBalances = rdex_balances::Module<Test>;
 pub type RDexSwap = rdex_swap::Module<Test>;
 pub type RBalances = rtoken_balances::Module<Test>;
+pub type XBalances = xtoken_balances::Module<Test>;
 
 pub struct ExistentialDeposit;
 impl Get<Balance> for ExistentialDeposit {
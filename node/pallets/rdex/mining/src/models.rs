@@ -1,6 +1,6 @@
 use codec::{Decode, Encode};
-use node_primitives::RSymbol;
-use sp_runtime::RuntimeDebug;
+use node_primitives::{RSymbol, XSymbol};
+use sp_runtime::{helpers_128bit::multiply_by_rational, RuntimeDebug};
 use sp_std::prelude::*;
 
 #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Copy)]
@@ -50,3 +50,76 @@ pub struct StakeUser<AccountId> {
     /// total claimed reward
     pub claimed_reward: u128,
 }
+
+/// Reward boost of remaining lock time: a stake weighs its lp plus up to `max_boost` of it, in
+/// proportion to the blocks left of its lock up to `max_lock_blocks`
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub struct LockBoost {
+    /// extra weight of one lp locked for `max_lock_blocks` or more, scaled by `BOOST_BASE`
+    pub max_boost: u128,
+    /// lock blocks of the full boost
+    pub max_lock_blocks: u32,
+}
+
+/// Asset a stake pool rewards in
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub enum RewardAsset {
+    /// fis
+    Fis,
+    /// rToken
+    RToken(RSymbol),
+    /// xToken
+    XToken(XSymbol),
+}
+
+/// Reward of a stake pool paid on top of its fis reward, accounted the same way
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub struct ExtraReward {
+    /// reward asset
+    pub asset: RewardAsset,
+    /// reward per block
+    pub reward_per_block: u128,
+    /// total reward
+    pub total_reward: u128,
+    /// left reward
+    pub left_reward: u128,
+    /// last reward block
+    pub last_reward_block: u32,
+    /// reward per share
+    pub reward_per_share: u128,
+}
+
+/// Claimed rewards of an account in one asset, vesting linearly
+#[derive(PartialEq, Eq, Clone, Default, Encode, Decode, RuntimeDebug)]
+pub struct VestingReward {
+    /// amount not vested yet
+    pub locked: u128,
+    /// amount vested and not paid yet
+    pub vested: u128,
+    /// block up to which `locked` has been vested
+    pub last_block: u32,
+    /// block at which `locked` is fully vested
+    pub end_block: u32,
+}
+
+impl VestingReward {
+    /// Moves the part of `locked` vested up to `now` into `vested`
+    pub fn vest(&mut self, now: u32) {
+        if now >= self.end_block {
+            self.vested = self.vested.saturating_add(self.locked);
+            self.locked = 0;
+        } else if now > self.last_block {
+            let part = multiply_by_rational(
+                self.locked,
+                (now - self.last_block) as u128,
+                (self.end_block - self.last_block) as u128,
+            )
+            .unwrap_or(self.locked);
+            self.vested = self.vested.saturating_add(part);
+            self.locked = self.locked.saturating_sub(part);
+        }
+        if now > self.last_block {
+            self.last_block = now;
+        }
+    }
+}
@@ -3,7 +3,7 @@ use super::*;
 use crate::sp_api_hidden_includes_decl_storage::hidden_include::traits::OnFinalize;
 use crate::sp_api_hidden_includes_decl_storage::hidden_include::traits::OnInitialize;
 use frame_support::{assert_err, assert_ok, error as frame_support_error};
-use node_primitives::RSymbol;
+use node_primitives::{RSymbol, XSymbol};
 use rtoken_balances::traits::Currency;
 use sp_core::U256;
 
@@ -873,3 +873,443 @@ fn claim_reward_should_work_with_multi() {
         );
     });
 }
+
+fn setup_stake_pool(symbol: RSymbol) {
+    assert_ok!(RBalances::mint(&U256::from(42), symbol, 100));
+    assert_ok!(RDexSwap::create_pool(Origin::root(), U256::from(42), symbol, 20, 10));
+    assert_ok!(RBalances::mint(&U256::from(1), symbol, 100));
+    assert_ok!(RDexSwap::add_lp_to_whitelist(Origin::root(), symbol, U256::from(1)));
+    assert_ok!(RDexSwap::add_liquidity(Origin::signed(U256::from(1)), symbol, 20, 10));
+    assert_ok!(RDexMining::add_lp_to_whitelist(Origin::root(), symbol, U256::from(1)));
+
+    assert_ok!(RDexMining::increase_pool_index(Origin::root(), symbol));
+    // start block 2, lp locked blocks 10, reward per block 10, total reward 200
+    assert_ok!(RDexMining::add_pool(Origin::root(), symbol, 0, 2, 10, 10, 200, false));
+    assert_ok!(Balances::transfer(Origin::signed(U256::from(2)), RDexMining::account_id(), 200));
+}
+
+#[test]
+fn claim_reward_should_pay_extra_rewards() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        let reth = RewardAsset::RToken(RSymbol::RETH);
+        let wra = RewardAsset::XToken(XSymbol::WRA);
+        setup_stake_pool(symbol);
+
+        assert_err!(
+            RDexMining::add_extra_reward(Origin::root(), symbol, 0, 1, reth, 5, 100),
+            Error::<Test>::GradeIndexOverflow
+        );
+        assert_ok!(RDexMining::add_extra_reward(Origin::root(), symbol, 0, 0, reth, 5, 100));
+        assert_ok!(RBalances::mint(&RDexMining::account_id(), RSymbol::RETH, 100));
+        assert_ok!(XBalances::mint(&RDexMining::account_id(), XSymbol::WRA, 40));
+
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 0, 1));
+        assert_eq!(RDexMining::extra_reward_debts((symbol, 0, &U256::from(1), 0)), vec![0]);
+
+        // added after the deposit, earned from its own start only
+        run_to_block(7);
+        assert_ok!(RDexMining::add_extra_reward(Origin::root(), symbol, 0, 0, wra, 2, 40));
+
+        run_to_block(12);
+        assert_ok!(RDexMining::claim_reward(Origin::signed(U256::from(1)), symbol, 0, 0));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 100);
+        assert_eq!(RBalances::free_balance(&U256::from(1), RSymbol::RETH), 50);
+        assert_eq!(XBalances::free_balance(&U256::from(1), XSymbol::WRA), 10);
+
+        let extra_rewards = RDexMining::extra_rewards((symbol, 0, 0));
+        assert_eq!(extra_rewards[0].left_reward, 50);
+        assert_eq!(extra_rewards[1].left_reward, 30);
+        assert_eq!(
+            RDexMining::extra_reward_debts((symbol, 0, &U256::from(1), 0)),
+            vec![50, 10]
+        );
+
+        // nothing more within the same block
+        assert_ok!(RDexMining::claim_reward(Origin::signed(U256::from(1)), symbol, 0, 0));
+        assert_eq!(RBalances::free_balance(&U256::from(1), RSymbol::RETH), 50);
+        assert_eq!(XBalances::free_balance(&U256::from(1), XSymbol::WRA), 10);
+
+        run_to_block(14);
+        assert_ok!(RDexMining::withdraw(Origin::signed(U256::from(1)), symbol, 0, 0, 1));
+        assert_eq!(RBalances::free_balance(&U256::from(1), RSymbol::RETH), 60);
+        assert_eq!(XBalances::free_balance(&U256::from(1), XSymbol::WRA), 14);
+        assert_eq!(RDexMining::extra_reward_debts((symbol, 0, &U256::from(1), 0)), vec![0, 0]);
+    });
+}
+
+#[test]
+fn claimed_reward_should_vest() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        let reth = RewardAsset::RToken(RSymbol::RETH);
+        setup_stake_pool(symbol);
+        assert_ok!(RDexMining::add_extra_reward(Origin::root(), symbol, 0, 0, reth, 5, 100));
+        assert_ok!(RBalances::mint(&RDexMining::account_id(), RSymbol::RETH, 100));
+        assert_ok!(RDexMining::set_reward_vesting(Origin::root(), symbol, 0, 10));
+
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 0, 1));
+        run_to_block(12);
+        assert_ok!(RDexMining::claim_reward(Origin::signed(U256::from(1)), symbol, 0, 0));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90);
+        assert_eq!(
+            RDexMining::vesting_rewards((&U256::from(1), RewardAsset::Fis)),
+            Some(VestingReward { locked: 100, vested: 0, last_block: 12, end_block: 22 })
+        );
+        assert_err!(
+            RDexMining::claim_vested_reward(Origin::signed(U256::from(1)), RewardAsset::Fis),
+            Error::<Test>::NoVestedReward
+        );
+
+        run_to_block(17);
+        assert_ok!(RDexMining::claim_vested_reward(Origin::signed(U256::from(1)), RewardAsset::Fis));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 50);
+
+        // claiming again merges into the schedule and moves its end
+        assert_ok!(RDexMining::claim_reward(Origin::signed(U256::from(1)), symbol, 0, 0));
+        assert_eq!(
+            RDexMining::vesting_rewards((&U256::from(1), RewardAsset::Fis)),
+            Some(VestingReward { locked: 100, vested: 0, last_block: 17, end_block: 27 })
+        );
+
+        run_to_block(27);
+        assert_ok!(RDexMining::claim_vested_reward(Origin::signed(U256::from(1)), RewardAsset::Fis));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 150);
+        assert_eq!(RDexMining::vesting_rewards((&U256::from(1), RewardAsset::Fis)), None);
+
+        assert_ok!(RDexMining::claim_vested_reward(Origin::signed(U256::from(1)), reth));
+        assert_eq!(RBalances::free_balance(&U256::from(1), RSymbol::RETH), 75);
+    });
+}
+
+#[test]
+fn compound_should_work() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        setup_stake_pool(symbol);
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 0, 10));
+
+        run_to_block(12);
+        assert_err!(
+            RDexMining::compound(Origin::signed(U256::from(1)), symbol, 0, 0, 17),
+            rdex_swap::Error::<Test>::LessThanMinUnits
+        );
+        assert_ok!(RDexMining::compound(Origin::signed(U256::from(1)), symbol, 0, 0, 16));
+
+        // 8 of the 100 fis reward is swapped, 92 fis and 8 rtoken are added to the 20 fis/40 rtoken pool
+        let swap_pool = RDexSwap::swap_pools(symbol).unwrap();
+        assert_eq!(swap_pool.fis_balance, 120);
+        assert_eq!(swap_pool.rtoken_balance, 40);
+        assert_eq!(swap_pool.total_unit, 36);
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90);
+        assert_eq!(Balances::free_balance(&RDexMining::account_id()), 100);
+        assert_eq!(LpBalances::free_balance(&RDexMining::account_id(), symbol), 26);
+
+        let stake_user = RDexMining::stake_users((symbol, 0, &U256::from(1), 0)).unwrap();
+        assert_eq!(stake_user.lp_amount, 26);
+        assert_eq!(stake_user.reward_debt, 260);
+        assert_eq!(stake_user.total_fis_value, 10 + 16 * 120 / 36);
+        assert_eq!(stake_user.total_rtoken_value, 20 + 16 * 40 / 36);
+        assert_eq!(stake_user.claimed_reward, 100);
+        assert_eq!(RDexMining::user_stake_count((symbol, 0, &U256::from(1))), 1);
+        assert_eq!(RDexMining::stake_pools((symbol, 0)).unwrap()[0].total_stake_lp, 26);
+
+        assert_err!(
+            RDexMining::compound(Origin::signed(U256::from(1)), symbol, 0, 0, 0),
+            Error::<Test>::AmountZero
+        );
+
+        // keepers compound stakes that opted in
+        run_to_block(14);
+        assert_err!(
+            RDexMining::compound_for(Origin::signed(U256::from(3)), U256::from(1), symbol, 0, 0, 0),
+            Error::<Test>::NotCompoundKeeper
+        );
+        assert_ok!(RDexMining::set_compound_keeper(Origin::root(), U256::from(3), true));
+        assert_err!(
+            RDexMining::compound_for(Origin::signed(U256::from(3)), U256::from(1), symbol, 0, 0, 0),
+            Error::<Test>::AutoCompoundNotEnabled
+        );
+        assert_ok!(RDexMining::set_auto_compound(Origin::signed(U256::from(1)), symbol, 0, 0, true));
+        assert_ok!(RDexMining::compound_for(Origin::signed(U256::from(3)), U256::from(1), symbol, 0, 0, 0));
+
+        let stake_user = RDexMining::stake_users((symbol, 0, &U256::from(1), 0)).unwrap();
+        assert!(stake_user.lp_amount > 26);
+        assert_eq!(stake_user.claimed_reward, 100 + 19);
+        assert_eq!(LpBalances::free_balance(&RDexMining::account_id(), symbol), stake_user.lp_amount);
+        assert_eq!(
+            stake_user.reward_debt,
+            stake_user.lp_amount * RDexMining::stake_pools((symbol, 0)).unwrap()[0].reward_per_share / REWARD_FACTOR
+        );
+
+        // rewards under vesting are not compounded
+        assert_ok!(RDexMining::set_reward_vesting(Origin::root(), symbol, 0, 10));
+        run_to_block(16);
+        assert_err!(
+            RDexMining::compound(Origin::signed(U256::from(1)), symbol, 0, 0, 0),
+            Error::<Test>::CompoundWhileVesting
+        );
+    });
+}
+
+#[test]
+fn cal_impermanent_loss_should_work() {
+    // 10 fis + 10 rtoken deposited at 1:1, the price of rtoken goes 4x along x * y = k
+    assert_eq!(RDexMining::cal_impermanent_loss(10, 10, 20, 5, 200, 50), 10);
+    // price back where it was, no loss
+    assert_eq!(RDexMining::cal_impermanent_loss(10, 10, 10, 10, 100, 100), 0);
+    // fees made the lp worth more than holding
+    assert_eq!(RDexMining::cal_impermanent_loss(10, 10, 11, 11, 100, 100), 0);
+    assert_eq!(RDexMining::cal_impermanent_loss(10, 10, 20, 5, 0, 50), 0);
+    assert_eq!(
+        RDexMining::cal_impermanent_loss(u128::max_value(), u128::max_value(), 0, 0, u128::max_value(), 1),
+        u128::max_value()
+    );
+}
+
+#[test]
+fn withdraw_should_pay_guard() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        setup_stake_pool(symbol);
+        // grade 1 guards impermanent loss
+        assert_ok!(RDexMining::add_pool(Origin::root(), symbol, 0, 2, 10, 10, 200, true));
+        assert_ok!(RDexMining::set_guard_line(Origin::root(), symbol, 0, 15));
+        assert_ok!(RDexMining::set_guard_reserve(Origin::root(), symbol, 100));
+
+        // 10 of 20 units, worth 10 fis and 20 rtoken
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 1, 10));
+        let stake_user = RDexMining::stake_users((symbol, 0, &U256::from(1), 0)).unwrap();
+        assert_eq!((stake_user.total_fis_value, stake_user.total_rtoken_value), (10, 20));
+
+        // rtoken price in fis goes from 0.5 to 8
+        RDexSwap::help_set_pool(symbol, rdex_swap::SwapPool { symbol, fis_balance: 80, rtoken_balance: 10, total_unit: 20 });
+
+        // before the guard line
+        run_to_block(12);
+        assert_ok!(RDexMining::withdraw(Origin::signed(U256::from(1)), symbol, 0, 0, 5));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 100);
+        assert_eq!(RDexMining::guard_reserve(symbol), 100);
+
+        // deposited 5 fis + 10 rtoken, now 20 fis + 2 rtoken: (5 * 10 + 10 * 80 - 20 * 10 - 2 * 80) / 10 = 49
+        run_to_block(16);
+        assert_ok!(RDexMining::withdraw(Origin::signed(U256::from(1)), symbol, 0, 0, 5));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 140 + 49);
+        assert_eq!(RDexMining::guard_reserve(symbol), 51);
+        assert_eq!(Balances::free_balance(&RDexMining::account_id()), 200 - 140 - 49);
+    });
+}
+
+#[test]
+fn guard_should_be_capped_by_reserve() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        setup_stake_pool(symbol);
+        assert_ok!(RDexMining::add_pool(Origin::root(), symbol, 0, 2, 10, 10, 200, true));
+        assert_ok!(RDexMining::set_guard_line(Origin::root(), symbol, 0, 0));
+        assert_ok!(RDexMining::set_guard_reserve(Origin::root(), symbol, 30));
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 1, 10));
+        RDexSwap::help_set_pool(symbol, rdex_swap::SwapPool { symbol, fis_balance: 80, rtoken_balance: 10, total_unit: 20 });
+
+        run_to_block(12);
+        let stake_user = RDexMining::stake_users((symbol, 0, &U256::from(1), 0)).unwrap();
+        // (10 * 10 + 20 * 80 - 40 * 10 - 5 * 80) / 10 = 90
+        assert_eq!(RDexMining::cal_guard_amount(symbol, &stake_user, 10), 30);
+        assert_ok!(RDexMining::withdraw(Origin::signed(U256::from(1)), symbol, 0, 0, 10));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 100 + 30);
+        assert_eq!(RDexMining::guard_reserve(symbol), 0);
+    });
+}
+
+#[test]
+fn guard_should_value_loss_at_average_price() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        setup_stake_pool(symbol);
+        assert_ok!(RDexMining::add_pool(Origin::root(), symbol, 0, 2, 10, 10, 200, true));
+        assert_ok!(RDexMining::set_guard_line(Origin::root(), symbol, 0, 0));
+        assert_ok!(RDexMining::set_guard_reserve(Origin::root(), symbol, 100_000));
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 1, 10));
+        RDexSwap::help_set_pool(symbol, rdex_swap::SwapPool { symbol, fis_balance: 80, rtoken_balance: 10, total_unit: 20 });
+        let stake_user = RDexMining::stake_users((symbol, 0, &U256::from(1), 0)).unwrap();
+
+        // no price history as old as the window
+        run_to_block(10);
+        assert_eq!(RDexMining::cal_guard_amount(symbol, &stake_user, 10), 0);
+
+        run_to_block(12);
+        assert_eq!(RDexMining::cal_guard_amount(symbol, &stake_user, 10), 90);
+
+        // the price moved within the block only counts from the next block on
+        assert_ok!(RDexSwap::swap(Origin::signed(U256::from(1)), symbol, 1, 1, false));
+        RDexSwap::help_set_pool(symbol, rdex_swap::SwapPool { symbol, fis_balance: 800, rtoken_balance: 1, total_unit: 20 });
+        // at the spot price: (10 * 1 + 20 * 800 - 400 * 1 - 0 * 800) / 1 = 15610
+        // at the average price of 8: 10 + 20 * 8 - 400 - 0 * 8 < 0
+        assert_eq!(RDexMining::cal_guard_amount(symbol, &stake_user, 10), 0);
+    });
+}
+
+#[test]
+fn cal_boosted_weight_should_work() {
+    let boost = LockBoost { max_boost: BOOST_BASE, max_lock_blocks: 10 };
+    assert_eq!(RDexMining::cal_boosted_weight(10, 10, &boost), 20);
+    assert_eq!(RDexMining::cal_boosted_weight(10, 5, &boost), 15);
+    assert_eq!(RDexMining::cal_boosted_weight(10, 0, &boost), 10);
+    // capped at max lock blocks
+    assert_eq!(RDexMining::cal_boosted_weight(10, 100, &boost), 20);
+    let boost = LockBoost { max_boost: BOOST_BASE * 3 / 2, max_lock_blocks: 4 };
+    assert_eq!(RDexMining::cal_boosted_weight(100, 1, &boost), 137);
+    assert_eq!(RDexMining::cal_boosted_weight(u128::max_value(), 4, &boost), u128::max_value());
+}
+
+#[test]
+fn lock_boost_should_weight_rewards() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        setup_stake_pool(symbol);
+        assert_ok!(RBalances::mint(&U256::from(3), symbol, 100));
+        assert_ok!(RDexSwap::add_lp_to_whitelist(Origin::root(), symbol, U256::from(3)));
+        assert_ok!(RDexSwap::add_liquidity(Origin::signed(U256::from(3)), symbol, 20, 10));
+        assert_ok!(RDexMining::add_lp_to_whitelist(Origin::root(), symbol, U256::from(3)));
+
+        assert_err!(
+            RDexMining::set_lock_boost(Origin::root(), symbol, 1, BOOST_BASE, 10),
+            Error::<Test>::StakePoolNotExist
+        );
+        // twice the weight with 10 blocks of lock left
+        assert_ok!(RDexMining::set_lock_boost(Origin::root(), symbol, 0, BOOST_BASE, 10));
+
+        run_to_block(1);
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 0, 10));
+        assert_eq!(RDexMining::stake_weights((symbol, 0, &U256::from(1), 0)), Some(20));
+
+        // anyone may bring the weight down to the 5 blocks left, the reward goes to the owner
+        run_to_block(6);
+        assert_ok!(RDexMining::kick(Origin::signed(U256::from(3)), U256::from(1), symbol, 0, 0, 0));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 40);
+        assert_eq!(RDexMining::stake_weights((symbol, 0, &U256::from(1), 0)), Some(15));
+
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(3)), symbol, 0, 0, 10));
+        assert_eq!(RDexMining::stake_weights((symbol, 0, &U256::from(3), 0)), Some(20));
+        assert_eq!(RDexMining::total_stake_weights((symbol, 0, 0)), Some(35));
+        assert_eq!(RDexMining::stake_pools((symbol, 0)).unwrap()[0].total_stake_lp, 20);
+
+        // 70 shared 15 : 20
+        run_to_block(13);
+        assert_ok!(RDexMining::claim_reward(Origin::signed(U256::from(1)), symbol, 0, 0));
+        assert_ok!(RDexMining::claim_reward(Origin::signed(U256::from(3)), symbol, 0, 0));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 40 + 30);
+        assert_eq!(Balances::free_balance(&U256::from(3)), 990 + 40);
+        // the lock of user 1 is over, user 3 has 3 blocks left
+        assert_eq!(RDexMining::total_stake_weights((symbol, 0, 0)), Some(10 + 13));
+    });
+}
+
+#[test]
+fn migrate_stake_to_longer_grade_should_work() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        setup_stake_pool(symbol);
+        // grade 1 locks 15 blocks
+        assert_ok!(RDexMining::add_pool(Origin::root(), symbol, 0, 2, 15, 10, 200, false));
+        run_to_block(1);
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 0, 10));
+
+        run_to_block(5);
+        assert_err!(
+            RDexMining::migrate_stake(Origin::signed(U256::from(1)), symbol, 0, 0, 0, 0),
+            Error::<Test>::MigrateToSameGrade
+        );
+        assert_err!(
+            RDexMining::migrate_stake(Origin::signed(U256::from(1)), symbol, 0, 0, 0, 2),
+            Error::<Test>::GradeIndexOverflow
+        );
+        assert_ok!(RDexMining::migrate_stake(Origin::signed(U256::from(1)), symbol, 0, 0, 0, 1));
+        // rewards of grade 0 are paid on the way out
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 30);
+        let stake_user = RDexMining::stake_users((symbol, 0, &U256::from(1), 0)).unwrap();
+        assert_eq!(stake_user.grade_index, 1);
+        assert_eq!(stake_user.deposit_height, 1);
+        assert_eq!(stake_user.lp_amount, 10);
+        assert_eq!(stake_user.claimed_reward, 30);
+        let stake_pools = RDexMining::stake_pools((symbol, 0)).unwrap();
+        assert_eq!((stake_pools[0].total_stake_lp, stake_pools[1].total_stake_lp), (0, 10));
+        assert_eq!(RDexMining::user_stake_count((symbol, 0, &U256::from(1))), 1);
+
+        // no way back to the shorter lock
+        assert_err!(
+            RDexMining::migrate_stake(Origin::signed(U256::from(1)), symbol, 0, 0, 0, 0),
+            Error::<Test>::LockShortened
+        );
+        run_to_block(12);
+        assert_err!(
+            RDexMining::withdraw(Origin::signed(U256::from(1)), symbol, 0, 0, 10),
+            Error::<Test>::LpStillLocked
+        );
+        run_to_block(17);
+        assert_ok!(RDexMining::withdraw(Origin::signed(U256::from(1)), symbol, 0, 0, 10));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 30 + 120);
+        assert_eq!(LpBalances::free_balance(&U256::from(1), symbol), 10);
+    });
+}
+
+#[test]
+fn migrate_stake_to_new_pool_index_should_work() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        setup_stake_pool(symbol);
+        run_to_block(1);
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 0, 10));
+        assert_ok!(RDexMining::set_auto_compound(Origin::signed(U256::from(1)), symbol, 0, 0, true));
+
+        assert_ok!(RDexMining::increase_pool_index(Origin::root(), symbol));
+        assert_err!(
+            RDexMining::migrate_stake(Origin::signed(U256::from(1)), symbol, 0, 0, 1, 0),
+            Error::<Test>::GradeIndexOverflow
+        );
+        assert_ok!(RDexMining::add_pool(Origin::root(), symbol, 1, 2, 10, 10, 200, false));
+
+        run_to_block(4);
+        assert_ok!(RDexMining::migrate_stake(Origin::signed(U256::from(1)), symbol, 0, 0, 1, 0));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 20);
+        assert_eq!(RDexMining::stake_users((symbol, 0, &U256::from(1), 0)), None);
+        assert!(!RDexMining::auto_compound((symbol, 0, &U256::from(1), 0)));
+        let stake_user = RDexMining::stake_users((symbol, 1, &U256::from(1), 0)).unwrap();
+        assert_eq!(stake_user.grade_index, 0);
+        assert_eq!(stake_user.lp_amount, 10);
+        assert_eq!(stake_user.reserved_lp_reward, 20);
+        assert!(RDexMining::auto_compound((symbol, 1, &U256::from(1), 0)));
+        assert_eq!(RDexMining::user_stake_count((symbol, 1, &U256::from(1))), 1);
+        assert_eq!(RDexMining::stake_pools((symbol, 0)).unwrap()[0].total_stake_lp, 0);
+        assert_eq!(RDexMining::stake_pools((symbol, 1)).unwrap()[0].total_stake_lp, 10);
+
+        // earns in the new pool index from the migration on
+        run_to_block(12);
+        assert_ok!(RDexMining::withdraw(Origin::signed(U256::from(1)), symbol, 1, 0, 10));
+        assert_eq!(Balances::free_balance(&U256::from(1)), 90 + 20 + 80);
+    });
+}
+
+#[test]
+fn migrate_stake_should_be_allowed_as_deposits() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        setup_stake_pool(symbol);
+        assert_ok!(RDexMining::add_pool(Origin::root(), symbol, 0, 2, 15, 10, 200, false));
+        run_to_block(1);
+        assert_ok!(RDexMining::deposit(Origin::signed(U256::from(1)), symbol, 0, 0, 10));
+
+        // out of the whitelist with the lp switch closed
+        assert_ok!(RDexMining::remove_lp_from_whitelist(Origin::root(), symbol, U256::from(1)));
+        run_to_block(5);
+        assert_err!(
+            RDexMining::migrate_stake(Origin::signed(U256::from(1)), symbol, 0, 0, 0, 1),
+            Error::<Test>::DepositNotAllowed
+        );
+        assert_eq!(RDexMining::stake_users((symbol, 0, &U256::from(1), 0)).unwrap().grade_index, 0);
+
+        assert_ok!(RDexMining::toggle_lp_switch(Origin::root(), symbol));
+        assert_ok!(RDexMining::migrate_stake(Origin::signed(U256::from(1)), symbol, 0, 0, 0, 1));
+        assert_eq!(RDexMining::stake_users((symbol, 0, &U256::from(1), 0)).unwrap().grade_index, 1);
+    });
+}
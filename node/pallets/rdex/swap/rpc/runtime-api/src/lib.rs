@@ -0,0 +1,24 @@
+// Copyright 2019-2021 Stafi Protocol.
+// This file is part of Stafi.
+
+// Stafi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Stafi.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime API definition for the rdex swap pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use node_primitives::RSymbol;
+pub use rdex_swap::TwapPrice;
+
+sp_api::decl_runtime_apis! {
+	pub trait RDexSwapApi {
+		/// Time weighted average prices of the pool of `symbol` over at least `window` blocks
+		fn twap(symbol: RSymbol, window: u32) -> Option<TwapPrice>;
+	}
+}
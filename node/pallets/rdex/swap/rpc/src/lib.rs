@@ -0,0 +1,78 @@
+// Copyright 2019-2021 Stafi Protocol.
+// This file is part of Stafi.
+
+// Stafi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Stafi.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC interface for the rdex swap pallet.
+
+use std::sync::Arc;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use node_primitives::RSymbol;
+pub use rdex_swap_rpc_runtime_api::{RDexSwapApi as RDexSwapRuntimeApi, TwapPrice};
+
+#[rpc]
+pub trait RDexSwapApi<BlockHash> {
+	/// Time weighted average prices of the pool of `symbol` over at least `window` blocks
+	#[rpc(name = "rdexSwap_twap")]
+	fn twap(&self, symbol: RSymbol, window: u32, at: Option<BlockHash>) -> Result<Option<TwapPrice>>;
+}
+
+/// A struct that implements the [`RDexSwapApi`].
+pub struct RDexSwap<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> RDexSwap<C, B> {
+	/// Create new `RDexSwap` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+impl<C, Block> RDexSwapApi<<Block as BlockT>::Hash> for RDexSwap<C, Block>
+where
+	Block: BlockT,
+	C: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: RDexSwapRuntimeApi<Block>,
+{
+	fn twap(
+		&self,
+		symbol: RSymbol,
+		window: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<TwapPrice>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.twap(&at, symbol, window).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(Error::RuntimeError.into()),
+			message: "Unable to query twap.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})
+	}
+}
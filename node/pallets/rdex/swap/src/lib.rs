@@ -2,8 +2,9 @@
 
 use frame_support::{
     decl_error, decl_event, decl_module, decl_storage,
-    dispatch::DispatchResult,
+    dispatch::{DispatchError, DispatchResult},
     ensure,
+    storage::IterableStorageDoubleMap,
     traits::{Currency, ExistenceRequirement::KeepAlive},
 };
 use sp_std::prelude::*;
@@ -14,9 +15,10 @@ use rdex_balances::traits::Currency as LpCurrency;
 use rtoken_balances::traits::Currency as RCurrency;
 use sp_runtime::{
     traits::{AccountIdConversion, SaturatedConversion},
-    ModuleId,
+    ModuleId, Perbill,
 };
-pub trait Trait: system::Trait {
+use rtoken_rate::{RateType, RATEBASE};
+pub trait Trait: system::Trait + rtoken_rate::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
     /// currency of rtoken
     type RCurrency: RCurrency<Self::AccountId>;
@@ -28,6 +30,8 @@ pub trait Trait: system::Trait {
 
 pub mod models;
 pub use models::*;
+pub mod traits;
+pub use traits::{LiquidityProvider, PriceOracle};
 use sp_core::U512;
 
 #[cfg(test)]
@@ -36,6 +40,14 @@ mod mock;
 mod tests;
 
 const MODULE_ID: ModuleId = ModuleId(*b"rdx/swap");
+/// max amplification coefficient of stableswap pools
+pub const MAX_AMPLIFICATION: u128 = 1_000_000;
+/// max rounds of newton's method when solving the stableswap invariant
+const STABLESWAP_ITERATIONS: usize = 255;
+/// scale of prices kept by the oracle
+pub const PRICE_BASE: u128 = 1_000_000_000_000;
+/// slots of price observations kept per pool
+pub const OBSERVATION_SIZE: u32 = 64;
 
 decl_event! {
     pub enum Event<T> where
@@ -49,6 +61,20 @@ decl_event! {
         AddLiquidity(AccountId, RSymbol, u128, u128, u128, u128, u128, u128),
         /// RemoveLiquidity: (account, symbol, rm unit, swap unit, rm fis amount, rm rToken amount, input is fis, fis balance, rtoken balance)
         RemoveLiquidity(AccountId, RSymbol, u128, u128, u128, u128, bool, u128, u128),
+        /// PoolCurveSet: (symbol, curve)
+        PoolCurveSet(RSymbol, SwapCurve),
+        /// SwapFee: (symbol, input amount, input is fis, lp fee, protocol fee), fees are in the output token
+        SwapFee(RSymbol, u128, bool, u128, u128),
+        /// ProtocolFeeShareSet: (symbol, share of fees sent to the protocol)
+        ProtocolFeeShareSet(RSymbol, Perbill),
+        /// ProtocolFeeReceiverSet: (receiver)
+        ProtocolFeeReceiverSet(AccountId),
+        /// ProtocolFeeClaimed: (symbol, receiver, fis amount, rToken amount)
+        ProtocolFeeClaimed(RSymbol, AccountId, u128, u128),
+        /// ObservationPeriodSet: (min blocks between two price observations)
+        ObservationPeriodSet(u32),
+        /// ZapIn: (account, symbol, input amount, input is fis, swap amount, swap result, new total unit, add lp unit, fis balance, rtoken balance)
+        ZapIn(AccountId, RSymbol, u128, bool, u128, u128, u128, u128, u128, u128),
     }
 }
 
@@ -69,6 +95,13 @@ decl_error! {
         AddLpUnitIsZero,
         PoolOneSideZero,
         AddLpNotAllowed,
+        InvalidAmplification,
+        /// the stableswap peg is the rtoken rate, which is in FIS only for rFIS
+        StableSwapNotSupported,
+        ProtocolFeeReceiverNotSet,
+        NoProtocolFee,
+        InvalidObservationPeriod,
+        LessThanMinUnits,
     }
 }
 
@@ -80,6 +113,22 @@ decl_storage! {
         pub LpWhitelist get(fn lp_whitelist): map hasher(blake2_128_concat) (RSymbol, T::AccountId) => bool = false;
         /// lp switch
         pub LpSwitch get(fn lp_switch): map hasher(blake2_128_concat)  RSymbol => bool = false;
+        /// pricing curve of pools
+        pub PoolCurves get(fn pool_curves): map hasher(blake2_128_concat) RSymbol => SwapCurve;
+        /// share of swap fees sent to the protocol, the rest stays in the pool for lps
+        pub ProtocolFeeShares get(fn protocol_fee_shares): map hasher(blake2_128_concat) RSymbol => Perbill;
+        /// protocol fees accrued in the module account and not claimed yet
+        pub ProtocolFees get(fn protocol_fees): map hasher(blake2_128_concat) RSymbol => ProtocolFee;
+        /// account receiving protocol fees
+        pub ProtocolFeeReceiver get(fn protocol_fee_receiver): Option<T::AccountId>;
+        /// price accumulators of pools, brought up to date before every change of a pool
+        pub PriceCumulatives get(fn price_cumulatives): map hasher(blake2_128_concat) RSymbol => Option<PriceObservation<T::BlockNumber>>;
+        /// ring buffer of past price accumulators of pools
+        pub PriceObservations get(fn price_observations): double_map hasher(blake2_128_concat) RSymbol, hasher(twox_64_concat) u32 => Option<PriceObservation<T::BlockNumber>>;
+        /// next slot of the ring buffer to write
+        pub ObservationIndex get(fn observation_index): map hasher(blake2_128_concat) RSymbol => u32;
+        /// min blocks between two price observations of a pool
+        pub ObservationPeriod get(fn observation_period): u32 = 100;
     }
 }
 
@@ -94,13 +143,17 @@ decl_module! {
             let mut pool = Self::swap_pools(symbol).ok_or(Error::<T>::PoolNotExist)?;
             ensure!(input_amount > 0 && min_out_amount > 0, Error::<T>::AmountZero);
 
-            let (result, fee) = Self::cal_swap_result(pool.fis_balance, pool.rtoken_balance, input_amount, input_is_fis);
+            let (result, fee) = Self::cal_pool_swap_result(symbol, pool.fis_balance, pool.rtoken_balance, input_amount, input_is_fis);
             ensure!(result > 0, Error::<T>::SwapAmountTooFew);
             ensure!(result >= min_out_amount, Error::<T>::LessThanMinOutAmount);
+            let (lp_fee, protocol_fee) = Self::split_fee(symbol, fee);
+            // protocol fee is taken out of the pool along with the result
+            let pool_out = result.saturating_add(protocol_fee);
+            Self::update_price_cumulative(symbol, &pool);
 
             if input_is_fis {
                 ensure!(T::Currency::free_balance(&who).saturated_into::<u128>() > input_amount, Error::<T>::UserFisAmountNotEnough);
-                ensure!(result < pool.rtoken_balance, Error::<T>::PoolRTokenBalanceNotEnough);
+                ensure!(pool_out < pool.rtoken_balance, Error::<T>::PoolRTokenBalanceNotEnough);
 
                 // transfer
                 T::Currency::transfer(&who, &Self::account_id(), input_amount.saturated_into(), KeepAlive)?;
@@ -108,10 +161,10 @@ decl_module! {
 
                 // update pool
                 pool.fis_balance = pool.fis_balance.saturating_add(input_amount);
-                pool.rtoken_balance = pool.rtoken_balance.saturating_sub(result);
+                pool.rtoken_balance = pool.rtoken_balance.saturating_sub(pool_out);
             } else {
                 ensure!(T::RCurrency::free_balance(&who, symbol) >= input_amount, Error::<T>::UserRTokenAmountNotEnough);
-                ensure!(result < pool.fis_balance, Error::<T>::PoolFisBalanceNotEnough);
+                ensure!(pool_out < pool.fis_balance, Error::<T>::PoolFisBalanceNotEnough);
 
                 // transfer
                 T::Currency::transfer(&Self::account_id(), &who, result.saturated_into(), KeepAlive)?;
@@ -119,12 +172,14 @@ decl_module! {
 
                 // update pool
                 pool.rtoken_balance = pool.rtoken_balance.saturating_add(input_amount);
-                pool.fis_balance = pool.fis_balance.saturating_sub(result);
+                pool.fis_balance = pool.fis_balance.saturating_sub(pool_out);
             }
 
             // update pool storage
             <SwapPools>::insert(symbol, pool.clone());
+            Self::accrue_protocol_fee(symbol, protocol_fee, !input_is_fis);
             Self::deposit_event(RawEvent::Swap(who, symbol, input_amount, result, fee, input_is_fis, pool.fis_balance, pool.rtoken_balance));
+            Self::deposit_event(RawEvent::SwapFee(symbol, input_amount, input_is_fis, lp_fee, protocol_fee));
             Ok(())
         }
 
@@ -141,6 +196,7 @@ decl_module! {
 
             let (new_total_pool_unit, add_lp_unit) = Self::cal_pool_unit(pool.total_unit, pool.fis_balance, pool.rtoken_balance, fis_amount, rtoken_amount);
             ensure!(add_lp_unit > 0, Error::<T>::AddLpUnitIsZero);
+            Self::update_price_cumulative(symbol, &pool);
 
             // transfer token to module account
             if fis_amount > 0 {
@@ -162,6 +218,17 @@ decl_module! {
             Ok(())
         }
 
+        /// add liquidity from a single asset, the part needed to balance the deposit is swapped first
+        #[weight = 20_000_000_000]
+        pub fn zap_in(origin, symbol: RSymbol, amount: u128, input_is_fis: bool, min_units: u128) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::swap_pools(symbol).is_some(), Error::<T>::PoolNotExist);
+            ensure!(Self::lp_whitelist((symbol, who.clone())) || Self::lp_switch(symbol), Error::<T>::AddLpNotAllowed);
+
+            Self::do_zap_in(&who, symbol, amount, input_is_fis, min_units)?;
+            Ok(())
+        }
+
         /// remove liquidity
         #[weight = 10_000_000_000]
         pub fn remove_liquidity(origin, symbol: RSymbol, rm_unit: u128, swap_unit: u128, min_fis_out_amount: u128, min_rtoken_out_amount: u128, input_is_fis: bool) -> DispatchResult {
@@ -172,29 +239,33 @@ decl_module! {
             let pool_rtoken_balance = T::RCurrency::free_balance(&Self::account_id(), symbol);
 
             ensure!(rm_unit > 0 && rm_unit <= lp_unit && rm_unit >= swap_unit, Error::<T>::UnitAmountImproper);
+            Self::update_price_cumulative(symbol, &pool);
 
             let (mut rm_fis_amount, mut rm_rtoken_amount, swap_input_amount) = Self::cal_remove_result(pool.total_unit, rm_unit, swap_unit, pool.fis_balance, pool.rtoken_balance, input_is_fis);
             //update pool/lp
             pool.total_unit = pool.total_unit.saturating_sub(rm_unit);
             pool.fis_balance =  pool.fis_balance.saturating_sub(rm_fis_amount);
             pool.rtoken_balance = pool.rtoken_balance.saturating_sub(rm_rtoken_amount);
+            let mut swap_fee = (0, 0);
             if swap_input_amount > 0 {
-                let (swap_result, _) = Self::cal_swap_result(pool.fis_balance, pool.rtoken_balance, swap_input_amount, input_is_fis);
+                let (swap_result, fee) = Self::cal_pool_swap_result(symbol, pool.fis_balance, pool.rtoken_balance, swap_input_amount, input_is_fis);
                 ensure!(swap_result > 0, Error::<T>::SwapAmountTooFew);
+                swap_fee = Self::split_fee(symbol, fee);
+                let pool_out = swap_result.saturating_add(swap_fee.1);
 
                 if input_is_fis {
-                    ensure!(swap_result < pool.rtoken_balance, Error::<T>::PoolRTokenBalanceNotEnough);
+                    ensure!(pool_out < pool.rtoken_balance, Error::<T>::PoolRTokenBalanceNotEnough);
 
                     pool.fis_balance = pool.fis_balance.saturating_add(swap_input_amount);
-                    pool.rtoken_balance = pool.rtoken_balance.saturating_sub(swap_result);
+                    pool.rtoken_balance = pool.rtoken_balance.saturating_sub(pool_out);
 
                     rm_fis_amount = rm_fis_amount.saturating_sub(swap_input_amount);
                     rm_rtoken_amount = rm_rtoken_amount.saturating_add(swap_result);
                 } else {
-                    ensure!(swap_result < pool.fis_balance, Error::<T>::PoolFisBalanceNotEnough);
+                    ensure!(pool_out < pool.fis_balance, Error::<T>::PoolFisBalanceNotEnough);
 
                     pool.rtoken_balance = pool.rtoken_balance.saturating_add(swap_input_amount);
-                    pool.fis_balance = pool.fis_balance.saturating_sub(swap_result);
+                    pool.fis_balance = pool.fis_balance.saturating_sub(pool_out);
 
                     rm_rtoken_amount = rm_rtoken_amount.saturating_sub(swap_input_amount);
                     rm_fis_amount = rm_fis_amount.saturating_add(swap_result);
@@ -218,6 +289,11 @@ decl_module! {
             // update pool
             <SwapPools>::insert(symbol, pool.clone());
             Self::deposit_event(RawEvent::RemoveLiquidity(who, symbol, rm_unit, swap_unit, rm_fis_amount, rm_rtoken_amount, input_is_fis, pool.fis_balance, pool.rtoken_balance));
+            if swap_input_amount > 0 {
+                let (lp_fee, protocol_fee) = swap_fee;
+                Self::accrue_protocol_fee(symbol, protocol_fee, !input_is_fis);
+                Self::deposit_event(RawEvent::SwapFee(symbol, swap_input_amount, input_is_fis, lp_fee, protocol_fee));
+            }
             Ok(())
         }
 
@@ -245,6 +321,7 @@ decl_module! {
 
             // update pool/lp
             T::LpCurrency::mint(&who, symbol, lp_unit)?;
+            Self::update_price_cumulative(symbol, &pool);
             <SwapPools>::insert(symbol, pool);
             Self::deposit_event(RawEvent::CreatePool(who, symbol, fis_amount, rtoken_amount, pool_unit, lp_unit));
             Ok(())
@@ -264,6 +341,72 @@ decl_module! {
             <LpWhitelist<T>>::remove((symbol, who));
             Ok(())
         }
+        /// set pricing curve of a pool
+        #[weight = 10_000]
+        pub fn set_pool_curve(origin, symbol: RSymbol, curve: SwapCurve) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Self::swap_pools(symbol).is_some(), Error::<T>::PoolNotExist);
+            if let SwapCurve::StableSwap { amplification, .. } = curve {
+                ensure!(symbol == RSymbol::RFIS, Error::<T>::StableSwapNotSupported);
+                ensure!(amplification > 0 && amplification <= MAX_AMPLIFICATION, Error::<T>::InvalidAmplification);
+            }
+
+            <PoolCurves>::insert(symbol, curve);
+            Self::deposit_event(RawEvent::PoolCurveSet(symbol, curve));
+            Ok(())
+        }
+
+        /// set share of swap fees sent to the protocol
+        #[weight = 10_000]
+        pub fn set_protocol_fee_share(origin, symbol: RSymbol, share: Perbill) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Self::swap_pools(symbol).is_some(), Error::<T>::PoolNotExist);
+
+            <ProtocolFeeShares>::insert(symbol, share);
+            Self::deposit_event(RawEvent::ProtocolFeeShareSet(symbol, share));
+            Ok(())
+        }
+
+        /// set account receiving protocol fees
+        #[weight = 10_000]
+        pub fn set_protocol_fee_receiver(origin, receiver: T::AccountId) -> DispatchResult {
+            ensure_root(origin)?;
+            <ProtocolFeeReceiver<T>>::put(&receiver);
+            Self::deposit_event(RawEvent::ProtocolFeeReceiverSet(receiver));
+            Ok(())
+        }
+
+        /// pay accrued protocol fees of a pool to the receiver, anyone can trigger it
+        #[weight = 10_000_000]
+        pub fn claim_protocol_fee(origin, symbol: RSymbol) -> DispatchResult {
+            ensure_signed(origin)?;
+            let receiver = Self::protocol_fee_receiver().ok_or(Error::<T>::ProtocolFeeReceiverNotSet)?;
+            let accrued = Self::protocol_fees(symbol);
+            ensure!(accrued.fis_amount > 0 || accrued.rtoken_amount > 0, Error::<T>::NoProtocolFee);
+
+            if accrued.fis_amount > 0 {
+                T::Currency::transfer(&Self::account_id(), &receiver, accrued.fis_amount.saturated_into(), KeepAlive)?;
+            }
+            if accrued.rtoken_amount > 0 {
+                T::RCurrency::transfer(&Self::account_id(), &receiver, symbol, accrued.rtoken_amount)?;
+            }
+
+            <ProtocolFees>::remove(symbol);
+            Self::deposit_event(RawEvent::ProtocolFeeClaimed(symbol, receiver, accrued.fis_amount, accrued.rtoken_amount));
+            Ok(())
+        }
+
+        /// set min blocks between two price observations
+        #[weight = 10_000]
+        pub fn set_observation_period(origin, period: u32) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(period > 0, Error::<T>::InvalidObservationPeriod);
+
+            ObservationPeriod::put(period);
+            Self::deposit_event(RawEvent::ObservationPeriodSet(period));
+            Ok(())
+        }
+
         /// turn on/off lp switch, default closed
         #[weight = 100_000]
         fn toggle_lp_switch(origin, symbol: RSymbol) -> DispatchResult {
@@ -281,6 +424,91 @@ impl<T: Trait> Module<T> {
         MODULE_ID.into_account()
     }
 
+    /// splits the fee of a swap into (lp fee, protocol fee)
+    pub fn split_fee(symbol: RSymbol, fee: u128) -> (u128, u128) {
+        let protocol_fee = Self::protocol_fee_shares(symbol) * fee;
+        (fee.saturating_sub(protocol_fee), protocol_fee)
+    }
+
+    fn accrue_protocol_fee(symbol: RSymbol, amount: u128, is_fis: bool) {
+        if amount == 0 {
+            return;
+        }
+        <ProtocolFees>::mutate(symbol, |accrued| {
+            if is_fis {
+                accrued.fis_amount = accrued.fis_amount.saturating_add(amount);
+            } else {
+                accrued.rtoken_amount = accrued.rtoken_amount.saturating_add(amount);
+            }
+        });
+    }
+
+    /// price of one `base` in `quote`, scaled by PRICE_BASE
+    pub fn cal_price(base_balance: u128, quote_balance: u128) -> u128 {
+        let price = U512::from(quote_balance)
+            .saturating_mul(U512::from(PRICE_BASE))
+            .checked_div(U512::from(base_balance))
+            .unwrap_or(U512::zero());
+        Self::safe_to_u128(price)
+    }
+
+    /// price accumulators of a pool extended to the current block with its balances
+    pub fn current_price_cumulative(symbol: RSymbol, pool: &SwapPool) -> Option<PriceObservation<T::BlockNumber>> {
+        let now = system::Module::<T>::block_number();
+        let mut cumulative = Self::price_cumulatives(symbol)?;
+        if now > cumulative.block && pool.fis_balance > 0 && pool.rtoken_balance > 0 {
+            let elapsed = (now - cumulative.block).saturated_into::<u128>();
+            let rtoken_price = Self::cal_price(pool.rtoken_balance, pool.fis_balance);
+            let fis_price = Self::cal_price(pool.fis_balance, pool.rtoken_balance);
+            cumulative.rtoken_price_cumulative = cumulative.rtoken_price_cumulative.wrapping_add(rtoken_price.wrapping_mul(elapsed));
+            cumulative.fis_price_cumulative = cumulative.fis_price_cumulative.wrapping_add(fis_price.wrapping_mul(elapsed));
+        }
+        cumulative.block = now;
+        Some(cumulative)
+    }
+
+    /// brings the price accumulators up to date, must be called with the pool before it is changed
+    /// so that a price moved within a block only counts from the next block on
+    fn update_price_cumulative(symbol: RSymbol, pool: &SwapPool) {
+        let cumulative = Self::current_price_cumulative(symbol, pool).unwrap_or(PriceObservation {
+            block: system::Module::<T>::block_number(),
+            rtoken_price_cumulative: 0,
+            fis_price_cumulative: 0,
+        });
+        <PriceCumulatives<T>>::insert(symbol, &cumulative);
+
+        let index = Self::observation_index(symbol);
+        let last_index = (index + OBSERVATION_SIZE - 1) % OBSERVATION_SIZE;
+        let due = match Self::price_observations(symbol, last_index) {
+            Some(last) => cumulative.block >= last.block + Self::observation_period().into(),
+            None => true,
+        };
+        if due {
+            <PriceObservations<T>>::insert(symbol, index, cumulative);
+            ObservationIndex::insert(symbol, (index + 1) % OBSERVATION_SIZE);
+        }
+    }
+
+    /// time weighted average prices of a pool over at least `window` blocks, `None` if no
+    /// observation of the pool is that old
+    pub fn twap(symbol: RSymbol, window: u32) -> Option<TwapPrice> {
+        let pool = Self::swap_pools(symbol)?;
+        let current = Self::current_price_cumulative(symbol, &pool)?;
+        let start = <PriceObservations<T>>::iter_prefix_values(symbol)
+            .filter(|o| o.block + window.into() <= current.block)
+            .max_by_key(|o| o.block)?;
+        let elapsed = (current.block - start.block).saturated_into::<u32>();
+        if elapsed == 0 {
+            return None;
+        }
+
+        Some(TwapPrice {
+            rtoken_price: current.rtoken_price_cumulative.wrapping_sub(start.rtoken_price_cumulative) / elapsed as u128,
+            fis_price: current.fis_price_cumulative.wrapping_sub(start.fis_price_cumulative) / elapsed as u128,
+            window: elapsed,
+        })
+    }
+
     // F = fis Balance (before)
     // R = rToken Balance (before)
     // f = fis added;
@@ -358,6 +586,96 @@ impl<T: Trait> Module<T> {
         (Self::safe_to_u128(total_unit), Self::safe_to_u128(add_unit))
     }
 
+    /// adds liquidity of `who` from a single asset, returning the lp unit minted to `who`
+    pub fn do_zap_in(who: &T::AccountId, symbol: RSymbol, amount: u128, input_is_fis: bool, min_units: u128) -> Result<u128, DispatchError> {
+        let mut pool = Self::swap_pools(symbol).ok_or(Error::<T>::PoolNotExist)?;
+        ensure!(amount > 0, Error::<T>::AmountZero);
+        ensure!(pool.fis_balance > 0 && pool.rtoken_balance > 0, Error::<T>::PoolOneSideZero);
+        if input_is_fis {
+            ensure!(T::Currency::free_balance(who).saturated_into::<u128>() > amount, Error::<T>::UserFisAmountNotEnough);
+        } else {
+            ensure!(T::RCurrency::free_balance(who, symbol) >= amount, Error::<T>::UserRTokenAmountNotEnough);
+        }
+
+        let quote = Self::cal_zap_in(symbol, &pool, amount, input_is_fis);
+        ensure!(quote.add_unit > 0, Error::<T>::AddLpUnitIsZero);
+        ensure!(quote.add_unit >= min_units, Error::<T>::LessThanMinUnits);
+        Self::update_price_cumulative(symbol, &pool);
+
+        // the swap output never leaves the module account, only the protocol fee is taken from the pool
+        if input_is_fis {
+            T::Currency::transfer(who, &Self::account_id(), amount.saturated_into(), KeepAlive)?;
+            pool.fis_balance = pool.fis_balance.saturating_add(amount);
+            pool.rtoken_balance = pool.rtoken_balance.saturating_sub(quote.protocol_fee);
+        } else {
+            T::RCurrency::transfer(who, &Self::account_id(), symbol, amount)?;
+            pool.rtoken_balance = pool.rtoken_balance.saturating_add(amount);
+            pool.fis_balance = pool.fis_balance.saturating_sub(quote.protocol_fee);
+        }
+        pool.total_unit = quote.new_total_unit;
+
+        T::LpCurrency::mint(who, symbol, quote.add_unit)?;
+        <SwapPools>::insert(symbol, pool.clone());
+        Self::accrue_protocol_fee(symbol, quote.protocol_fee, !input_is_fis);
+        if quote.swap_amount > 0 {
+            Self::deposit_event(RawEvent::SwapFee(symbol, quote.swap_amount, input_is_fis, quote.lp_fee, quote.protocol_fee));
+        }
+        Self::deposit_event(RawEvent::ZapIn(who.clone(), symbol, amount, input_is_fis, quote.swap_amount, quote.swap_result, quote.new_total_unit, quote.add_unit, pool.fis_balance, pool.rtoken_balance));
+        Ok(quote.add_unit)
+    }
+
+    /// quote of `zap_in` with the current pool
+    pub fn quote_zap_in(symbol: RSymbol, amount: u128, input_is_fis: bool) -> Option<ZapInQuote> {
+        let pool = Self::swap_pools(symbol)?;
+        Some(Self::cal_zap_in(symbol, &pool, amount, input_is_fis))
+    }
+
+    // s of the amount is swapped, the rest is added along with the swap result
+    // units are unimodal in s and s is searched in [0, min(amount, X)] where X is the balance of the
+    // input asset, beyond it the clp output decreases
+    pub fn cal_zap_in(symbol: RSymbol, pool: &SwapPool, amount: u128, input_is_fis: bool) -> ZapInQuote {
+        let quote = |swap_amount: u128| -> ZapInQuote {
+            let (swap_result, fee) = if swap_amount > 0 {
+                Self::cal_pool_swap_result(symbol, pool.fis_balance, pool.rtoken_balance, swap_amount, input_is_fis)
+            } else {
+                (0, 0)
+            };
+            let (lp_fee, protocol_fee) = Self::split_fee(symbol, fee);
+            let pool_out = swap_result.saturating_add(protocol_fee);
+            let out_balance = if input_is_fis { pool.rtoken_balance } else { pool.fis_balance };
+            let (new_total_unit, add_unit) = if swap_amount > 0 && pool_out >= out_balance {
+                (pool.total_unit, 0)
+            } else if input_is_fis {
+                Self::cal_pool_unit(pool.total_unit, pool.fis_balance.saturating_add(swap_amount), pool.rtoken_balance - pool_out, amount - swap_amount, swap_result)
+            } else {
+                Self::cal_pool_unit(pool.total_unit, pool.fis_balance - pool_out, pool.rtoken_balance.saturating_add(swap_amount), swap_result, amount - swap_amount)
+            };
+            ZapInQuote { swap_amount, swap_result, lp_fee, protocol_fee, new_total_unit, add_unit }
+        };
+
+        let x_balance = if input_is_fis { pool.fis_balance } else { pool.rtoken_balance };
+        let mut low = 0u128;
+        let mut high = amount.min(x_balance);
+        while high - low > 2 {
+            let m1 = low + (high - low) / 3;
+            let m2 = high - (high - low) / 3;
+            if quote(m1).add_unit < quote(m2).add_unit {
+                low = m1 + 1;
+            } else {
+                high = m2;
+            }
+        }
+
+        let mut best = quote(low);
+        for swap_amount in low + 1..=high {
+            let candidate = quote(swap_amount);
+            if candidate.add_unit > best.add_unit {
+                best = candidate;
+            }
+        }
+        best
+    }
+
     // y = (x * X * Y) / (x + X)^2
     // fee = (x^2 * Y)/(x + X)^2
     pub fn cal_swap_result(
@@ -392,6 +710,114 @@ impl<T: Trait> Module<T> {
         (Self::safe_to_u128(y), Self::safe_to_u128(fee))
     }
 
+    /// swap result and fee of a pool according to its curve
+    pub fn cal_pool_swap_result(
+        symbol: RSymbol,
+        fis_balance: u128,
+        rtoken_balance: u128,
+        input_amount: u128,
+        input_is_fis: bool,
+    ) -> (u128, u128) {
+        match Self::pool_curves(symbol) {
+            SwapCurve::Clp => Self::cal_swap_result(fis_balance, rtoken_balance, input_amount, input_is_fis),
+            SwapCurve::StableSwap { amplification, fee } => {
+                let rate = rtoken_rate::Module::<T>::rate(symbol).unwrap_or(RATEBASE);
+                Self::cal_stable_swap_result(fis_balance, rtoken_balance, input_amount, input_is_fis, amplification, fee, rate)
+            }
+        }
+    }
+
+    // rtoken is valued in fis at `rate`, then with n = 2 coins and Ann = A * n^n:
+    // Ann * (x + y) + D = Ann * D + D^3 / (4 * x * y)
+    // y is solved for the new x keeping D, output = y - y' - 1, fee = output * fee rate
+    pub fn cal_stable_swap_result(
+        fis_balance: u128,
+        rtoken_balance: u128,
+        input_amount: u128,
+        input_is_fis: bool,
+        amplification: u128,
+        fee_rate: Perbill,
+        rate: RateType,
+    ) -> (u128, u128) {
+        if fis_balance == 0 || rtoken_balance == 0 || input_amount == 0 || amplification == 0 || rate == 0 {
+            return (0, 0);
+        }
+        let rate = U512::from(rate);
+        let base = U512::from(RATEBASE);
+        let to_fis = |rtoken: U512| rtoken.saturating_mul(rate) / base;
+
+        let fis = U512::from(fis_balance);
+        let rtoken_in_fis = to_fis(U512::from(rtoken_balance));
+        let (x, y, dx) = if input_is_fis {
+            (fis, rtoken_in_fis, U512::from(input_amount))
+        } else {
+            (rtoken_in_fis, fis, to_fis(U512::from(input_amount)))
+        };
+
+        let amp = U512::from(amplification);
+        let d = Self::stable_invariant(x, y, amp);
+        let new_y = Self::stable_y(x.saturating_add(dx), d, amp);
+        let dy = y.saturating_sub(new_y).saturating_sub(U512::one());
+        let fee = U512::from(fee_rate * Self::safe_to_u128(dy));
+        let out = dy.saturating_sub(fee);
+
+        if input_is_fis {
+            let to_rtoken = |amount: U512| amount.saturating_mul(base) / rate;
+            (Self::safe_to_u128(to_rtoken(out)), Self::safe_to_u128(to_rtoken(fee)))
+        } else {
+            (Self::safe_to_u128(out), Self::safe_to_u128(fee))
+        }
+    }
+
+    /// D of the stableswap invariant, by newton's method
+    pub fn stable_invariant(x: U512, y: U512, amp: U512) -> U512 {
+        let s = x.saturating_add(y);
+        if x.is_zero() || y.is_zero() {
+            return U512::zero();
+        }
+        let two = U512::from(2);
+        let ann = amp.saturating_mul(U512::from(4));
+        let mut d = s;
+        for _ in 0..STABLESWAP_ITERATIONS {
+            // D^3 / (4 * x * y)
+            let d_p = (d.saturating_mul(d) / x.saturating_mul(two)).saturating_mul(d) / y.saturating_mul(two);
+            let prev = d;
+            let numerator = ann.saturating_mul(s).saturating_add(d_p.saturating_mul(two)).saturating_mul(d);
+            let denominator = ann.saturating_sub(U512::one()).saturating_mul(d).saturating_add(d_p.saturating_mul(U512::from(3)));
+            d = numerator.checked_div(denominator).unwrap_or(U512::zero());
+            if Self::converged(d, prev) {
+                break;
+            }
+        }
+        d
+    }
+
+    /// balance y of the other side keeping D when this side is `x`
+    pub fn stable_y(x: U512, d: U512, amp: U512) -> U512 {
+        if x.is_zero() || d.is_zero() {
+            return U512::zero();
+        }
+        let two = U512::from(2);
+        let ann = amp.saturating_mul(U512::from(4));
+        // c = D^3 / (4 * x * Ann), b = x + D / Ann
+        let c = (d.saturating_mul(d) / x.saturating_mul(two)).saturating_mul(d) / ann.saturating_mul(two);
+        let b = x.saturating_add(d / ann);
+        let mut y = d;
+        for _ in 0..STABLESWAP_ITERATIONS {
+            let prev = y;
+            let denominator = y.saturating_mul(two).saturating_add(b).saturating_sub(d);
+            y = y.saturating_mul(y).saturating_add(c).checked_div(denominator).unwrap_or(U512::zero());
+            if Self::converged(y, prev) {
+                break;
+            }
+        }
+        y
+    }
+
+    fn converged(a: U512, b: U512) -> bool {
+        if a > b { a - b <= U512::one() } else { b - a <= U512::one() }
+    }
+
     pub fn cal_remove_result(
         pool_unit: u128,
         rm_unit: u128,
@@ -453,3 +879,19 @@ impl<T: Trait> Module<T> {
         <SwapPools>::insert(symbol, pool);
     }
 }
+
+impl<T: Trait> PriceOracle for Module<T> {
+    fn rtoken_price(symbol: RSymbol, window: u32) -> Option<u128> {
+        Self::twap(symbol, window).map(|price| price.rtoken_price)
+    }
+
+    fn price_updated_at(symbol: RSymbol) -> Option<u64> {
+        Self::price_cumulatives(symbol).map(|cumulative| cumulative.block.saturated_into::<u64>())
+    }
+}
+
+impl<T: Trait> LiquidityProvider<T::AccountId> for Module<T> {
+    fn add_single_sided_liquidity(who: &T::AccountId, symbol: RSymbol, amount: u128, input_is_fis: bool, min_units: u128) -> Result<u128, DispatchError> {
+        Self::do_zap_in(who, symbol, amount, input_is_fis, min_units)
+    }
+}
@@ -100,6 +100,10 @@ impl rtoken_balances::Trait for Test {
     type Event = ();
 }
 
+impl rtoken_rate::Trait for Test {
+    type Event = ();
+}
+
 pub fn new_test_ext() -> sp_io::TestExternalities {
     let mut t = frame_system::GenesisConfig::default()
         .build_storage::<Test>()
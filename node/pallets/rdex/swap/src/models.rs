@@ -1,7 +1,9 @@
 use codec::{Decode, Encode};
 use node_primitives::RSymbol;
-use sp_runtime::RuntimeDebug;
+use sp_runtime::{Perbill, RuntimeDebug};
 use sp_std::prelude::*;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 
 #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
 pub struct SwapPool {
@@ -15,3 +17,75 @@ pub struct SwapPool {
     pub total_unit: u128,
 }
 
+
+/// Pricing curve of a swap pool
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub enum SwapCurve {
+    /// slip based clp, y = x * X * Y / (x + X)^2
+    Clp,
+    /// curve style stableswap around the rate of the rtoken, only for rFIS as other rates are not in FIS
+    StableSwap {
+        /// amplification coefficient, the higher the flatter the curve around the peg
+        amplification: u128,
+        /// fee taken from the output
+        fee: Perbill,
+    },
+}
+
+impl Default for SwapCurve {
+    fn default() -> Self {
+        SwapCurve::Clp
+    }
+}
+
+/// Protocol fee of a pool accrued but not claimed yet
+#[derive(PartialEq, Eq, Clone, Default, Encode, Decode, RuntimeDebug)]
+pub struct ProtocolFee {
+    /// fee in fis
+    pub fis_amount: u128,
+    /// fee in rToken
+    pub rtoken_amount: u128,
+}
+
+/// Price accumulators of a pool at a block.
+///
+/// Each accumulator is the sum over blocks of the price at the end of the previous block, scaled by
+/// `PRICE_BASE`. It wraps on overflow, only differences of two accumulators are meaningful.
+#[derive(PartialEq, Eq, Clone, Default, Encode, Decode, RuntimeDebug)]
+pub struct PriceObservation<BlockNumber> {
+    /// block of the observation
+    pub block: BlockNumber,
+    /// accumulated price of one rToken in fis
+    pub rtoken_price_cumulative: u128,
+    /// accumulated price of one fis in rToken
+    pub fis_price_cumulative: u128,
+}
+
+/// Time weighted average prices of a pool
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct TwapPrice {
+    /// price of one rToken in fis, scaled by `PRICE_BASE`
+    pub rtoken_price: u128,
+    /// price of one fis in rToken, scaled by `PRICE_BASE`
+    pub fis_price: u128,
+    /// blocks actually averaged over, not less than the requested window
+    pub window: u32,
+}
+
+/// Liquidity added from a single asset, part of which is swapped to the other one
+#[derive(PartialEq, Eq, Clone, Default, Encode, Decode, RuntimeDebug)]
+pub struct ZapInQuote {
+    /// part of the input swapped
+    pub swap_amount: u128,
+    /// output of the swap, added along with the rest of the input
+    pub swap_result: u128,
+    /// lp fee of the swap
+    pub lp_fee: u128,
+    /// protocol fee of the swap
+    pub protocol_fee: u128,
+    /// total lp unit of the pool after adding
+    pub new_total_unit: u128,
+    /// lp unit added
+    pub add_unit: u128,
+}
@@ -1,7 +1,10 @@
 use super::mock::*;
 use super::*;
-use frame_support::{assert_err, assert_ok};
+use frame_support::{assert_err, assert_ok, StorageMap};
 use node_primitives::RSymbol;
+use rtoken_rate::RATEBASE;
+use sp_core::U512;
+use sp_runtime::Perbill;
 pub struct CalPoolUnitData {
     pub old_pool_unit: u128,
     pub fis_balance: u128,
@@ -180,6 +183,145 @@ fn cal_swap_result_should_work() {
     });
 }
 
+/// deterministic pseudo random numbers for property tests
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, n: u128) -> u128 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0 as u128 % n
+    }
+}
+
+const AMPLIFICATIONS: [u128; 4] = [1, 10, 100, 1000];
+
+fn stable_fee() -> Perbill {
+    Perbill::from_rational_approximation(4u32, 10000u32)
+}
+
+fn normalized(rtoken: u128, rate: u128) -> U512 {
+    U512::from(rtoken) * U512::from(rate) / U512::from(RATEBASE)
+}
+
+#[test]
+fn stable_swap_should_beat_clp_at_peg() {
+    let mut rng = Lcg(7);
+    for _ in 0..500 {
+        let balance = (rng.next(1_000_000_000) + 1) * 1_000_000_000_000;
+        let input_amount = balance * (rng.next(500) + 1) / 1000;
+        let amplification = AMPLIFICATIONS[rng.next(4) as usize];
+        let input_is_fis = rng.next(2) == 0;
+
+        let (stable_out, stable_fee_amount) = RDexSwap::cal_stable_swap_result(
+            balance, balance, input_amount, input_is_fis, amplification, stable_fee(), RATEBASE,
+        );
+        let (clp_out, clp_fee) = RDexSwap::cal_swap_result(balance, balance, input_amount, input_is_fis);
+        assert!(stable_out >= clp_out);
+        assert!(stable_fee_amount <= clp_fee);
+        assert!(stable_out < input_amount);
+    }
+}
+
+#[test]
+fn stable_swap_should_keep_invariant() {
+    let mut rng = Lcg(11);
+    for _ in 0..500 {
+        let rate = (RATEBASE + rng.next(RATEBASE as u128) as u64) as u128;
+        let fis_balance = (rng.next(1_000_000_000) + 1) * 1_000_000_000_000;
+        let rtoken_balance = fis_balance * RATEBASE as u128 / rate * (50 + rng.next(150)) / 100;
+        let input_amount = fis_balance * (rng.next(500) + 1) / 1000;
+        let amplification = AMPLIFICATIONS[rng.next(4) as usize];
+        let input_is_fis = rng.next(2) == 0;
+        let amp = U512::from(amplification);
+
+        let (out, _) = RDexSwap::cal_stable_swap_result(
+            fis_balance, rtoken_balance, input_amount, input_is_fis, amplification, stable_fee(), rate as u64,
+        );
+        let (before, after) = if input_is_fis {
+            assert!(out < rtoken_balance);
+            (
+                RDexSwap::stable_invariant(U512::from(fis_balance), normalized(rtoken_balance, rate), amp),
+                RDexSwap::stable_invariant(U512::from(fis_balance + input_amount), normalized(rtoken_balance - out, rate), amp),
+            )
+        } else {
+            assert!(out < fis_balance);
+            (
+                RDexSwap::stable_invariant(normalized(rtoken_balance, rate), U512::from(fis_balance), amp),
+                RDexSwap::stable_invariant(normalized(rtoken_balance + input_amount, rate), U512::from(fis_balance - out), amp),
+            )
+        };
+        // fees stay in the pool, so D never decreases
+        assert!(after >= before);
+
+        let (more_out, _) = RDexSwap::cal_stable_swap_result(
+            fis_balance, rtoken_balance, input_amount * 2, input_is_fis, amplification, stable_fee(), rate as u64,
+        );
+        assert!(more_out >= out);
+    }
+}
+
+#[test]
+fn stable_swap_should_follow_rate_peg() {
+    let fis_balance = 2_000_000_000_000_000;
+    let rtoken_balance = 1_000_000_000_000_000;
+    let rate = 2 * RATEBASE;
+
+    // 1 rtoken is worth 2 fis, A = 100 keeps the slippage at 5 ppm
+    let (out, fee) = RDexSwap::cal_stable_swap_result(
+        fis_balance, rtoken_balance, 1_000_000_000_000, false, 100, stable_fee(), rate,
+    );
+    assert_eq!(out + fee, 1_999_990_049_790);
+    assert!(fee >= 799_996_019 && fee <= 799_996_020);
+
+    let (out, fee) = RDexSwap::cal_stable_swap_result(
+        fis_balance, rtoken_balance, 2_000_000_000_000, true, 100, stable_fee(), rate,
+    );
+    assert!(out + fee >= 999_995_024_893 && out + fee <= 999_995_024_894);
+    assert!(fee >= 399_998_009 && fee <= 399_998_010);
+
+    let (out, fee) = RDexSwap::cal_stable_swap_result(
+        fis_balance, rtoken_balance, 1_000_000_000_000, false, 100, Perbill::zero(), rate,
+    );
+    assert_eq!((out, fee), (1_999_990_049_790, 0));
+}
+
+#[test]
+fn swap_with_stable_curve_should_work() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RFIS;
+        let curve = SwapCurve::StableSwap { amplification: 100, fee: stable_fee() };
+        assert_err!(RDexSwap::set_pool_curve(Origin::root(), symbol, curve), Error::<Test>::PoolNotExist);
+
+        assert_ok!(RBalances::mint(&1, symbol, 50));
+        assert_ok!(RBalances::mint(&2, symbol, 10));
+        assert_ok!(RDexSwap::create_pool(Origin::root(), 1, symbol, 50, 50));
+        assert_err!(
+            RDexSwap::set_pool_curve(Origin::root(), symbol, SwapCurve::StableSwap { amplification: 0, fee: stable_fee() }),
+            Error::<Test>::InvalidAmplification
+        );
+        assert_ok!(RDexSwap::set_pool_curve(Origin::root(), symbol, curve));
+        assert_eq!(RDexSwap::pool_curves(symbol), curve);
+
+        // the rate of other rtokens is in their native token
+        assert_ok!(RBalances::mint(&1, RSymbol::RDOT, 50));
+        assert_ok!(RDexSwap::create_pool(Origin::root(), 1, RSymbol::RDOT, 50, 20));
+        assert_err!(
+            RDexSwap::set_pool_curve(Origin::root(), RSymbol::RDOT, curve),
+            Error::<Test>::StableSwapNotSupported
+        );
+
+        let (clp_out, _) = RDexSwap::cal_swap_result(50, 50, 10, false);
+        let (stable_out, _) = RDexSwap::cal_pool_swap_result(symbol, 50, 50, 10, false);
+        assert!(stable_out > clp_out);
+
+        assert_ok!(RDexSwap::swap(Origin::signed(2), symbol, 10, stable_out, false));
+        assert_eq!(Balances::free_balance(2), 100 + stable_out);
+        let pool = RDexSwap::swap_pools(symbol).unwrap();
+        assert_eq!(pool.fis_balance, 50 - stable_out);
+        assert_eq!(pool.rtoken_balance, 60);
+    });
+}
+
 pub struct CalRemoveResultData {
     pub pool_unit: u128,
     pub rm_unit: u128,
@@ -535,3 +677,176 @@ fn swap_should_fail() {
         );
     });
 }
+
+#[test]
+fn swap_should_split_fee_with_protocol() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        assert_ok!(RBalances::mint(&(42 as u64), symbol, 1000));
+        assert_ok!(RDexSwap::create_pool(Origin::root(), 42 as u64, symbol, 900, 90));
+
+        assert_err!(
+            RDexSwap::set_protocol_fee_share(Origin::root(), RSymbol::RETH, Perbill::from_percent(50)),
+            Error::<Test>::PoolNotExist
+        );
+        assert_ok!(RDexSwap::set_protocol_fee_share(Origin::root(), symbol, Perbill::from_percent(50)));
+
+        // out = 30 * 90 * 900 / 120^2 = 168, fee = 30^2 * 900 / 120^2 = 56
+        assert_ok!(RDexSwap::swap(Origin::signed(2), symbol, 30, 168, true));
+        assert_eq!(RBalances::free_balance(&(2 as u64), symbol), 168);
+
+        let pool = RDexSwap::swap_pools(symbol).unwrap();
+        assert_eq!(pool.fis_balance, 120);
+        assert_eq!(pool.rtoken_balance, 900 - 168 - 28);
+        assert_eq!(RDexSwap::protocol_fees(symbol), ProtocolFee { fis_amount: 0, rtoken_amount: 28 });
+        assert_eq!(RBalances::free_balance(&RDexSwap::account_id(), symbol), 900 - 168);
+
+        assert_err!(
+            RDexSwap::claim_protocol_fee(Origin::signed(2), symbol),
+            Error::<Test>::ProtocolFeeReceiverNotSet
+        );
+        assert_ok!(RDexSwap::set_protocol_fee_receiver(Origin::root(), 9));
+        assert_ok!(RDexSwap::claim_protocol_fee(Origin::signed(2), symbol));
+        assert_eq!(RBalances::free_balance(&(9 as u64), symbol), 28);
+        assert_eq!(RBalances::free_balance(&RDexSwap::account_id(), symbol), pool.rtoken_balance);
+        assert_eq!(RDexSwap::protocol_fees(symbol), ProtocolFee::default());
+        assert_err!(
+            RDexSwap::claim_protocol_fee(Origin::signed(2), symbol),
+            Error::<Test>::NoProtocolFee
+        );
+    });
+}
+
+#[test]
+fn split_fee_should_work() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        assert_eq!(RDexSwap::split_fee(symbol, 100), (100, 0));
+        ProtocolFeeShares::insert(symbol, Perbill::from_percent(30));
+        assert_eq!(RDexSwap::split_fee(symbol, 100), (70, 30));
+        ProtocolFeeShares::insert(symbol, Perbill::one());
+        assert_eq!(RDexSwap::split_fee(symbol, 100), (0, 100));
+    });
+}
+
+#[test]
+fn twap_should_work() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        System::set_block_number(1);
+        assert_ok!(RBalances::mint(&(42 as u64), symbol, 900));
+        assert_ok!(RDexSwap::create_pool(Origin::root(), 42 as u64, symbol, 900, 90));
+        assert_ok!(RDexSwap::set_observation_period(Origin::root(), 10));
+        assert_eq!(RDexSwap::twap(symbol, 0), None);
+
+        // 0.1 fis per rtoken during blocks [1, 101)
+        System::set_block_number(101);
+        assert_ok!(RDexSwap::swap(Origin::signed(2), symbol, 30, 168, true));
+        let pool = RDexSwap::swap_pools(symbol).unwrap();
+        assert_eq!((pool.fis_balance, pool.rtoken_balance), (120, 732));
+        let moved_price = RDexSwap::cal_price(732, 120);
+        assert_eq!(moved_price, 163934426229);
+
+        System::set_block_number(201);
+        let expected = TwapPrice {
+            rtoken_price: (100_000_000_000 + moved_price) / 2,
+            fis_price: (10 * PRICE_BASE + RDexSwap::cal_price(120, 732)) / 2,
+            window: 200,
+        };
+        assert_eq!(RDexSwap::twap(symbol, 200), Some(expected.clone()));
+        assert_eq!(RDexSwap::rtoken_price(symbol, 100), Some(moved_price));
+        assert_eq!(RDexSwap::twap(symbol, 300), None);
+        assert_eq!(RDexSwap::price_updated_at(symbol), Some(101));
+
+        // a swap moves the spot price but not the average within the same block
+        assert_ok!(RDexSwap::swap(Origin::signed(1), symbol, 50, 1, true));
+        assert_eq!(RDexSwap::twap(symbol, 200), Some(expected));
+        assert_eq!(RDexSwap::observation_index(symbol), 3);
+        assert_eq!(RDexSwap::price_updated_at(symbol), Some(201));
+    });
+}
+
+#[test]
+fn zap_in_units_should_be_within_bounds() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        let mut rng = Lcg(13);
+        for _ in 0..300 {
+            let fis_balance = (rng.next(1_000_000_000) + 1) * 1_000_000_000_000;
+            let rtoken_balance = fis_balance * (rng.next(150) + 50) / 100;
+            let pool = SwapPool { symbol, fis_balance, rtoken_balance, total_unit: fis_balance };
+            let input_is_fis = rng.next(2) == 0;
+            let x_balance = if input_is_fis { fis_balance } else { rtoken_balance };
+            let amount = x_balance * (rng.next(500) + 1) / 1000;
+
+            let quote = RDexSwap::cal_zap_in(symbol, &pool, amount, input_is_fis);
+            assert!(quote.swap_amount <= amount);
+            assert_eq!(quote.new_total_unit, pool.total_unit + quote.add_unit);
+
+            // never more than the share of the pool value brought in, swap fees only take from it
+            let upper = U512::from(pool.total_unit) * U512::from(amount) / U512::from(2 * x_balance);
+            assert!(U512::from(quote.add_unit) <= upper);
+            assert!(U512::from(quote.add_unit) * U512::from(x_balance) >= upper * U512::from(x_balance - amount));
+
+            // at least as good as adding single sided or swapping half
+            let (_, single) = if input_is_fis {
+                RDexSwap::cal_pool_unit(pool.total_unit, fis_balance, rtoken_balance, amount, 0)
+            } else {
+                RDexSwap::cal_pool_unit(pool.total_unit, fis_balance, rtoken_balance, 0, amount)
+            };
+            let half = amount / 2;
+            let (half_out, _) = RDexSwap::cal_swap_result(fis_balance, rtoken_balance, half, input_is_fis);
+            let (_, halved) = if input_is_fis {
+                RDexSwap::cal_pool_unit(pool.total_unit, fis_balance + half, rtoken_balance - half_out, amount - half, half_out)
+            } else {
+                RDexSwap::cal_pool_unit(pool.total_unit, fis_balance - half_out, rtoken_balance + half, half_out, amount - half)
+            };
+            assert!(quote.add_unit >= single);
+            assert!(quote.add_unit >= halved);
+        }
+    });
+}
+
+#[test]
+fn zap_in_should_work() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RATOM;
+        assert_ok!(RBalances::mint(&(42 as u64), symbol, 900));
+        assert_ok!(RDexSwap::create_pool(Origin::root(), 42 as u64, symbol, 900, 90));
+
+        assert_err!(
+            RDexSwap::zap_in(Origin::signed(2), symbol, 50, true, 0),
+            Error::<Test>::AddLpNotAllowed
+        );
+        assert_ok!(RDexSwap::add_lp_to_whitelist(Origin::root(), symbol, 2));
+        assert_err!(
+            RDexSwap::zap_in(Origin::signed(2), symbol, 0, true, 0),
+            Error::<Test>::AmountZero
+        );
+        assert_err!(
+            RDexSwap::zap_in(Origin::signed(2), symbol, 50, false, 0),
+            Error::<Test>::UserRTokenAmountNotEnough
+        );
+
+        let quote = RDexSwap::quote_zap_in(symbol, 50, true).unwrap();
+        // 22 fis is swapped for 22 * 90 * 900 / 112^2 = 142 rtoken, 28 fis and 142 rtoken are added
+        assert_eq!(quote.swap_amount, 22);
+        assert_eq!(quote.swap_result, 142);
+        assert_eq!(quote.add_unit, 19);
+        assert_err!(
+            RDexSwap::zap_in(Origin::signed(2), symbol, 50, true, 20),
+            Error::<Test>::LessThanMinUnits
+        );
+
+        assert_ok!(RDexSwap::zap_in(Origin::signed(2), symbol, 50, true, 19));
+        assert_eq!(LpBalances::free_balance(&(2 as u64), symbol), 19);
+        assert_eq!(Balances::free_balance(&(2 as u64)), 50);
+        assert_eq!(RBalances::free_balance(&(2 as u64), symbol), 0);
+        let pool = RDexSwap::swap_pools(symbol).unwrap();
+        assert_eq!(pool.fis_balance, 140);
+        assert_eq!(pool.rtoken_balance, 900);
+        assert_eq!(pool.total_unit, 109);
+        assert_eq!(Balances::free_balance(RDexSwap::account_id()), 140);
+        assert_eq!(RBalances::free_balance(&RDexSwap::account_id(), symbol), 900);
+    });
+}
@@ -0,0 +1,17 @@
+use sp_runtime::DispatchError;
+use node_primitives::RSymbol;
+
+/// Manipulation resistant prices of rTokens
+pub trait PriceOracle {
+    /// Time weighted average price of one rToken in fis over at least `window` blocks,
+    /// scaled by `PRICE_BASE`. `None` if there is no pool or no price history that old.
+    fn rtoken_price(symbol: RSymbol, window: u32) -> Option<u128>;
+    /// Block the price of the pool last moved at by a swap or a liquidity change. `None` if there is no pool price.
+    fn price_updated_at(symbol: RSymbol) -> Option<u64>;
+}
+
+/// Liquidity added by other pallets
+pub trait LiquidityProvider<AccountId> {
+    /// Adds liquidity of `who` from a single asset, returning the lp unit minted to `who`
+    fn add_single_sided_liquidity(who: &AccountId, symbol: RSymbol, amount: u128, input_is_fis: bool, min_units: u128) -> Result<u128, DispatchError>;
+}
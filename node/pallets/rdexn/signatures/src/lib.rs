@@ -2,16 +2,28 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use sp_std::prelude::*;
+use codec::{Decode, Encode};
 use frame_support::{
     decl_error, decl_event, decl_module, decl_storage,
     dispatch::{DispatchResult}, ensure,
-    traits::{Currency}
+    storage::{IterableStorageDoubleMap, IterableStorageMap},
+    traits::{Currency, Get},
+    weights::Weight,
 };
 
-use frame_system::{self as system, ensure_signed};
+use frame_system::{self as system, ensure_root, ensure_signed};
+use general_signature::{ed25519_verify, eth_recover, SigVerifyResult};
 use node_primitives::{RSymbol, ChainType};
 use rdexn_payers as payers;
+use sp_runtime::{traits::SaturatedConversion, RuntimeDebug};
 
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+/// max blocks of signatures pruned in one block
+pub const MAX_PRUNED_BLOCKS: u64 = 16;
 
 pub trait Trait: system::Trait + rtoken_rate::Trait + payers::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
@@ -19,12 +31,31 @@ pub trait Trait: system::Trait + rtoken_rate::Trait + payers::Trait {
     type Currency: Currency<Self::AccountId>;
 }
 
+/// Storage version of the pallet
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Releases {
+    /// signatures not indexed by block
+    V1_0_0,
+    /// signatures indexed by block in BlockProposals and pruned
+    V2_0_0,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1_0_0
+    }
+}
+
 decl_event! {
     pub enum Event<T> where
         <T as frame_system::Trait>::AccountId
     {
         /// submit signatures: account, symbol, block, proposalId, signature
         SubmitSignatures(AccountId, RSymbol, u64, Vec<u8>, Vec<u8>),
+        /// signatures of a proposal reached the payer threshold: symbol, block, proposalId
+        SignaturesThresholdReached(RSymbol, u64, Vec<u8>),
+        /// payer registered the pubkey it signs with: account, symbol, pubkey
+        PubkeyRegistered(AccountId, RSymbol, Vec<u8>),
     }
 }
 
@@ -32,8 +63,18 @@ decl_error! {
     pub enum Error for Module<T: Trait> {
         /// signature repeated
         SignatureRepeated,
-        /// invalid symbol
+        /// symbol whose signatures can't be submitted, or whose pubkeys can't be registered as its signatures aren't verified
         InvalidRSymbol,
+        /// invalid pubkey for the chain of the symbol
+        InvalidPubkey,
+        /// pubkey registered by another payer
+        PubkeyInUse,
+        /// payer has no pubkey registered
+        PubkeyNotRegistered,
+        /// signature does not verify against the pubkey of the payer
+        InvalidSignature,
+        /// signatures of the block are pruned
+        BlockPruned,
     }
 }
 
@@ -41,6 +82,20 @@ decl_storage! {
     trait Store for Module<T: Trait> as RDexnSignatures {
         pub Signatures get(fn signatures): double_map hasher(blake2_128_concat) RSymbol, hasher(blake2_128_concat) (u64, Vec<u8>) => Option<Vec<Vec<u8>>>;
         pub AccountSignature get(fn account_signature): map hasher(blake2_128_concat) (T::AccountId, RSymbol, u64, Vec<u8>) => Option<Vec<u8>>;
+        /// pubkeys payers sign with: symbol, account => pubkey
+        pub PayerPubkeys get(fn payer_pubkeys): double_map hasher(blake2_128_concat) RSymbol, hasher(blake2_128_concat) T::AccountId => Option<Vec<u8>>;
+        /// payers of pubkeys: (symbol, pubkey) => account
+        pub PubkeyPayer get(fn pubkey_payer): map hasher(blake2_128_concat) (RSymbol, Vec<u8>) => Option<T::AccountId>;
+        /// signers of a proposal, in the order of its signatures: (symbol, block, proposalId) => accounts
+        pub ProposalSigners get(fn proposal_signers): map hasher(blake2_128_concat) (RSymbol, u64, Vec<u8>) => Vec<T::AccountId>;
+        /// proposals signed for a block: block => (symbol, proposalId)
+        pub BlockProposals get(fn block_proposals): map hasher(blake2_128_concat) u64 => Vec<(RSymbol, Vec<u8>)>;
+        /// blocks signatures are kept for
+        pub SignatureRetention get(fn signature_retention): u64 = 100800;
+        /// signatures of blocks up to this one are pruned
+        pub LastPrunedBlock get(fn last_pruned_block): u64;
+        /// storage version
+        StorageVersion get(fn storage_version) build(|_| Releases::V2_0_0): Releases;
     }
 }
 
@@ -48,27 +103,187 @@ decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
         fn deposit_event() = default;
 
+        fn on_initialize(now: T::BlockNumber) -> Weight {
+            let now = now.saturated_into::<u64>();
+            let last_pruned = Self::last_pruned_block();
+            let target = now.saturating_sub(Self::signature_retention()).min(last_pruned.saturating_add(MAX_PRUNED_BLOCKS));
+            if target <= last_pruned {
+                return T::DbWeight::get().reads(2);
+            }
+
+            let mut pruned: Weight = 0;
+            for block in (last_pruned + 1)..=target {
+                pruned = pruned.saturating_add(Self::prune_block(block));
+            }
+            LastPrunedBlock::put(target);
+            let blocks = target - last_pruned;
+            T::DbWeight::get().reads_writes(blocks + pruned + 2, blocks + pruned + 1)
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            if Self::storage_version() == Releases::V1_0_0 {
+                let weight = Self::index_legacy_signatures();
+                StorageVersion::put(Releases::V2_0_0);
+                weight
+            } else {
+                0
+            }
+        }
+
+        /// register the pubkey the payer signs with on the chain of the symbol
+        #[weight = 10_000_000]
+        pub fn register_pubkey(origin, symbol: RSymbol, pubkey: Vec<u8>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::is_verifiable(symbol.chain_type()), Error::<T>::InvalidRSymbol);
+            ensure!(payers::Module::<T>::is_payer(symbol, &who), payers::Error::<T>::MustBePayer);
+            ensure!(Self::check_pubkey(symbol.chain_type(), &pubkey), Error::<T>::InvalidPubkey);
+            ensure!(Self::pubkey_payer((symbol, &pubkey)).is_none(), Error::<T>::PubkeyInUse);
+
+            if let Some(old_pubkey) = Self::payer_pubkeys(symbol, &who) {
+                <PubkeyPayer<T>>::remove((symbol, old_pubkey));
+            }
+            <PayerPubkeys<T>>::insert(symbol, &who, &pubkey);
+            <PubkeyPayer<T>>::insert((symbol, &pubkey), &who);
+            Self::deposit_event(RawEvent::PubkeyRegistered(who, symbol, pubkey));
+            Ok(())
+        }
+
         /// Submit tx signatures
         #[weight = 10_000_000]
         pub fn submit_signatures(origin, symbol: RSymbol, block: u64, proposal_id: Vec<u8>, signature: Vec<u8>) -> DispatchResult {
             let who = ensure_signed(origin)?;
             ensure!(symbol.chain_type() != ChainType::Substrate, Error::<T>::InvalidRSymbol);
             ensure!(payers::Module::<T>::is_payer(symbol, &who), payers::Error::<T>::MustBePayer);
+            ensure!(block > Self::last_pruned_block(), Error::<T>::BlockPruned);
             ensure!(Self::account_signature((&who, symbol, block, &proposal_id)).is_none(), Error::<T>::SignatureRepeated);
+            if Self::is_verifiable(symbol.chain_type()) {
+                let pubkey = Self::payer_pubkeys(symbol, &who).ok_or(Error::<T>::PubkeyNotRegistered)?;
+                ensure!(Self::verify(symbol.chain_type(), &pubkey, &signature, &proposal_id), Error::<T>::InvalidSignature);
+            }
 
             let mut signatures = Signatures::get(symbol, (block, &proposal_id)).unwrap_or(vec![]);
             ensure!(!signatures.contains(&signature), Error::<T>::SignatureRepeated);
 
             //update state
+            if signatures.is_empty() {
+                <BlockProposals>::mutate(block, |proposals| proposals.push((symbol, proposal_id.clone())));
+            }
             signatures.push(signature.clone());
             Signatures::insert(symbol, (block, &proposal_id), &signatures);
             <AccountSignature<T>>::insert((&who, symbol, block, &proposal_id), &signature);
+            <ProposalSigners<T>>::mutate((symbol, block, &proposal_id), |signers| signers.push(who.clone()));
 
-            Self::deposit_event(RawEvent::SubmitSignatures(who.clone(), symbol, block, proposal_id, signature));
+            Self::deposit_event(RawEvent::SubmitSignatures(who.clone(), symbol, block, proposal_id.clone(), signature));
+            if signatures.len() == payers::PayerThreshold::get(symbol) as usize {
+                Self::deposit_event(RawEvent::SignaturesThresholdReached(symbol, block, proposal_id));
+            }
+            Ok(())
+        }
+
+        /// set blocks signatures are kept for
+        #[weight = 10_000]
+        pub fn set_signature_retention(origin, retention: u64) -> DispatchResult {
+            ensure_root(origin)?;
+            SignatureRetention::put(retention);
             Ok(())
         }
     }
 }
 
 impl<T: Trait> Module<T> {
-}
\ No newline at end of file
+    /// Whether signatures of payers on a chain of `chain_type` can be verified on chain. Tendermint
+    /// payers sign the sign doc of the tx, which can't be rebuilt from the proposal id, so their
+    /// signatures are taken unverified.
+    pub fn is_verifiable(chain_type: ChainType) -> bool {
+        match chain_type {
+            ChainType::Solana | ChainType::Ethereum => true,
+            ChainType::Substrate | ChainType::Tendermint => false,
+        }
+    }
+
+    /// Checks `pubkey` has the shape of a key signing on a chain of `chain_type`, an address on ethereum
+    pub fn check_pubkey(chain_type: ChainType, pubkey: &Vec<u8>) -> bool {
+        match chain_type {
+            ChainType::Solana => pubkey.len() == 32,
+            ChainType::Ethereum => pubkey.len() == 20,
+            ChainType::Substrate | ChainType::Tendermint => false,
+        }
+    }
+
+    /// Verifies `signature` of `pubkey` over the proposal id:
+    /// - solana: an ed25519 signature of the raw proposal id bytes, the message of the tx to sign
+    /// - ethereum: a 65 byte `personal_sign` signature of the raw proposal id bytes, so over
+    ///   keccak256("\x19Ethereum Signed Message:\n" + len(proposal_id) + proposal_id), recovering
+    ///   to the address registered as pubkey
+    pub fn verify(chain_type: ChainType, pubkey: &Vec<u8>, signature: &Vec<u8>, proposal_id: &Vec<u8>) -> bool {
+        match chain_type {
+            ChainType::Substrate | ChainType::Tendermint => false,
+            ChainType::Solana => {
+                signature.len() == 64 && ed25519_verify(pubkey, signature, proposal_id) == SigVerifyResult::Pass
+            },
+            ChainType::Ethereum => {
+                if signature.len() != 65 {
+                    return false;
+                }
+                let mut sig = [0u8; 65];
+                sig.copy_from_slice(signature);
+                eth_recover(&sig, proposal_id).map(|signer| signer.to_vec()) == Some(pubkey.clone())
+            },
+        }
+    }
+
+    /// Removes the signatures of the proposals of a block, returning how many proposals were removed
+    fn prune_block(block: u64) -> Weight {
+        let proposals = <BlockProposals>::take(block);
+        for (symbol, proposal_id) in proposals.iter() {
+            Signatures::remove(symbol, (block, proposal_id));
+            for signer in <ProposalSigners<T>>::take((symbol, block, proposal_id)) {
+                <AccountSignature<T>>::remove((signer, symbol, block, proposal_id));
+            }
+        }
+        proposals.len() as Weight
+    }
+
+    /// Indexes signatures submitted before they were indexed by block, so that they get pruned.
+    /// Blocks before the first signed one are skipped by pruning as far as they are due.
+    fn index_legacy_signatures() -> Weight {
+        let mut proposals: Weight = 0;
+        let mut first_block: Option<u64> = None;
+        for (symbol, (block, proposal_id), _) in Signatures::iter() {
+            <BlockProposals>::mutate(block, |block_proposals| block_proposals.push((symbol, proposal_id)));
+            first_block = Some(first_block.map_or(block, |first| first.min(block)));
+            proposals += 1;
+        }
+
+        let mut signers: Weight = 0;
+        for ((who, symbol, block, proposal_id), signature) in <AccountSignature<T>>::iter() {
+            Self::insert_signer(symbol, block, &proposal_id, who, &signature);
+            signers += 1;
+        }
+
+        if let Some(first_block) = first_block {
+            let now = system::Module::<T>::block_number().saturated_into::<u64>();
+            let skipped = first_block.saturating_sub(1).min(now.saturating_sub(Self::signature_retention()));
+            if skipped > Self::last_pruned_block() {
+                LastPrunedBlock::put(skipped);
+            }
+        }
+
+        // placing a signer reads the signatures of the signers before it, a few payers
+        T::DbWeight::get().reads_writes(proposals + signers * 4 + 2, proposals + signers + 1)
+    }
+
+    /// Adds a signer to the signers of a proposal, at the position of its signature
+    fn insert_signer(symbol: RSymbol, block: u64, proposal_id: &Vec<u8>, who: T::AccountId, signature: &Vec<u8>) {
+        let signatures = Signatures::get(symbol, (block, proposal_id)).unwrap_or(vec![]);
+        let position = |signature: &Vec<u8>| signatures.iter().position(|s| s == signature);
+        let who_position = position(signature);
+        <ProposalSigners<T>>::mutate((symbol, block, proposal_id), |signers| {
+            let index = signers
+                .iter()
+                .take_while(|signer| Self::account_signature((*signer, symbol, block, proposal_id)).and_then(|s| position(&s)) < who_position)
+                .count();
+            signers.insert(index, who);
+        });
+    }
+}
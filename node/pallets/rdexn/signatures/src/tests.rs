@@ -0,0 +1,191 @@
+use super::mock::*;
+use super::*;
+use frame_support::{assert_noop, assert_ok, traits::{OnInitialize, OnRuntimeUpgrade}, StorageDoubleMap, StorageMap};
+use general_signature::to_ascii_hex;
+use hex_literal::hex;
+use sp_core::{ed25519, Pair};
+
+fn proposal_id() -> Vec<u8> {
+    hex!("1c8aff950685c2ed4bc3174f3472287b56d9517b9c948127319a09a7a36deac8").to_vec()
+}
+
+fn solana_payer(seed: u8) -> ed25519::Pair {
+    ed25519::Pair::from_seed(&[seed; 32])
+}
+
+fn setup_payers(symbol: RSymbol) {
+    assert_ok!(payers::Module::<Test>::add_payer(Origin::root(), symbol, PAYER_A));
+    assert_ok!(payers::Module::<Test>::add_payer(Origin::root(), symbol, PAYER_B));
+    assert_ok!(payers::Module::<Test>::set_threshold(Origin::root(), symbol, 2));
+}
+
+#[test]
+fn solana_signature_should_be_over_raw_proposal_id() {
+    new_test_ext().execute_with(|| {
+        let pair = solana_payer(1);
+        let pubkey = pair.public().0.to_vec();
+        let signature = pair.sign(&proposal_id()).0.to_vec();
+        assert!(RDexnSignatures::verify(ChainType::Solana, &pubkey, &signature, &proposal_id()));
+
+        // not over its hex as general_signature::verify_signature expects
+        let hex_signature = pair.sign(&to_ascii_hex(&proposal_id())).0.to_vec();
+        assert!(!RDexnSignatures::verify(ChainType::Solana, &pubkey, &hex_signature, &proposal_id()));
+        let other = solana_payer(2).public().0.to_vec();
+        assert!(!RDexnSignatures::verify(ChainType::Solana, &other, &signature, &proposal_id()));
+    });
+}
+
+#[test]
+fn ethereum_signature_should_be_personal_sign_of_proposal_id() {
+    new_test_ext().execute_with(|| {
+        let address = hex!("80379e6507d9de50ce45f3365693b1943da8f260").to_vec();
+        // personal_sign: keccak256("\x19Ethereum Signed Message:\n32" + proposal id)
+        let signature = hex!("e8b5ed23cc4b3f86974f15862047f2f7e681406d4510d7db865aef54ac938fd04be78789d6010a20766994865b14776699364672adf3c1efadac310e9d1f627301").to_vec();
+        // same key over keccak256(proposal id), without the prefix
+        let raw_signature = hex!("18beaa970babca9d3e6a2682c1ee5327ca1deeb23ca06a59ebb43c838f731f0449567091221efcc4b8bbb4faee808b76328c104cdc35a333797bcf9b950c0ce701").to_vec();
+
+        assert!(RDexnSignatures::verify(ChainType::Ethereum, &address, &signature, &proposal_id()));
+        assert!(!RDexnSignatures::verify(ChainType::Ethereum, &address, &raw_signature, &proposal_id()));
+        assert!(!RDexnSignatures::verify(ChainType::Ethereum, &address, &signature[..64].to_vec(), &proposal_id()));
+    });
+}
+
+fn threshold_reached_events() -> usize {
+    System::events()
+        .into_iter()
+        .filter(|record| matches!(record.event, TestEvent::rdexn_signatures(RawEvent::SignaturesThresholdReached(..))))
+        .count()
+}
+
+#[test]
+fn tendermint_signatures_should_be_taken_unverified() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let symbol = RSymbol::RATOM;
+        setup_payers(symbol);
+        let pubkey = vec![2; 33];
+        assert!(!RDexnSignatures::verify(ChainType::Tendermint, &pubkey, &vec![1; 64], &proposal_id()));
+        assert_noop!(
+            RDexnSignatures::register_pubkey(Origin::signed(PAYER_A), symbol, pubkey),
+            Error::<Test>::InvalidRSymbol
+        );
+
+        assert_noop!(
+            RDexnSignatures::submit_signatures(Origin::signed(3), symbol, 1, proposal_id(), vec![1; 64]),
+            payers::Error::<Test>::MustBePayer
+        );
+        assert_ok!(RDexnSignatures::submit_signatures(Origin::signed(PAYER_A), symbol, 1, proposal_id(), vec![1; 64]));
+        assert_noop!(
+            RDexnSignatures::submit_signatures(Origin::signed(PAYER_A), symbol, 1, proposal_id(), vec![2; 64]),
+            Error::<Test>::SignatureRepeated
+        );
+        assert_noop!(
+            RDexnSignatures::submit_signatures(Origin::signed(PAYER_B), symbol, 1, proposal_id(), vec![1; 64]),
+            Error::<Test>::SignatureRepeated
+        );
+        assert_eq!(threshold_reached_events(), 0);
+
+        assert_ok!(RDexnSignatures::submit_signatures(Origin::signed(PAYER_B), symbol, 1, proposal_id(), vec![2; 64]));
+        assert_eq!(RDexnSignatures::signatures(symbol, (1, proposal_id())), Some(vec![vec![1; 64], vec![2; 64]]));
+        assert_eq!(RDexnSignatures::proposal_signers((symbol, 1, proposal_id())), vec![PAYER_A, PAYER_B]);
+        assert_eq!(threshold_reached_events(), 1);
+    });
+}
+
+#[test]
+fn substrate_signatures_should_be_rejected() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RDOT;
+        setup_payers(symbol);
+        assert_noop!(
+            RDexnSignatures::submit_signatures(Origin::signed(PAYER_A), symbol, 1, proposal_id(), vec![1; 64]),
+            Error::<Test>::InvalidRSymbol
+        );
+    });
+}
+
+#[test]
+fn submit_signatures_should_work() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RSOL;
+        setup_payers(symbol);
+        let pair = solana_payer(1);
+        let signature = pair.sign(&proposal_id()).0.to_vec();
+
+        assert_noop!(
+            RDexnSignatures::submit_signatures(Origin::signed(PAYER_A), symbol, 1, proposal_id(), signature.clone()),
+            Error::<Test>::PubkeyNotRegistered
+        );
+        assert_ok!(RDexnSignatures::register_pubkey(Origin::signed(PAYER_A), symbol, pair.public().0.to_vec()));
+        assert_noop!(
+            RDexnSignatures::register_pubkey(Origin::signed(PAYER_B), symbol, pair.public().0.to_vec()),
+            Error::<Test>::PubkeyInUse
+        );
+        assert_noop!(
+            RDexnSignatures::submit_signatures(Origin::signed(PAYER_A), symbol, 1, vec![1], signature.clone()),
+            Error::<Test>::InvalidSignature
+        );
+        assert_ok!(RDexnSignatures::submit_signatures(Origin::signed(PAYER_A), symbol, 1, proposal_id(), signature.clone()));
+        assert_noop!(
+            RDexnSignatures::submit_signatures(Origin::signed(PAYER_A), symbol, 1, proposal_id(), signature.clone()),
+            Error::<Test>::SignatureRepeated
+        );
+
+        assert_eq!(RDexnSignatures::signatures(symbol, (1, proposal_id())), Some(vec![signature]));
+        assert_eq!(RDexnSignatures::proposal_signers((symbol, 1, proposal_id())), vec![PAYER_A]);
+        assert_eq!(RDexnSignatures::block_proposals(1), vec![(symbol, proposal_id())]);
+    });
+}
+
+#[test]
+fn legacy_signatures_should_be_indexed_and_pruned() {
+    new_test_ext().execute_with(|| {
+        let symbol = RSymbol::RSOL;
+        let (sig_a, sig_b) = (vec![1; 64], vec![2; 64]);
+        // signatures submitted before they were indexed by block, B signed first
+        Signatures::insert(symbol, (500, proposal_id()), vec![sig_b.clone(), sig_a.clone()]);
+        Signatures::insert(symbol, (600, proposal_id()), vec![sig_a.clone()]);
+        <AccountSignature<Test>>::insert((PAYER_A, symbol, 500, proposal_id()), &sig_a);
+        <AccountSignature<Test>>::insert((PAYER_B, symbol, 500, proposal_id()), &sig_b);
+        <AccountSignature<Test>>::insert((PAYER_A, symbol, 600, proposal_id()), &sig_a);
+        assert_eq!(RDexnSignatures::storage_version(), Releases::V1_0_0);
+
+        System::set_block_number(1000);
+        assert_ok!(RDexnSignatures::set_signature_retention(Origin::root(), 100));
+        RDexnSignatures::on_runtime_upgrade();
+
+        assert_eq!(RDexnSignatures::storage_version(), Releases::V2_0_0);
+        assert_eq!(RDexnSignatures::block_proposals(500), vec![(symbol, proposal_id())]);
+        assert_eq!(RDexnSignatures::block_proposals(600), vec![(symbol, proposal_id())]);
+        assert_eq!(RDexnSignatures::proposal_signers((symbol, 500, proposal_id())), vec![PAYER_B, PAYER_A]);
+        assert_eq!(RDexnSignatures::proposal_signers((symbol, 600, proposal_id())), vec![PAYER_A]);
+        // no signature before block 500 to prune
+        assert_eq!(RDexnSignatures::last_pruned_block(), 499);
+
+        RDexnSignatures::on_initialize(1000);
+        assert_eq!(RDexnSignatures::last_pruned_block(), 499 + MAX_PRUNED_BLOCKS);
+        assert_eq!(RDexnSignatures::signatures(symbol, (500, proposal_id())), None);
+        assert_eq!(RDexnSignatures::account_signature((PAYER_A, symbol, 500, proposal_id())), None);
+        assert_eq!(RDexnSignatures::account_signature((PAYER_B, symbol, 500, proposal_id())), None);
+        assert!(RDexnSignatures::proposal_signers((symbol, 500, proposal_id())).is_empty());
+        assert!(RDexnSignatures::signatures(symbol, (600, proposal_id())).is_some());
+
+        // indexed once
+        assert_eq!(RDexnSignatures::on_runtime_upgrade(), 0);
+    });
+}
+
+#[test]
+fn genesis_built_chain_should_skip_indexing() {
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    GenesisConfig::default().assimilate_storage::<Test>(&mut t).unwrap();
+    sp_io::TestExternalities::from(t).execute_with(|| {
+        assert_eq!(RDexnSignatures::storage_version(), Releases::V2_0_0);
+        System::set_block_number(1000);
+        Signatures::insert(RSymbol::RSOL, (500, proposal_id()), vec![vec![1; 64]]);
+
+        assert_eq!(RDexnSignatures::on_runtime_upgrade(), 0);
+        assert!(RDexnSignatures::block_proposals(500).is_empty());
+        assert_eq!(RDexnSignatures::last_pruned_block(), 0);
+    });
+}
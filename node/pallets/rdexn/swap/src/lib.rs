@@ -1,10 +1,14 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, Encode};
 use frame_support::{
     decl_error, decl_event, decl_module, decl_storage,
-    dispatch::DispatchResult,
+    dispatch::{DispatchError, DispatchResult},
     ensure,
-    traits::{Currency, ExistenceRequirement::{KeepAlive}},
+    storage::{migration::{put_storage_value, StorageIterator}, IterableStorageMap},
+    traits::{Currency, ExistenceRequirement::{AllowDeath, KeepAlive}, Get},
+    transactional,
+    weights::Weight,
 };
 use sp_std::prelude::*;
 
@@ -14,19 +18,47 @@ use rtoken_balances::traits::Currency as RCurrency;
 use general_signature::verify_recipient;
 use rtoken_rate as RTokenRate;
 use rdexn_payers as RDexnPayers;
+use rdex_swap::{PriceOracle, PRICE_BASE};
 use sp_arithmetic::helpers_128bit::multiply_by_rational;
-use sp_runtime::traits::{SaturatedConversion};
+use sp_runtime::{traits::{AccountIdConversion, SaturatedConversion}, ModuleId, RuntimeDebug};
 pub trait Trait: system::Trait + RTokenRate::Trait + RDexnPayers::Trait{
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
     /// currency of rtoken
     type RCurrency: RCurrency<Self::AccountId>;
     /// The currency mechanism.
     type Currency: Currency<Self::AccountId>;
+    /// prices of the rdex_swap pools
+    type PriceOracle: PriceOracle;
 }
 
 pub mod models;
 pub use models::*;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
 pub const RATEBASE: u128 = 1_000_000_000_000;
+/// max points of the discount and premium curve of a derived swap rate
+pub const MAX_CURVE_POINTS: usize = 16;
+const MODULE_ID: ModuleId = ModuleId(*b"rdxn/swp");
+
+/// Storage version of the pallet
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Releases {
+    /// swap transaction infos without tx hash and rtoken amount
+    V1_0_0,
+    /// swap transaction infos carry the payout tx hash and can be refunded
+    V2_0_0,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1_0_0
+    }
+}
+
 decl_event! {
     pub enum Event<T> where
         AccountId = <T as system::Trait>::AccountId
@@ -35,12 +67,28 @@ decl_event! {
         SwapRTokenToNative(AccountId, Vec<u8>, RSymbol, u64, Balance, u128, u128, u64, u128),
         /// swap rFIS to FIS: account, rtoken amount, out amount, rtoken rate, swap rate
         SwapRFisToFis(AccountId, AccountId, u128, u128, u64, u128),
-        /// report with block: account, symbol, deal block
-        ReportTransResultWithBlock(AccountId, RSymbol, u64),
-        /// report with index: account, symbol, deal block
-        ReportTransResultWithIndex(AccountId, RSymbol, u64, u32),
+        /// report with block: account, symbol, deal block, tx hash
+        ReportTransResultWithBlock(AccountId, RSymbol, u64, Vec<u8>),
+        /// report with index: account, symbol, deal block, index, tx hash
+        ReportTransResultWithIndex(AccountId, RSymbol, u64, u32, Vec<u8>),
         /// report with index: account, symbol, deal block
         ReportTransResultWithIndexBlockEnd(AccountId, RSymbol, u64),
+        /// refund swap: account, symbol, trans block, index, rtoken amount, out amount
+        RefundSwap(AccountId, RSymbol, u64, u32, u128, u128),
+        /// set derived swap rate: symbol, grade, derived swap rate
+        DerivedSwapRateSet(RSymbol, u8, DerivedSwapRate),
+        /// price fed: feeder, symbol, price
+        PriceFed(AccountId, RSymbol, u128),
+        /// limit order placed: account, symbol, order id, rtoken amount, min out amount, grade
+        LimitOrderPlaced(AccountId, RSymbol, u64, u128, u128, u8),
+        /// limit order filled: account, symbol, order id, trans block
+        LimitOrderFilled(AccountId, RSymbol, u64, u64),
+        /// limit order cancelled: account, symbol, order id
+        LimitOrderCancelled(AccountId, RSymbol, u64),
+        /// limit order expired and refunded: account, symbol, order id
+        LimitOrderExpired(AccountId, RSymbol, u64),
+        /// limit order crossing its min out amount failed to fill and stays open: symbol, order id, error
+        LimitOrderFillFailed(RSymbol, u64, DispatchError),
     }
 }
 
@@ -78,6 +126,32 @@ decl_error! {
         VoterRepeat,
         /// get trans info failed
         GetTransInfoFailed,
+        /// tx hash differs from the one of other payers
+        TxHashMismatch,
+        /// swap already dealt
+        AlreadyDeal,
+        /// swap already refunded
+        AlreadyRefunded,
+        /// swap not made by the account
+        NotSwapOwner,
+        /// swap made before rtoken amounts were recorded
+        NotRefundable,
+        /// payout of the swap has not timed out
+        PayoutNotTimedOut,
+        /// payers already reported on the payout
+        PayoutReported,
+        /// rate source not supported for the symbol
+        RateSourceNotSupported,
+        /// rate source has no fresh price
+        RateSourceStale,
+        /// not a price feeder
+        NotPriceFeeder,
+        /// limit order not exist
+        LimitOrderNotExist,
+        /// limit order not placed by the account
+        NotOrderOwner,
+        /// too many open limit orders
+        OpenOrdersOverflow,
     }
 }
 
@@ -108,6 +182,32 @@ decl_storage! {
         pub VoteInfos get(fn vote_infos): map hasher(blake2_128_concat) (RSymbol, u64) => Option<Vec<T::AccountId>>;
         /// vote info with index
         pub VoteInfosWithIndex get(fn vote_infos_with_index): map hasher(blake2_128_concat) (RSymbol, u64, u32) => Option<Vec<T::AccountId>>;
+        /// payout tx hash voted with block
+        pub VoteTxHashes get(fn vote_tx_hashes): map hasher(blake2_128_concat) (RSymbol, u64) => Option<Vec<u8>>;
+        /// payout tx hash voted with index
+        pub VoteTxHashesWithIndex get(fn vote_tx_hashes_with_index): map hasher(blake2_128_concat) (RSymbol, u64, u32) => Option<Vec<u8>>;
+        /// blocks after the trans block an unpaid swap can be refunded
+        pub PayoutTimeout get(fn payout_timeout): map hasher(blake2_128_concat) RSymbol => u64 = 14400;
+        /// swap rates derived from a price source, used instead of the swap rate of the grade
+        pub DerivedSwapRates get(fn derived_swap_rates): map hasher(blake2_128_concat) (RSymbol, u8) => Option<DerivedSwapRate>;
+        /// accounts allowed to feed prices
+        pub PriceFeeders get(fn price_feeders): map hasher(blake2_128_concat) T::AccountId => bool = false;
+        /// fed prices
+        pub PriceFeeds get(fn price_feeds): map hasher(blake2_128_concat) RSymbol => Option<PriceFeed>;
+        /// next limit order id
+        pub NextOrderId get(fn next_order_id): map hasher(blake2_128_concat) RSymbol => u64;
+        /// limit orders: (symbol, order id) => limit order
+        pub LimitOrders get(fn limit_orders): map hasher(blake2_128_concat) (RSymbol, u64) => Option<LimitOrder<T::AccountId>>;
+        /// ids of open limit orders, in the order they are matched
+        pub OpenOrders get(fn open_orders): map hasher(blake2_128_concat) RSymbol => Vec<u64>;
+        /// last error an open limit order failed to fill with, a failure is reported when it changes
+        pub LimitOrderFillErrors get(fn limit_order_fill_errors): map hasher(blake2_128_concat) (RSymbol, u64) => Option<DispatchError>;
+        /// max open limit orders of a symbol
+        pub MaxOpenOrders get(fn max_open_orders): u32 = 1000;
+        /// limit orders tried in on_initialize of each block
+        pub OrdersMatchedPerBlock get(fn orders_matched_per_block): u32 = 20;
+        /// storage version
+        StorageVersion get(fn storage_version) build(|_| Releases::V2_0_0): Releases;
     }
 }
 
@@ -115,53 +215,92 @@ decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
         fn deposit_event() = default;
 
+        fn on_initialize(_now: T::BlockNumber) -> Weight {
+            let mut left = Self::orders_matched_per_block();
+            let symbols = OpenOrders::iter().map(|(symbol, _)| symbol).collect::<Vec<RSymbol>>();
+            let mut weight = T::DbWeight::get().reads(symbols.len() as Weight + 1);
+            for symbol in symbols {
+                if left == 0 {
+                    break;
+                }
+                let tried = Self::match_open_orders(symbol, left);
+                left = left.saturating_sub(tried);
+                weight = weight.saturating_add(T::DbWeight::get().reads_writes(12, 8).saturating_mul(tried as Weight));
+            }
+            weight
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            if Self::storage_version() == Releases::V1_0_0 {
+                let weight = Self::migrate_trans_infos();
+                StorageVersion::put(Releases::V2_0_0);
+                weight
+            } else {
+                0
+            }
+        }
+
         /// swap rtoken for native token
         #[weight = 10_000_000_000]
         pub fn swap_rtoken_for_native_token(origin, receiver: Vec<u8>, symbol: RSymbol, rtoken_amount: u128, min_out_amount: u128, grade: u8) -> DispatchResult {
-            let who = ensure_signed(origin)?;   
+            let who = ensure_signed(origin)?;
+            let fee_amount = Self::swap_fees(symbol);
+            Self::do_swap_rtoken_for_native_token(&who, &who, receiver, symbol, rtoken_amount, min_out_amount, grade, fee_amount)?;
+            Ok(())
+        }
+
+        /// place a limit order swapping rtoken for native token once it gets at least min out amount,
+        /// the rtoken and swap fee are escrowed until it fills, is cancelled or expires after expire block
+        #[weight = 10_000_000_000]
+        pub fn place_limit_order(origin, receiver: Vec<u8>, symbol: RSymbol, rtoken_amount: u128, min_out_amount: u128, grade: u8, expire_block: u64) -> DispatchResult {
+            let who = ensure_signed(origin)?;
             let now_block = system::Module::<T>::block_number().saturated_into::<u64>();
-            let fund_addr = Self::fund_address().ok_or(Error::<T>::NoFundAddress)?; 
-            let rtoken_rate = RTokenRate::Rate::get(symbol).ok_or(Error::<T>::RTokenRateFailed)?;
             let fee_amount = Self::swap_fees(symbol);
-            let swap_rate = Self::swap_rates((symbol, grade)).ok_or(Error::<T>::SwapRateFailed)?;
-            let trans_block = now_block.checked_add(swap_rate.lock_number).ok_or(Error::<T>::OverFlow)?;
-            let out_reserve = Self::native_token_reserves(symbol);
+            let mut open_orders = Self::open_orders(symbol);
+            let order_id = Self::next_order_id(symbol);
 
-            
             ensure!(Self::swap_total_switch(), Error::<T>::SwapTotalClosed);
             ensure!(Self::swap_rtoken_switch(symbol), Error::<T>::SwapRtokenClosed);
             ensure!(rtoken_amount > u128::MIN, Error::<T>::ParamsErr);
             ensure!(min_out_amount > u128::MIN, Error::<T>::ParamsErr);
-            ensure!(rtoken_rate > 0, Error::<T>::RTokenRateFailed);
-            ensure!(swap_rate.rate > 0, Error::<T>::SwapRateFailed);
+            ensure!(expire_block > now_block, Error::<T>::ParamsErr);
+            ensure!(Self::swap_rates((symbol, grade)).is_some() || Self::derived_swap_rates((symbol, grade)).is_some(), Error::<T>::SwapRateFailed);
+            ensure!(open_orders.len() < Self::max_open_orders() as usize, Error::<T>::OpenOrdersOverflow);
             ensure!(T::RCurrency::free_balance(&who, symbol) >= rtoken_amount, Error::<T>::RTokenAmountNotEnough);
+            ensure!(verify_recipient(symbol, &receiver), Error::<T>::ReceiverInvalid);
 
-            // check receiver
-            match verify_recipient(symbol, &receiver) {
-                false => Err(Error::<T>::ReceiverInvalid)?,
-                _ => (),
+            if fee_amount > 0 {
+                T::Currency::transfer(&who, &Self::account_id(), fee_amount.saturated_into(), KeepAlive)?;
             }
+            T::RCurrency::transfer(&who, &Self::account_id(), symbol, rtoken_amount)?;
+            open_orders.push(order_id);
+            let order = LimitOrder{account: who.clone(), receiver, rtoken_amount, min_out_amount, grade, fee_amount, place_block: now_block, expire_block};
+            <LimitOrders<T>>::insert((symbol, order_id), order);
+            OpenOrders::insert(symbol, open_orders);
+            NextOrderId::insert(symbol, order_id + 1);
+            Self::deposit_event(RawEvent::LimitOrderPlaced(who, symbol, order_id, rtoken_amount, min_out_amount, grade));
+            Ok(())
+        }
 
-            // check limit per block
-            let mut trans_block_trans_info = Self::trans_infos((symbol, trans_block)).unwrap_or(vec![]);
-            ensure!(trans_block_trans_info.len() < Self::swap_limit_per_block() as usize, Error::<T>::OverSwapLimitPerBlock);
-            
-            // check min out amount and reserve amount
-            let temp_out_amount = RTokenRate::Module::<T>::rtoken_to_token(symbol, rtoken_amount);
-            let out_amount = multiply_by_rational(temp_out_amount, swap_rate.rate, RATEBASE.into()).unwrap_or(u128::MIN) as u128;
+        /// cancel a limit order, its escrowed rtoken and swap fee are returned
+        #[weight = 1_000_000_000]
+        pub fn cancel_limit_order(origin, symbol: RSymbol, order_id: u64) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let order = Self::limit_orders((symbol, order_id)).ok_or(Error::<T>::LimitOrderNotExist)?;
+            ensure!(order.account == who, Error::<T>::NotOrderOwner);
 
-            ensure!(out_amount >= min_out_amount, Error::<T>::LessThanMinOutAmount);
-            ensure!(out_amount < out_reserve, Error::<T>::NativeTokenReserveNotEnough);
+            Self::refund_limit_order(symbol, order_id, &order)?;
+            let open_orders = Self::open_orders(symbol).into_iter().filter(|id| *id != order_id).collect::<Vec<u64>>();
+            Self::put_open_orders(symbol, open_orders);
+            Self::deposit_event(RawEvent::LimitOrderCancelled(who, symbol, order_id));
+            Ok(())
+        }
 
-            //update state
-            if fee_amount > 0 {
-                T::Currency::transfer(&who, &fund_addr, fee_amount.saturated_into(), KeepAlive)?;
-            }
-            T::RCurrency::transfer(&who, &fund_addr, symbol, rtoken_amount)?;
-            trans_block_trans_info.push(SwapTransactionInfo{account: who.clone(), receiver: receiver.clone(), value: out_amount, is_deal: false});
-            <TransInfos<T>>::insert((symbol, trans_block), trans_block_trans_info);
-            NativeTokenReserves::insert(symbol, out_reserve.saturating_sub(out_amount));
-            Self::deposit_event(RawEvent::SwapRTokenToNative(who.clone(), receiver.clone(), symbol, trans_block, fee_amount, rtoken_amount, out_amount, rtoken_rate, swap_rate.rate));
+        /// fill the open limit orders of a symbol that cross their limit, trying at most max orders
+        #[weight = 10_000_000_000]
+        pub fn match_orders(origin, symbol: RSymbol, max_orders: u32) -> DispatchResult {
+            ensure_signed(origin)?;
+            Self::match_open_orders(symbol, max_orders);
             Ok(())
         }
 
@@ -180,7 +319,7 @@ decl_module! {
             let fund_addr = Self::fund_address().ok_or(Error::<T>::NoFundAddress)?; 
             let native_pool_addr = Self::native_pool_address().ok_or(Error::<T>::NoNativePoolAddress)?; 
             let rtoken_rate = RTokenRate::Rate::get(symbol).ok_or(Error::<T>::RTokenRateFailed)?;
-            let swap_rate = Self::swap_rates((symbol, grade)).ok_or(Error::<T>::SwapRateFailed)?;
+            let swap_rate = Self::current_swap_rate(symbol, grade)?;
             
             ensure!(rtoken_rate > 0, Error::<T>::RTokenRateFailed);
             ensure!(swap_rate.rate > 0, Error::<T>::SwapRateFailed);
@@ -202,40 +341,52 @@ decl_module! {
 
         /// report transfer result with block
         #[weight = 100_000]
-        pub fn report_transfer_result_with_block(origin, symbol: RSymbol, block: u64) -> DispatchResult {
+        pub fn report_transfer_result_with_block(origin, symbol: RSymbol, block: u64, tx_hash: Vec<u8>) -> DispatchResult {
             let who = ensure_signed(origin)?;
             // check
             ensure!(RDexnPayers::Module::<T>::is_payer(symbol, &who), RDexnPayers::Error::<T>::MustBePayer);
+            ensure!(!tx_hash.is_empty(), Error::<T>::ParamsErr);
             let mut trans_block_trans_info = Self::trans_infos((symbol, block)).unwrap_or(vec![]);
             
             let mut vote_infos = Self::vote_infos((symbol, block)).unwrap_or(vec![]);
             ensure!(!vote_infos.contains(&who), Error::<T>::VoterRepeat);
+            match Self::vote_tx_hashes((symbol, block)) {
+                Some(voted_hash) => ensure!(voted_hash == tx_hash, Error::<T>::TxHashMismatch),
+                None => VoteTxHashes::insert((symbol, block), &tx_hash),
+            }
 
             vote_infos.push(who.clone());
             <VoteInfos<T>>::insert((symbol, block), &vote_infos);
 
             if vote_infos.len() == RDexnPayers::PayerThreshold::get(symbol) as usize {
                 LatestDealBlock::insert(symbol, block);
-                for trans_info in trans_block_trans_info.iter_mut() {
+                for trans_info in trans_block_trans_info.iter_mut().filter(|trans_info| !trans_info.is_refunded) {
                     trans_info.is_deal = true;
+                    trans_info.tx_hash = tx_hash.clone();
                 }
                 <TransInfos<T>>::insert((symbol, block), trans_block_trans_info);
-                Self::deposit_event(RawEvent::ReportTransResultWithBlock(who.clone(), symbol, block));
+                Self::deposit_event(RawEvent::ReportTransResultWithBlock(who.clone(), symbol, block, tx_hash));
             }
             Ok(())
         }
 
         /// report transfer result with index
         #[weight = 100_000]
-        pub fn report_transfer_result_with_index(origin, symbol: RSymbol, block: u64, index: u32) -> DispatchResult {
+        pub fn report_transfer_result_with_index(origin, symbol: RSymbol, block: u64, index: u32, tx_hash: Vec<u8>) -> DispatchResult {
             let who = ensure_signed(origin)?;
             // check
             ensure!(RDexnPayers::Module::<T>::is_payer(symbol, &who), RDexnPayers::Error::<T>::MustBePayer);
+            ensure!(!tx_hash.is_empty(), Error::<T>::ParamsErr);
             let mut trans_block_trans_info = Self::trans_infos((symbol, block)).unwrap_or(vec![]);
             ensure!(trans_block_trans_info.len() > index as usize, Error::<T>::ParamsErr);
+            ensure!(!trans_block_trans_info[index as usize].is_refunded, Error::<T>::AlreadyRefunded);
             
             let mut vote_infos = Self::vote_infos_with_index((symbol, block, index)).unwrap_or(vec![]);
             ensure!(!vote_infos.contains(&who), Error::<T>::VoterRepeat);
+            match Self::vote_tx_hashes_with_index((symbol, block, index)) {
+                Some(voted_hash) => ensure!(voted_hash == tx_hash, Error::<T>::TxHashMismatch),
+                None => VoteTxHashesWithIndex::insert((symbol, block, index), &tx_hash),
+            }
 
             vote_infos.push(who.clone());
             <VoteInfosWithIndex<T>>::insert((symbol, block, index), &vote_infos);
@@ -243,11 +394,12 @@ decl_module! {
             if vote_infos.len() == RDexnPayers::PayerThreshold::get(symbol) as usize {
                 let trans_info = trans_block_trans_info.get_mut(index as usize).ok_or(Error::<T>::GetTransInfoFailed)?;
                 trans_info.is_deal = true;
+                trans_info.tx_hash = tx_hash.clone();
                 <TransInfos<T>>::insert((symbol, block), trans_block_trans_info.clone());
-                Self::deposit_event(RawEvent::ReportTransResultWithIndex(who.clone(), symbol, block, index));
+                Self::deposit_event(RawEvent::ReportTransResultWithIndex(who.clone(), symbol, block, index, tx_hash));
                 let mut block_deal_ok = true;
                 for trans in trans_block_trans_info.iter() {
-                    if !trans.is_deal {
+                    if !trans.is_deal && !trans.is_refunded {
                         block_deal_ok = false;
                         break;
                     }
@@ -260,6 +412,34 @@ decl_module! {
             Ok(())
         }
 
+        /// refund the rtoken of a swap whose payout was not reported within the payout timeout
+        #[weight = 1_000_000_000]
+        pub fn refund_swap(origin, symbol: RSymbol, block: u64, index: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let now_block = system::Module::<T>::block_number().saturated_into::<u64>();
+            let fund_addr = Self::fund_address().ok_or(Error::<T>::NoFundAddress)?;
+            let mut trans_block_trans_info = Self::trans_infos((symbol, block)).ok_or(Error::<T>::GetTransInfoFailed)?;
+            let trans_info = trans_block_trans_info.get_mut(index as usize).ok_or(Error::<T>::GetTransInfoFailed)?;
+
+            ensure!(trans_info.account == who, Error::<T>::NotSwapOwner);
+            ensure!(!trans_info.is_deal, Error::<T>::AlreadyDeal);
+            ensure!(!trans_info.is_refunded, Error::<T>::AlreadyRefunded);
+            ensure!(trans_info.rtoken_amount > 0, Error::<T>::NotRefundable);
+            ensure!(now_block > block.saturating_add(Self::payout_timeout(symbol)), Error::<T>::PayoutNotTimedOut);
+            // a payer reporting means the payout may have been made
+            ensure!(Self::vote_infos((symbol, block)).is_none(), Error::<T>::PayoutReported);
+            ensure!(Self::vote_infos_with_index((symbol, block, index)).is_none(), Error::<T>::PayoutReported);
+
+            let rtoken_amount = trans_info.rtoken_amount;
+            let out_amount = trans_info.value;
+            T::RCurrency::transfer(&fund_addr, &who, symbol, rtoken_amount)?;
+            trans_info.is_refunded = true;
+            <TransInfos<T>>::insert((symbol, block), trans_block_trans_info);
+            NativeTokenReserves::mutate(symbol, |reserve| *reserve = reserve.saturating_add(out_amount));
+            Self::deposit_event(RawEvent::RefundSwap(who, symbol, block, index, rtoken_amount, out_amount));
+            Ok(())
+        }
+
         /// turn on/off swap total switch, default closed
         #[weight = 100_000]
         fn toggle_swap_total_switch(origin) -> DispatchResult {
@@ -318,6 +498,62 @@ decl_module! {
             Ok(())
         }
 
+        /// set payout timeout
+        #[weight = 100_000]
+        fn set_payout_timeout(origin, symbol: RSymbol, timeout: u64) -> DispatchResult {
+            ensure_root(origin)?;
+            PayoutTimeout::insert(symbol, timeout);
+            Ok(())
+        }
+
+        /// derive the swap rate of a grade from a price source
+        #[weight = 100_000]
+        fn set_derived_swap_rate(origin, symbol: RSymbol, grade: u8, lock_number: u64, source: RateSource, curve: Vec<RateCurvePoint>, max_age: u64) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(!curve.is_empty() && curve.len() <= MAX_CURVE_POINTS, Error::<T>::ParamsErr);
+            ensure!(curve.iter().all(|point| point.discount <= RATEBASE), Error::<T>::ParamsErr);
+            ensure!(curve.windows(2).all(|pair| pair[0].market_rate < pair[1].market_rate), Error::<T>::ParamsErr);
+            if let RateSource::PoolPrice(_) = source {
+                ensure!(symbol == RSymbol::RFIS, Error::<T>::RateSourceNotSupported);
+            }
+            let derived = DerivedSwapRate{lock_number, source, curve, max_age};
+            DerivedSwapRates::insert((symbol, grade), &derived);
+            Self::deposit_event(RawEvent::DerivedSwapRateSet(symbol, grade, derived));
+            Ok(())
+        }
+
+        /// go back to the swap rate set for a grade
+        #[weight = 100_000]
+        fn remove_derived_swap_rate(origin, symbol: RSymbol, grade: u8) -> DispatchResult {
+            ensure_root(origin)?;
+            DerivedSwapRates::remove((symbol, grade));
+            Ok(())
+        }
+
+        /// add or remove a price feeder
+        #[weight = 100_000]
+        fn set_price_feeder(origin, feeder: T::AccountId, enable: bool) -> DispatchResult {
+            ensure_root(origin)?;
+            if enable {
+                <PriceFeeders<T>>::insert(feeder, true);
+            } else {
+                <PriceFeeders<T>>::remove(feeder);
+            }
+            Ok(())
+        }
+
+        /// feed the price of one rToken in native token, scaled by RATEBASE
+        #[weight = 100_000]
+        pub fn feed_price(origin, symbol: RSymbol, price: u128) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::price_feeders(&who), Error::<T>::NotPriceFeeder);
+            ensure!(price > 0, Error::<T>::PriceZero);
+            let now_block = system::Module::<T>::block_number().saturated_into::<u64>();
+            PriceFeeds::insert(symbol, PriceFeed{price, block: now_block});
+            Self::deposit_event(RawEvent::PriceFed(who, symbol, price));
+            Ok(())
+        }
+
         #[weight = 100_000]
         fn set_swap_limit_per_block(origin, limit: u32) -> DispatchResult {
             ensure_root(origin)?;
@@ -330,7 +566,7 @@ decl_module! {
             ensure_root(origin)?;
             LatestDealBlock::insert(symbol, block);
             let mut trans_block_trans_info = Self::trans_infos((symbol, block)).unwrap_or(vec![]);
-            for trans_info in trans_block_trans_info.iter_mut() {
+            for trans_info in trans_block_trans_info.iter_mut().filter(|trans_info| !trans_info.is_refunded) {
                 trans_info.is_deal = true;
             }
             <TransInfos<T>>::insert((symbol, block), trans_block_trans_info);
@@ -339,4 +575,237 @@ decl_module! {
     }
 }
 
-impl<T: Trait> Module<T> {}
\ No newline at end of file
+impl<T: Trait> Module<T> {
+    /// Provides an AccountId for the pallet, which escrows limit orders.
+    pub fn account_id() -> T::AccountId {
+        MODULE_ID.into_account()
+    }
+
+    /// Swaps rtoken of `payer` for native token paid to `receiver` for `who`, queued in TransInfos
+    /// for payers to report. Returns the trans block.
+    fn do_swap_rtoken_for_native_token(payer: &T::AccountId, who: &T::AccountId, receiver: Vec<u8>, symbol: RSymbol, rtoken_amount: u128, min_out_amount: u128, grade: u8, fee_amount: Balance) -> Result<u64, DispatchError> {
+        let now_block = system::Module::<T>::block_number().saturated_into::<u64>();
+        let fund_addr = Self::fund_address().ok_or(Error::<T>::NoFundAddress)?;
+        let rtoken_rate = RTokenRate::Rate::get(symbol).ok_or(Error::<T>::RTokenRateFailed)?;
+        let swap_rate = Self::current_swap_rate(symbol, grade)?;
+        let trans_block = now_block.checked_add(swap_rate.lock_number).ok_or(Error::<T>::OverFlow)?;
+        let out_reserve = Self::native_token_reserves(symbol);
+
+        ensure!(Self::swap_total_switch(), Error::<T>::SwapTotalClosed);
+        ensure!(Self::swap_rtoken_switch(symbol), Error::<T>::SwapRtokenClosed);
+        ensure!(rtoken_amount > u128::MIN, Error::<T>::ParamsErr);
+        ensure!(min_out_amount > u128::MIN, Error::<T>::ParamsErr);
+        ensure!(rtoken_rate > 0, Error::<T>::RTokenRateFailed);
+        ensure!(swap_rate.rate > 0, Error::<T>::SwapRateFailed);
+        ensure!(T::RCurrency::free_balance(payer, symbol) >= rtoken_amount, Error::<T>::RTokenAmountNotEnough);
+
+        // check receiver
+        match verify_recipient(symbol, &receiver) {
+            false => Err(Error::<T>::ReceiverInvalid)?,
+            _ => (),
+        }
+
+        // check limit per block
+        let mut trans_block_trans_info = Self::trans_infos((symbol, trans_block)).unwrap_or(vec![]);
+        ensure!(trans_block_trans_info.len() < Self::swap_limit_per_block() as usize, Error::<T>::OverSwapLimitPerBlock);
+
+        // check min out amount and reserve amount
+        let temp_out_amount = RTokenRate::Module::<T>::rtoken_to_token(symbol, rtoken_amount);
+        let out_amount = multiply_by_rational(temp_out_amount, swap_rate.rate, RATEBASE.into()).unwrap_or(u128::MIN) as u128;
+
+        ensure!(out_amount >= min_out_amount, Error::<T>::LessThanMinOutAmount);
+        ensure!(out_amount < out_reserve, Error::<T>::NativeTokenReserveNotEnough);
+
+        //update state
+        if fee_amount > 0 {
+            let existence = if payer == who { KeepAlive } else { AllowDeath };
+            T::Currency::transfer(payer, &fund_addr, fee_amount.saturated_into(), existence)?;
+        }
+        T::RCurrency::transfer(payer, &fund_addr, symbol, rtoken_amount)?;
+        trans_block_trans_info.push(SwapTransactionInfo{account: who.clone(), receiver: receiver.clone(), value: out_amount, is_deal: false, tx_hash: vec![], rtoken_amount, is_refunded: false});
+        <TransInfos<T>>::insert((symbol, trans_block), trans_block_trans_info);
+        NativeTokenReserves::insert(symbol, out_reserve.saturating_sub(out_amount));
+        Self::deposit_event(RawEvent::SwapRTokenToNative(who.clone(), receiver, symbol, trans_block, fee_amount, rtoken_amount, out_amount, rtoken_rate, swap_rate.rate));
+        Ok(trans_block)
+    }
+
+    /// Fills a limit order at the current swap rate of its grade from its escrow, nothing is
+    /// changed if it fails
+    #[transactional]
+    fn fill_limit_order(symbol: RSymbol, order_id: u64) -> DispatchResult {
+        let order = Self::limit_orders((symbol, order_id)).ok_or(Error::<T>::LimitOrderNotExist)?;
+        let trans_block = Self::do_swap_rtoken_for_native_token(&Self::account_id(), &order.account, order.receiver, symbol, order.rtoken_amount, order.min_out_amount, order.grade, order.fee_amount)?;
+        <LimitOrders<T>>::remove((symbol, order_id));
+        LimitOrderFillErrors::remove((symbol, order_id));
+        Self::deposit_event(RawEvent::LimitOrderFilled(order.account, symbol, order_id, trans_block));
+        Ok(())
+    }
+
+    /// Refunds an expired limit order, nothing is changed if it fails
+    #[transactional]
+    fn expire_limit_order(symbol: RSymbol, order_id: u64, order: &LimitOrder<T::AccountId>) -> DispatchResult {
+        Self::refund_limit_order(symbol, order_id, order)?;
+        Self::deposit_event(RawEvent::LimitOrderExpired(order.account.clone(), symbol, order_id));
+        Ok(())
+    }
+
+    /// Returns the escrowed rtoken and swap fee of a limit order and removes it, not from the open orders
+    fn refund_limit_order(symbol: RSymbol, order_id: u64, order: &LimitOrder<T::AccountId>) -> DispatchResult {
+        T::RCurrency::transfer(&Self::account_id(), &order.account, symbol, order.rtoken_amount)?;
+        if order.fee_amount > 0 {
+            T::Currency::transfer(&Self::account_id(), &order.account, order.fee_amount.saturated_into(), AllowDeath)?;
+        }
+        <LimitOrders<T>>::remove((symbol, order_id));
+        LimitOrderFillErrors::remove((symbol, order_id));
+        Ok(())
+    }
+
+    /// Tries to fill or expire at most `limit` open orders of a symbol, returning how many were
+    /// tried. Orders tried and still open go to the back so the others get their turn.
+    pub fn match_open_orders(symbol: RSymbol, limit: u32) -> u32 {
+        let now_block = system::Module::<T>::block_number().saturated_into::<u64>();
+        let mut untried = Vec::new();
+        let mut unfilled = Vec::new();
+        let mut tried: u32 = 0;
+        for order_id in Self::open_orders(symbol) {
+            if tried >= limit {
+                untried.push(order_id);
+                continue;
+            }
+            tried += 1;
+            let order = match Self::limit_orders((symbol, order_id)) {
+                Some(order) => order,
+                None => continue,
+            };
+            if now_block > order.expire_block {
+                if Self::expire_limit_order(symbol, order_id, &order).is_err() {
+                    unfilled.push(order_id);
+                }
+                continue;
+            }
+            if let Err(err) = Self::fill_limit_order(symbol, order_id) {
+                // an order waits for the rate to reach its min out amount, other failures are reported
+                // once until they change. errors are compared encoded as their messages are not stored
+                let last_err = Self::limit_order_fill_errors((symbol, order_id));
+                if last_err.map(|last| last.encode()) != Some(err.encode()) {
+                    if err != Error::<T>::LessThanMinOutAmount.into() {
+                        Self::deposit_event(RawEvent::LimitOrderFillFailed(symbol, order_id, err));
+                    }
+                    LimitOrderFillErrors::insert((symbol, order_id), err);
+                }
+                unfilled.push(order_id);
+            }
+        }
+        untried.append(&mut unfilled);
+        Self::put_open_orders(symbol, untried);
+        tried
+    }
+
+    fn put_open_orders(symbol: RSymbol, open_orders: Vec<u64>) {
+        if open_orders.is_empty() {
+            OpenOrders::remove(symbol);
+        } else {
+            OpenOrders::insert(symbol, open_orders);
+        }
+    }
+
+    /// Swap rate of a grade, derived from its price source if it has one
+    pub fn current_swap_rate(symbol: RSymbol, grade: u8) -> Result<SwapRate, DispatchError> {
+        match Self::derived_swap_rates((symbol, grade)) {
+            Some(derived) => {
+                let market_rate = Self::market_rate(symbol, derived.source, derived.max_age).ok_or(Error::<T>::RateSourceStale)?;
+                let rate = Self::cal_derived_rate(market_rate, &derived.curve);
+                Ok(SwapRate{lock_number: derived.lock_number, rate})
+            }
+            None => Ok(Self::swap_rates((symbol, grade)).ok_or(Error::<T>::SwapRateFailed)?),
+        }
+    }
+
+    /// price of an rToken over its redemption value scaled by RATEBASE, `None` if the source is stale
+    pub fn market_rate(symbol: RSymbol, source: RateSource, max_age: u64) -> Option<u128> {
+        let now_block = system::Module::<T>::block_number().saturated_into::<u64>();
+        let fresh = |block: u64| now_block.saturating_sub(block) <= max_age;
+        match source {
+            RateSource::RTokenRate => {
+                let updated_at = RTokenRate::Module::<T>::rate_updated_at(symbol)?.saturated_into::<u64>();
+                if fresh(updated_at) {
+                    Some(RATEBASE)
+                } else {
+                    None
+                }
+            }
+            RateSource::PoolPrice(window) => {
+                // pools price rTokens in fis, the native token of rFIS only
+                if symbol != RSymbol::RFIS {
+                    return None;
+                }
+                if !fresh(T::PriceOracle::price_updated_at(symbol)?) {
+                    return None;
+                }
+                let price = T::PriceOracle::rtoken_price(symbol, window)?;
+                let redemption = RTokenRate::Module::<T>::rtoken_to_token(symbol, PRICE_BASE);
+                multiply_by_rational(price, RATEBASE, redemption).ok()
+            }
+            RateSource::OracleFeed => {
+                let feed = Self::price_feeds(symbol).filter(|feed| fresh(feed.block))?;
+                let redemption = RTokenRate::Module::<T>::rtoken_to_token(symbol, RATEBASE);
+                multiply_by_rational(feed.price, RATEBASE, redemption).ok()
+            }
+        }
+    }
+
+    // rate = market_rate * (RATEBASE + premium - discount) / RATEBASE, discount and premium taken from the curve at market_rate
+    pub fn cal_derived_rate(market_rate: u128, curve: &[RateCurvePoint]) -> u128 {
+        let (discount, premium) = Self::curve_at(curve, market_rate);
+        let factor = RATEBASE.saturating_add(premium).saturating_sub(discount);
+        multiply_by_rational(market_rate, factor, RATEBASE).unwrap_or(u128::MIN)
+    }
+
+    /// discount and premium of the curve at market_rate, linear between two points and flat beyond the ends
+    pub fn curve_at(curve: &[RateCurvePoint], market_rate: u128) -> (u128, u128) {
+        let first = match curve.first() {
+            Some(first) => first,
+            None => return (0, 0),
+        };
+        if market_rate <= first.market_rate {
+            return (first.discount, first.premium);
+        }
+        // market_rate is above the low point of every pair visited, so spans are never zero
+        for pair in curve.windows(2) {
+            let (low, high) = (&pair[0], &pair[1]);
+            if market_rate <= high.market_rate {
+                let offset = market_rate - low.market_rate;
+                let span = high.market_rate - low.market_rate;
+                return (
+                    Self::interpolate(low.discount, high.discount, offset, span),
+                    Self::interpolate(low.premium, high.premium, offset, span),
+                );
+            }
+        }
+        let last = &curve[curve.len() - 1];
+        (last.discount, last.premium)
+    }
+
+    fn interpolate(from: u128, to: u128, offset: u128, span: u128) -> u128 {
+        if to >= from {
+            from + multiply_by_rational(to - from, offset, span).unwrap_or(u128::MIN)
+        } else {
+            from - multiply_by_rational(from - to, offset, span).unwrap_or(u128::MIN)
+        }
+    }
+
+    /// Decodes swap transaction infos stored before the payout tx hash, they can't be refunded
+    /// as their rtoken amount was not recorded.
+    fn migrate_trans_infos() -> Weight {
+        let module: &[u8] = b"RDexnSwap";
+        let item: &[u8] = b"TransInfos";
+        let mut migrated: Weight = 0;
+        for (key, infos) in StorageIterator::<Vec<SwapTransactionInfoV1<T::AccountId>>>::new(module, item) {
+            let infos = infos.into_iter().map(Into::into).collect::<Vec<SwapTransactionInfo<T::AccountId>>>();
+            put_storage_value(module, item, &key, infos);
+            migrated += 1;
+        }
+
+        T::DbWeight::get().reads_writes(migrated + 1, migrated + 1)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,159 @@
+use crate as rdexn_swap;
+use crate::{Module, Trait};
+use frame_support::{
+    impl_outer_dispatch, impl_outer_event, impl_outer_origin, parameter_types, traits::Get,
+    weights::Weight,
+};
+use node_primitives::RSymbol;
+use rdex_swap::PriceOracle;
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup},
+    Perbill,
+};
+use sp_std::cell::RefCell;
+
+pub(crate) type Balance = u128;
+
+thread_local! {
+    static EXISTENTIAL_DEPOSIT: RefCell<Balance> = RefCell::new(0);
+    static POOL_PRICE: RefCell<Option<(u128, u64)>> = RefCell::new(None);
+}
+
+impl_outer_origin! {
+    pub enum Origin for Test where system = frame_system {}
+}
+
+impl_outer_event! {
+    pub enum TestEvent for Test {
+        frame_system<T>,
+        pallet_balances<T>,
+        rtoken_balances<T>,
+        rtoken_rate,
+        rdexn_payers<T>,
+        rdexn_swap<T>,
+    }
+}
+
+impl_outer_dispatch! {
+    pub enum Call for Test where origin: Origin {
+        frame_system::System,
+        self::RDexnSwap,
+    }
+}
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: Weight = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::one();
+}
+// For testing the pallet, we construct most of a mock runtime. This means
+// first constructing a configuration type (`Test`) which `impl`s each of the
+// configuration traits of pallets we want to use.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Test;
+
+impl frame_system::Trait for Test {
+    type BaseCallFilter = ();
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = TestEvent;
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type DbWeight = ();
+    type BlockExecutionWeight = ();
+    type ExtrinsicBaseWeight = ();
+    type MaximumExtrinsicWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type PalletInfo = ();
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+}
+
+/// one pool price of rFIS for any window, set by `set_pool_price`
+pub struct MockPriceOracle;
+impl PriceOracle for MockPriceOracle {
+    fn rtoken_price(symbol: RSymbol, _window: u32) -> Option<u128> {
+        POOL_PRICE.with(|v| v.borrow().filter(|_| symbol == RSymbol::RFIS).map(|(price, _)| price))
+    }
+
+    fn price_updated_at(symbol: RSymbol) -> Option<u64> {
+        POOL_PRICE.with(|v| v.borrow().filter(|_| symbol == RSymbol::RFIS).map(|(_, block)| block))
+    }
+}
+
+pub fn set_pool_price(price: u128, updated_at: u64) {
+    POOL_PRICE.with(|v| *v.borrow_mut() = Some((price, updated_at)));
+}
+
+impl Trait for Test {
+    type Event = TestEvent;
+    type RCurrency = RBalances;
+    type Currency = Balances;
+    type PriceOracle = MockPriceOracle;
+}
+
+impl pallet_balances::Trait for Test {
+    type MaxLocks = ();
+    type Balance = Balance;
+    type DustRemoval = ();
+    type Event = TestEvent;
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+}
+
+impl rtoken_balances::Trait for Test {
+    type Event = TestEvent;
+}
+
+impl rtoken_rate::Trait for Test {
+    type Event = TestEvent;
+}
+
+impl rdexn_payers::Trait for Test {
+    type Event = TestEvent;
+}
+
+pub const SWAPPER: u64 = 1;
+pub const FUND: u64 = 2;
+pub const PAYER_A: u64 = 3;
+pub const PAYER_B: u64 = 4;
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut t = frame_system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(SWAPPER, 100), (FUND, 100)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    t.into()
+}
+
+pub type RDexnSwap = Module<Test>;
+pub type System = frame_system::Module<Test>;
+pub type Balances = pallet_balances::Module<Test>;
+pub type RBalances = rtoken_balances::Module<Test>;
+
+pub struct ExistentialDeposit;
+impl Get<Balance> for ExistentialDeposit {
+    fn get() -> Balance {
+        EXISTENTIAL_DEPOSIT.with(|v| *v.borrow())
+    }
+}
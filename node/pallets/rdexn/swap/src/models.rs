@@ -12,6 +12,35 @@ pub struct SwapTransactionInfo<AccountId> {
     pub value: u128,
     /// deal state
     pub is_deal: bool,
+    /// tx hash of the payout on the other chain, set when payers report the deal
+    pub tx_hash: Vec<u8>,
+    /// rtoken amount taken for the swap, 0 for swaps made before it was recorded
+    pub rtoken_amount: u128,
+    /// refunded in rtoken after the payout timed out
+    pub is_refunded: bool,
+}
+
+/// SwapTransactionInfo before the payout tx hash and refunds
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct SwapTransactionInfoV1<AccountId> {
+    pub account: AccountId,
+    pub receiver: Vec<u8>,
+    pub value: u128,
+    pub is_deal: bool,
+}
+
+impl<AccountId> From<SwapTransactionInfoV1<AccountId>> for SwapTransactionInfo<AccountId> {
+    fn from(info: SwapTransactionInfoV1<AccountId>) -> Self {
+        SwapTransactionInfo {
+            account: info.account,
+            receiver: info.receiver,
+            value: info.value,
+            is_deal: info.is_deal,
+            tx_hash: Vec::new(),
+            rtoken_amount: 0,
+            is_refunded: false,
+        }
+    }
 }
 
 
@@ -22,3 +51,69 @@ pub struct SwapRate {
     /// swap rate ,admin can set
     pub rate: u128,
 }
+
+
+/// Source of the market rate of a derived swap rate: the price of an rToken over its redemption value
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub enum RateSource {
+    /// rtoken exchange rate, the market rate is the redemption value itself
+    RTokenRate,
+    /// time weighted price of the rdex_swap pool over a window of blocks, for rFIS only as pools are priced in fis
+    PoolPrice(u32),
+    /// price fed by price feeders
+    OracleFeed,
+}
+
+/// Point of the discount and premium curve of a derived swap rate
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct RateCurvePoint {
+    /// market rate of the point, scaled by RATEBASE
+    pub market_rate: u128,
+    /// discount on the market rate, scaled by RATEBASE
+    pub discount: u128,
+    /// premium on the market rate, scaled by RATEBASE
+    pub premium: u128,
+}
+
+/// Swap rate of a grade derived from a price source:
+/// rate = market rate * (RATEBASE + premium - discount) / RATEBASE,
+/// discount and premium are interpolated on the curve at the market rate
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct DerivedSwapRate {
+    /// lock block number
+    pub lock_number: u64,
+    /// source of the market rate
+    pub source: RateSource,
+    /// points of the curve, by increasing market rate
+    pub curve: Vec<RateCurvePoint>,
+    /// blocks after which a rtoken rate, a fed price or a pool price is stale
+    pub max_age: u64,
+}
+
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct PriceFeed {
+    /// price of one rToken in native token, scaled by RATEBASE
+    pub price: u128,
+    /// block the price was fed at
+    pub block: u64,
+}
+
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct LimitOrder<AccountId> {
+    /// account
+    pub account: AccountId,
+    /// receiver
+    pub receiver: Vec<u8>,
+    /// rtoken amount escrowed
+    pub rtoken_amount: u128,
+    /// min out amount the order fills at
+    pub min_out_amount: u128,
+    /// swap rate grade
+    pub grade: u8,
+    /// swap fee escrowed
+    pub fee_amount: u128,
+    /// block the order was placed at
+    pub place_block: u64,
+    /// last block the order can fill at, it is refunded after
+    pub expire_block: u64,
+}
@@ -0,0 +1,535 @@
+use super::mock::*;
+use super::*;
+use frame_support::{assert_err, assert_noop, assert_ok, traits::OnRuntimeUpgrade, Hashable};
+use node_primitives::RSymbol;
+
+const SYMBOL: RSymbol = RSymbol::RDOT;
+
+fn receiver() -> Vec<u8> {
+    vec![1; 32]
+}
+
+fn setup_swap() {
+    System::set_block_number(1);
+    RTokenRate::Module::<Test>::set_rate(SYMBOL, 0, 0);
+    assert_ok!(RDexnSwap::toggle_swap_total_switch(Origin::root()));
+    assert_ok!(RDexnSwap::set_fund_address(Origin::root(), FUND));
+    assert_ok!(RDexnSwap::set_swap_fee(Origin::root(), SYMBOL, 1));
+    assert_ok!(RDexnSwap::set_swap_rate(Origin::root(), SYMBOL, 0, 0, RATEBASE));
+    assert_ok!(RDexnSwap::set_native_token_reserve(Origin::root(), SYMBOL, 100));
+    assert_ok!(RDexnSwap::set_payout_timeout(Origin::root(), SYMBOL, 10));
+    assert_ok!(RBalances::mint(&SWAPPER, SYMBOL, 100));
+    assert_ok!(RDexnPayers::Module::<Test>::add_payer(Origin::root(), SYMBOL, PAYER_A));
+    assert_ok!(RDexnPayers::Module::<Test>::add_payer(Origin::root(), SYMBOL, PAYER_B));
+    assert_ok!(RDexnPayers::Module::<Test>::set_threshold(Origin::root(), SYMBOL, 2));
+}
+
+fn swap(rtoken_amount: u128) {
+    assert_ok!(RDexnSwap::swap_rtoken_for_native_token(Origin::signed(SWAPPER), receiver(), SYMBOL, rtoken_amount, rtoken_amount, 0));
+}
+
+#[test]
+fn swap_should_take_reserve() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        swap(10);
+
+        assert_eq!(RBalances::free_balance(&SWAPPER, SYMBOL), 90);
+        assert_eq!(RBalances::free_balance(&FUND, SYMBOL), 10);
+        assert_eq!(Balances::free_balance(&FUND), 101);
+        assert_eq!(RDexnSwap::native_token_reserves(SYMBOL), 90);
+        assert_eq!(
+            RDexnSwap::trans_infos((SYMBOL, 1)),
+            Some(vec![SwapTransactionInfo { account: SWAPPER, receiver: receiver(), value: 10, is_deal: false, tx_hash: vec![], rtoken_amount: 10, is_refunded: false }])
+        );
+
+        // the out amount must stay below the reserve
+        assert_noop!(
+            RDexnSwap::swap_rtoken_for_native_token(Origin::signed(SWAPPER), receiver(), SYMBOL, 90, 90, 0),
+            Error::<Test>::NativeTokenReserveNotEnough
+        );
+    });
+}
+
+#[test]
+fn timed_out_swap_should_be_refunded() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        swap(10);
+
+        System::set_block_number(11);
+        assert_noop!(RDexnSwap::refund_swap(Origin::signed(SWAPPER), SYMBOL, 1, 0), Error::<Test>::PayoutNotTimedOut);
+
+        System::set_block_number(12);
+        assert_noop!(RDexnSwap::refund_swap(Origin::signed(PAYER_A), SYMBOL, 1, 0), Error::<Test>::NotSwapOwner);
+        assert_noop!(RDexnSwap::refund_swap(Origin::signed(SWAPPER), SYMBOL, 1, 1), Error::<Test>::GetTransInfoFailed);
+        assert_ok!(RDexnSwap::refund_swap(Origin::signed(SWAPPER), SYMBOL, 1, 0));
+
+        assert_eq!(RBalances::free_balance(&SWAPPER, SYMBOL), 100);
+        assert_eq!(RBalances::free_balance(&FUND, SYMBOL), 0);
+        assert_eq!(RDexnSwap::native_token_reserves(SYMBOL), 100);
+        assert!(RDexnSwap::trans_infos((SYMBOL, 1)).unwrap()[0].is_refunded);
+        assert_noop!(RDexnSwap::refund_swap(Origin::signed(SWAPPER), SYMBOL, 1, 0), Error::<Test>::AlreadyRefunded);
+
+        // payers can no longer report the refunded swap as paid
+        assert_noop!(
+            RDexnSwap::report_transfer_result_with_index(Origin::signed(PAYER_A), SYMBOL, 1, 0, b"hash".to_vec()),
+            Error::<Test>::AlreadyRefunded
+        );
+        assert_ok!(RDexnSwap::report_transfer_result_with_block(Origin::signed(PAYER_A), SYMBOL, 1, b"hash".to_vec()));
+        assert_ok!(RDexnSwap::report_transfer_result_with_block(Origin::signed(PAYER_B), SYMBOL, 1, b"hash".to_vec()));
+        let trans_info = RDexnSwap::trans_infos((SYMBOL, 1)).unwrap()[0].clone();
+        assert!(trans_info.is_refunded);
+        assert!(!trans_info.is_deal);
+    });
+}
+
+#[test]
+fn refund_should_be_blocked_after_payer_vote() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        swap(10);
+        swap(20);
+
+        // a single vote is below the threshold, but the payout may be on its way
+        assert_ok!(RDexnSwap::report_transfer_result_with_index(Origin::signed(PAYER_A), SYMBOL, 1, 1, b"hash".to_vec()));
+        assert!(!RDexnSwap::trans_infos((SYMBOL, 1)).unwrap()[1].is_deal);
+
+        System::set_block_number(12);
+        assert_noop!(RDexnSwap::refund_swap(Origin::signed(SWAPPER), SYMBOL, 1, 1), Error::<Test>::PayoutReported);
+        assert_ok!(RDexnSwap::refund_swap(Origin::signed(SWAPPER), SYMBOL, 1, 0));
+        assert_eq!(RDexnSwap::native_token_reserves(SYMBOL), 80);
+
+        // a vote on the whole block blocks all of its swaps
+        swap(10);
+        assert_ok!(RDexnSwap::report_transfer_result_with_block(Origin::signed(PAYER_B), SYMBOL, 12, b"hash".to_vec()));
+        System::set_block_number(23);
+        assert_noop!(RDexnSwap::refund_swap(Origin::signed(SWAPPER), SYMBOL, 12, 0), Error::<Test>::PayoutReported);
+    });
+}
+
+#[test]
+fn payers_should_report_the_same_tx_hash() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        swap(10);
+
+        assert_noop!(
+            RDexnSwap::report_transfer_result_with_block(Origin::signed(SWAPPER), SYMBOL, 1, b"hash_a".to_vec()),
+            rdexn_payers::Error::<Test>::MustBePayer
+        );
+        assert_ok!(RDexnSwap::report_transfer_result_with_block(Origin::signed(PAYER_A), SYMBOL, 1, b"hash_a".to_vec()));
+        assert_noop!(
+            RDexnSwap::report_transfer_result_with_block(Origin::signed(PAYER_A), SYMBOL, 1, b"hash_a".to_vec()),
+            Error::<Test>::VoterRepeat
+        );
+        assert_noop!(
+            RDexnSwap::report_transfer_result_with_block(Origin::signed(PAYER_B), SYMBOL, 1, b"hash_b".to_vec()),
+            Error::<Test>::TxHashMismatch
+        );
+        assert_ok!(RDexnSwap::report_transfer_result_with_block(Origin::signed(PAYER_B), SYMBOL, 1, b"hash_a".to_vec()));
+
+        let trans_info = RDexnSwap::trans_infos((SYMBOL, 1)).unwrap()[0].clone();
+        assert!(trans_info.is_deal);
+        assert_eq!(trans_info.tx_hash, b"hash_a".to_vec());
+        assert_eq!(RDexnSwap::latest_deal_block(SYMBOL), 1);
+
+        System::set_block_number(2);
+        swap(10);
+        assert_ok!(RDexnSwap::report_transfer_result_with_index(Origin::signed(PAYER_A), SYMBOL, 2, 0, b"hash_a".to_vec()));
+        assert_noop!(
+            RDexnSwap::report_transfer_result_with_index(Origin::signed(PAYER_B), SYMBOL, 2, 0, b"hash_b".to_vec()),
+            Error::<Test>::TxHashMismatch
+        );
+        assert_ok!(RDexnSwap::report_transfer_result_with_index(Origin::signed(PAYER_B), SYMBOL, 2, 0, b"hash_a".to_vec()));
+        assert_eq!(RDexnSwap::trans_infos((SYMBOL, 2)).unwrap()[0].tx_hash, b"hash_a".to_vec());
+        assert_eq!(RDexnSwap::latest_deal_block(SYMBOL), 2);
+    });
+}
+
+#[test]
+fn v1_trans_infos_should_be_migrated() {
+    new_test_ext().execute_with(|| {
+        let legacy = SwapTransactionInfoV1 { account: SWAPPER, receiver: receiver(), value: 10, is_deal: false };
+        let dealt = SwapTransactionInfoV1 { account: SWAPPER, receiver: receiver(), value: 20, is_deal: true };
+        put_storage_value(b"RDexnSwap", b"TransInfos", &(SYMBOL, 5u64).blake2_128_concat(), vec![legacy, dealt]);
+        assert_eq!(RDexnSwap::storage_version(), Releases::V1_0_0);
+
+        RDexnSwap::on_runtime_upgrade();
+        assert_eq!(RDexnSwap::storage_version(), Releases::V2_0_0);
+        assert_eq!(
+            RDexnSwap::trans_infos((SYMBOL, 5)),
+            Some(vec![
+                SwapTransactionInfo { account: SWAPPER, receiver: receiver(), value: 10, is_deal: false, tx_hash: vec![], rtoken_amount: 0, is_refunded: false },
+                SwapTransactionInfo { account: SWAPPER, receiver: receiver(), value: 20, is_deal: true, tx_hash: vec![], rtoken_amount: 0, is_refunded: false },
+            ])
+        );
+
+        // their rtoken amount is unknown
+        System::set_block_number(20);
+        assert_ok!(RDexnSwap::set_fund_address(Origin::root(), FUND));
+        assert_ok!(RDexnSwap::set_payout_timeout(Origin::root(), SYMBOL, 10));
+        assert_noop!(RDexnSwap::refund_swap(Origin::signed(SWAPPER), SYMBOL, 5, 0), Error::<Test>::NotRefundable);
+
+        // migrated once
+        assert_eq!(RDexnSwap::on_runtime_upgrade(), 0);
+    });
+}
+
+#[test]
+fn genesis_built_chain_should_not_migrate_trans_infos() {
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> { balances: vec![(SWAPPER, 100), (FUND, 100)] }
+        .assimilate_storage(&mut t)
+        .unwrap();
+    GenesisConfig::default().assimilate_storage::<Test>(&mut t).unwrap();
+    sp_io::TestExternalities::from(t).execute_with(|| {
+        assert_eq!(RDexnSwap::storage_version(), Releases::V2_0_0);
+        setup_swap();
+        swap(10);
+        let infos = RDexnSwap::trans_infos((SYMBOL, 1));
+        assert!(infos.is_some());
+
+        assert_eq!(RDexnSwap::on_runtime_upgrade(), 0);
+        assert_eq!(RDexnSwap::trans_infos((SYMBOL, 1)), infos);
+    });
+}
+
+fn place_order(rtoken_amount: u128, min_out_amount: u128, expire_block: u64) {
+    assert_ok!(RDexnSwap::place_limit_order(Origin::signed(SWAPPER), receiver(), SYMBOL, rtoken_amount, min_out_amount, 0, expire_block));
+}
+
+#[test]
+fn limit_order_should_be_placed_and_cancelled() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        assert_noop!(
+            RDexnSwap::place_limit_order(Origin::signed(SWAPPER), receiver(), SYMBOL, 10, 10, 0, 1),
+            Error::<Test>::ParamsErr
+        );
+        assert_noop!(
+            RDexnSwap::place_limit_order(Origin::signed(SWAPPER), receiver(), SYMBOL, 10, 10, 1, 10),
+            Error::<Test>::SwapRateFailed
+        );
+        assert_noop!(
+            RDexnSwap::place_limit_order(Origin::signed(SWAPPER), receiver(), SYMBOL, 101, 10, 0, 10),
+            Error::<Test>::RTokenAmountNotEnough
+        );
+        place_order(10, 10, 10);
+
+        assert_eq!(
+            RDexnSwap::limit_orders((SYMBOL, 0)),
+            Some(LimitOrder { account: SWAPPER, receiver: receiver(), rtoken_amount: 10, min_out_amount: 10, grade: 0, fee_amount: 1, place_block: 1, expire_block: 10 })
+        );
+        assert_eq!(RDexnSwap::open_orders(SYMBOL), vec![0]);
+        assert_eq!(RDexnSwap::next_order_id(SYMBOL), 1);
+        assert_eq!(RBalances::free_balance(&SWAPPER, SYMBOL), 90);
+        assert_eq!(RBalances::free_balance(&RDexnSwap::account_id(), SYMBOL), 10);
+        assert_eq!(Balances::free_balance(&SWAPPER), 99);
+        assert_eq!(Balances::free_balance(&RDexnSwap::account_id()), 1);
+
+        assert_noop!(RDexnSwap::cancel_limit_order(Origin::signed(PAYER_A), SYMBOL, 0), Error::<Test>::NotOrderOwner);
+        assert_noop!(RDexnSwap::cancel_limit_order(Origin::signed(SWAPPER), SYMBOL, 1), Error::<Test>::LimitOrderNotExist);
+        assert_ok!(RDexnSwap::cancel_limit_order(Origin::signed(SWAPPER), SYMBOL, 0));
+
+        assert_eq!(RDexnSwap::limit_orders((SYMBOL, 0)), None);
+        assert!(RDexnSwap::open_orders(SYMBOL).is_empty());
+        assert_eq!(RBalances::free_balance(&SWAPPER, SYMBOL), 100);
+        assert_eq!(RBalances::free_balance(&RDexnSwap::account_id(), SYMBOL), 0);
+        assert_eq!(Balances::free_balance(&SWAPPER), 100);
+        assert_eq!(Balances::free_balance(&RDexnSwap::account_id()), 0);
+    });
+}
+
+#[test]
+fn limit_order_should_fill_once_it_crosses() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        // order 0 needs a better rate, order 1 crosses
+        place_order(10, 20, 10);
+        place_order(10, 10, 10);
+
+        // orders tried and not filled go to the back
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 1), 1);
+        assert_eq!(RDexnSwap::open_orders(SYMBOL), vec![1, 0]);
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 1), 1);
+        assert_eq!(RDexnSwap::open_orders(SYMBOL), vec![0]);
+        assert_eq!(RDexnSwap::limit_orders((SYMBOL, 1)), None);
+        assert_eq!(
+            RDexnSwap::trans_infos((SYMBOL, 1)),
+            Some(vec![SwapTransactionInfo { account: SWAPPER, receiver: receiver(), value: 10, is_deal: false, tx_hash: vec![], rtoken_amount: 10, is_refunded: false }])
+        );
+        assert_eq!(RBalances::free_balance(&FUND, SYMBOL), 10);
+        assert_eq!(Balances::free_balance(&FUND), 101);
+        assert_eq!(RDexnSwap::native_token_reserves(SYMBOL), 90);
+
+        // the rate doubles
+        assert_ok!(RDexnSwap::set_swap_rate(Origin::root(), SYMBOL, 0, 0, 2 * RATEBASE));
+        System::set_block_number(2);
+        assert_ok!(RDexnSwap::match_orders(Origin::signed(PAYER_A), SYMBOL, 20));
+        assert!(RDexnSwap::open_orders(SYMBOL).is_empty());
+        assert_eq!(RDexnSwap::trans_infos((SYMBOL, 2)).unwrap()[0].value, 20);
+        assert_eq!(RBalances::free_balance(&RDexnSwap::account_id(), SYMBOL), 0);
+        assert_eq!(Balances::free_balance(&RDexnSwap::account_id()), 0);
+        assert_eq!(RDexnSwap::native_token_reserves(SYMBOL), 70);
+    });
+}
+
+#[test]
+fn limit_order_should_wait_for_reserve() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        assert_ok!(RDexnSwap::set_native_token_reserve(Origin::root(), SYMBOL, 10));
+        place_order(10, 10, 10);
+
+        // the out amount must stay below the reserve, the escrow is left as it was
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        assert_eq!(RDexnSwap::open_orders(SYMBOL), vec![0]);
+        assert_eq!(RDexnSwap::trans_infos((SYMBOL, 1)), None);
+        assert_eq!(RBalances::free_balance(&RDexnSwap::account_id(), SYMBOL), 10);
+        assert_eq!(Balances::free_balance(&RDexnSwap::account_id()), 1);
+        assert_eq!(Balances::free_balance(&FUND), 100);
+
+        assert_ok!(RDexnSwap::set_native_token_reserve(Origin::root(), SYMBOL, 11));
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        assert!(RDexnSwap::open_orders(SYMBOL).is_empty());
+        assert_eq!(RDexnSwap::native_token_reserves(SYMBOL), 1);
+        assert_eq!(Balances::free_balance(&FUND), 101);
+    });
+}
+
+#[test]
+fn expired_limit_order_should_be_refunded() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        place_order(10, 20, 5);
+
+        System::set_block_number(5);
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        assert_eq!(RDexnSwap::open_orders(SYMBOL), vec![0]);
+
+        // past the expire block it is refunded even if it crosses
+        assert_ok!(RDexnSwap::set_swap_rate(Origin::root(), SYMBOL, 0, 0, 2 * RATEBASE));
+        System::set_block_number(6);
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        assert!(RDexnSwap::open_orders(SYMBOL).is_empty());
+        assert_eq!(RDexnSwap::limit_orders((SYMBOL, 0)), None);
+        assert_eq!(RDexnSwap::trans_infos((SYMBOL, 6)), None);
+        assert_eq!(RBalances::free_balance(&SWAPPER, SYMBOL), 100);
+        assert_eq!(Balances::free_balance(&SWAPPER), 100);
+        assert_eq!(RDexnSwap::native_token_reserves(SYMBOL), 100);
+    });
+}
+
+fn flat_curve(discount: u128, premium: u128) -> Vec<RateCurvePoint> {
+    vec![RateCurvePoint { market_rate: RATEBASE, discount, premium }]
+}
+
+/// discount of 20% at a market rate of 0.8 easing to 5% at par, turning into a premium of 10% at 1.2
+fn grade_curve() -> Vec<RateCurvePoint> {
+    vec![
+        RateCurvePoint { market_rate: 800_000_000_000, discount: 200_000_000_000, premium: 0 },
+        RateCurvePoint { market_rate: RATEBASE, discount: 50_000_000_000, premium: 0 },
+        RateCurvePoint { market_rate: 1_200_000_000_000, discount: 0, premium: 100_000_000_000 },
+    ]
+}
+
+#[test]
+fn derived_swap_rate_should_check_its_params() {
+    new_test_ext().execute_with(|| {
+        let set = |symbol: RSymbol, source: RateSource, curve: Vec<RateCurvePoint>| {
+            RDexnSwap::set_derived_swap_rate(Origin::root(), symbol, 0, 0, source, curve, 10)
+        };
+        assert_noop!(set(SYMBOL, RateSource::RTokenRate, vec![]), Error::<Test>::ParamsErr);
+        assert_noop!(set(SYMBOL, RateSource::RTokenRate, flat_curve(RATEBASE + 1, 0)), Error::<Test>::ParamsErr);
+        let mut unsorted = grade_curve();
+        unsorted.swap(0, 1);
+        assert_noop!(set(SYMBOL, RateSource::RTokenRate, unsorted), Error::<Test>::ParamsErr);
+        let too_long = (0..=MAX_CURVE_POINTS as u128)
+            .map(|i| RateCurvePoint { market_rate: i, discount: 0, premium: 0 })
+            .collect::<Vec<RateCurvePoint>>();
+        assert_noop!(set(SYMBOL, RateSource::RTokenRate, too_long), Error::<Test>::ParamsErr);
+        // pools price rTokens in fis, only the native token of rFIS
+        assert_noop!(set(SYMBOL, RateSource::PoolPrice(10), flat_curve(0, 0)), Error::<Test>::RateSourceNotSupported);
+        assert_ok!(set(RSymbol::RFIS, RateSource::PoolPrice(10), flat_curve(0, 0)));
+
+        assert_ok!(set(SYMBOL, RateSource::OracleFeed, grade_curve()));
+        assert_eq!(
+            RDexnSwap::derived_swap_rates((SYMBOL, 0)),
+            Some(DerivedSwapRate { lock_number: 0, source: RateSource::OracleFeed, curve: grade_curve(), max_age: 10 })
+        );
+        assert_noop!(
+            RDexnSwap::set_derived_swap_rate(Origin::signed(SWAPPER), SYMBOL, 0, 0, RateSource::RTokenRate, flat_curve(0, 0), 10),
+            DispatchError::BadOrigin
+        );
+    });
+}
+
+#[test]
+fn derived_rate_should_follow_the_curve() {
+    // flat beyond the ends
+    assert_eq!(RDexnSwap::curve_at(&grade_curve(), 500_000_000_000), (200_000_000_000, 0));
+    assert_eq!(RDexnSwap::curve_at(&grade_curve(), 800_000_000_000), (200_000_000_000, 0));
+    assert_eq!(RDexnSwap::curve_at(&grade_curve(), 1_500_000_000_000), (0, 100_000_000_000));
+    // linear between two points
+    assert_eq!(RDexnSwap::curve_at(&grade_curve(), 900_000_000_000), (125_000_000_000, 0));
+    assert_eq!(RDexnSwap::curve_at(&grade_curve(), RATEBASE), (50_000_000_000, 0));
+    assert_eq!(RDexnSwap::curve_at(&grade_curve(), 1_100_000_000_000), (25_000_000_000, 50_000_000_000));
+    assert_eq!(RDexnSwap::curve_at(&[], RATEBASE), (0, 0));
+
+    // rate = market rate * (1 + premium - discount)
+    assert_eq!(RDexnSwap::cal_derived_rate(900_000_000_000, &grade_curve()), 787_500_000_000);
+    assert_eq!(RDexnSwap::cal_derived_rate(1_100_000_000_000, &grade_curve()), 1_127_500_000_000);
+    assert_eq!(RDexnSwap::cal_derived_rate(1_500_000_000_000, &grade_curve()), 1_650_000_000_000);
+    assert_eq!(RDexnSwap::cal_derived_rate(RATEBASE, &flat_curve(100_000_000_000, 0)), 900_000_000_000);
+    assert_eq!(RDexnSwap::cal_derived_rate(RATEBASE, &flat_curve(0, 100_000_000_000)), 1_100_000_000_000);
+    // a full discount takes the whole rate
+    assert_eq!(RDexnSwap::cal_derived_rate(RATEBASE, &flat_curve(RATEBASE, 0)), 0);
+}
+
+#[test]
+fn rtoken_rate_source_should_fail_closed_when_stale() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        assert_ok!(RDexnSwap::set_derived_swap_rate(Origin::root(), SYMBOL, 0, 0, RateSource::RTokenRate, flat_curve(100_000_000_000, 0), 10));
+        // the derived rate is used instead of the swap rate of the grade
+        assert_eq!(RDexnSwap::current_swap_rate(SYMBOL, 0), Ok(SwapRate { lock_number: 0, rate: 900_000_000_000 }));
+        assert_ok!(RDexnSwap::swap_rtoken_for_native_token(Origin::signed(SWAPPER), receiver(), SYMBOL, 10, 9, 0));
+        assert_eq!(RDexnSwap::trans_infos((SYMBOL, 1)).unwrap()[0].value, 9);
+
+        // the rtoken rate was set at block 1
+        System::set_block_number(11);
+        assert_eq!(RDexnSwap::market_rate(SYMBOL, RateSource::RTokenRate, 10), Some(RATEBASE));
+        System::set_block_number(12);
+        assert_eq!(RDexnSwap::market_rate(SYMBOL, RateSource::RTokenRate, 10), None);
+        assert_err!(RDexnSwap::current_swap_rate(SYMBOL, 0), Error::<Test>::RateSourceStale);
+        assert_noop!(
+            RDexnSwap::swap_rtoken_for_native_token(Origin::signed(SWAPPER), receiver(), SYMBOL, 10, 9, 0),
+            Error::<Test>::RateSourceStale
+        );
+
+        RTokenRate::Module::<Test>::set_rate(SYMBOL, 0, 0);
+        assert_eq!(RDexnSwap::market_rate(SYMBOL, RateSource::RTokenRate, 10), Some(RATEBASE));
+        assert_ok!(RDexnSwap::swap_rtoken_for_native_token(Origin::signed(SWAPPER), receiver(), SYMBOL, 10, 9, 0));
+
+        // back to the swap rate of the grade
+        assert_ok!(RDexnSwap::remove_derived_swap_rate(Origin::root(), SYMBOL, 0));
+        assert_eq!(RDexnSwap::current_swap_rate(SYMBOL, 0), Ok(SwapRate { lock_number: 0, rate: RATEBASE }));
+    });
+}
+
+#[test]
+fn oracle_feed_source_should_fail_closed_when_stale() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        // one rDOT redeems two DOT
+        RTokenRate::Module::<Test>::set_rate(SYMBOL, 2, 1);
+        assert_ok!(RDexnSwap::set_derived_swap_rate(Origin::root(), SYMBOL, 0, 0, RateSource::OracleFeed, grade_curve(), 5));
+        assert_eq!(RDexnSwap::market_rate(SYMBOL, RateSource::OracleFeed, 5), None);
+        assert_err!(RDexnSwap::current_swap_rate(SYMBOL, 0), Error::<Test>::RateSourceStale);
+
+        assert_noop!(RDexnSwap::feed_price(Origin::signed(PAYER_A), SYMBOL, 1_800_000_000_000), Error::<Test>::NotPriceFeeder);
+        assert_ok!(RDexnSwap::set_price_feeder(Origin::root(), PAYER_A, true));
+        assert_noop!(RDexnSwap::feed_price(Origin::signed(PAYER_A), SYMBOL, 0), Error::<Test>::PriceZero);
+        // traded at 1.8 DOT, 10% under its redemption value
+        assert_ok!(RDexnSwap::feed_price(Origin::signed(PAYER_A), SYMBOL, 1_800_000_000_000));
+        assert_eq!(RDexnSwap::price_feeds(SYMBOL), Some(PriceFeed { price: 1_800_000_000_000, block: 1 }));
+        assert_eq!(RDexnSwap::market_rate(SYMBOL, RateSource::OracleFeed, 5), Some(900_000_000_000));
+        assert_eq!(RDexnSwap::current_swap_rate(SYMBOL, 0), Ok(SwapRate { lock_number: 0, rate: 787_500_000_000 }));
+
+        System::set_block_number(6);
+        assert_eq!(RDexnSwap::market_rate(SYMBOL, RateSource::OracleFeed, 5), Some(900_000_000_000));
+        System::set_block_number(7);
+        assert_eq!(RDexnSwap::market_rate(SYMBOL, RateSource::OracleFeed, 5), None);
+        assert_err!(RDexnSwap::current_swap_rate(SYMBOL, 0), Error::<Test>::RateSourceStale);
+
+        assert_ok!(RDexnSwap::set_price_feeder(Origin::root(), PAYER_A, false));
+        assert_noop!(RDexnSwap::feed_price(Origin::signed(PAYER_A), SYMBOL, 1_800_000_000_000), Error::<Test>::NotPriceFeeder);
+    });
+}
+
+#[test]
+fn pool_price_source_should_fail_closed_when_stale() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_eq!(RDexnSwap::market_rate(RSymbol::RFIS, RateSource::PoolPrice(10), 5), None);
+
+        // rFIS traded at 0.9 FIS, the pool price last moved at block 1
+        set_pool_price(PRICE_BASE * 9 / 10, 1);
+        assert_eq!(RDexnSwap::market_rate(RSymbol::RFIS, RateSource::PoolPrice(10), 5), Some(900_000_000_000));
+        assert_ok!(RDexnSwap::set_derived_swap_rate(Origin::root(), RSymbol::RFIS, 0, 0, RateSource::PoolPrice(10), flat_curve(0, 100_000_000_000), 5));
+        assert_eq!(RDexnSwap::current_swap_rate(RSymbol::RFIS, 0), Ok(SwapRate { lock_number: 0, rate: 990_000_000_000 }));
+        // other rTokens are not priced in their native token
+        assert_eq!(RDexnSwap::market_rate(SYMBOL, RateSource::PoolPrice(10), 5), None);
+
+        System::set_block_number(6);
+        assert_eq!(RDexnSwap::market_rate(RSymbol::RFIS, RateSource::PoolPrice(10), 5), Some(900_000_000_000));
+        System::set_block_number(7);
+        assert_eq!(RDexnSwap::market_rate(RSymbol::RFIS, RateSource::PoolPrice(10), 5), None);
+        assert_err!(RDexnSwap::current_swap_rate(RSymbol::RFIS, 0), Error::<Test>::RateSourceStale);
+
+        set_pool_price(PRICE_BASE, 7);
+        assert_eq!(RDexnSwap::current_swap_rate(RSymbol::RFIS, 0), Ok(SwapRate { lock_number: 0, rate: 1_100_000_000_000 }));
+    });
+}
+
+fn fill_failed_events() -> Vec<DispatchError> {
+    System::events()
+        .into_iter()
+        .filter_map(|record| match record.event {
+            TestEvent::rdexn_swap(RawEvent::LimitOrderFillFailed(_, _, err)) => Some(err),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn limit_order_fill_failure_should_be_reported_once_it_changes() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        assert_ok!(RDexnSwap::set_native_token_reserve(Origin::root(), SYMBOL, 10));
+        place_order(10, 10, 10);
+
+        // the order is tried in every block, the failure is reported once
+        for block in 1..4 {
+            System::set_block_number(block);
+            assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        }
+        let reserve_err: DispatchError = Error::<Test>::NativeTokenReserveNotEnough.into();
+        assert_eq!(fill_failed_events(), vec![reserve_err]);
+        assert_eq!(RDexnSwap::limit_order_fill_errors((SYMBOL, 0)).map(|err| err.encode()), Some(reserve_err.encode()));
+
+        // the rtoken rate goes stale, another reason
+        assert_ok!(RDexnSwap::set_derived_swap_rate(Origin::root(), SYMBOL, 0, 0, RateSource::RTokenRate, flat_curve(0, 0), 1));
+        for block in 4..6 {
+            System::set_block_number(block);
+            assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        }
+        assert_eq!(fill_failed_events(), vec![reserve_err, Error::<Test>::RateSourceStale.into()]);
+
+        // the first reason again
+        assert_ok!(RDexnSwap::remove_derived_swap_rate(Origin::root(), SYMBOL, 0));
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        assert_eq!(fill_failed_events().len(), 3);
+
+        assert_ok!(RDexnSwap::set_native_token_reserve(Origin::root(), SYMBOL, 11));
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        assert!(RDexnSwap::open_orders(SYMBOL).is_empty());
+        assert_eq!(RDexnSwap::limit_order_fill_errors((SYMBOL, 0)), None);
+        assert_eq!(fill_failed_events().len(), 3);
+    });
+}
+
+#[test]
+fn limit_order_waiting_for_its_rate_should_not_be_reported() {
+    new_test_ext().execute_with(|| {
+        setup_swap();
+        place_order(10, 20, 10);
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        assert_eq!(RDexnSwap::match_open_orders(SYMBOL, 20), 1);
+        assert!(fill_failed_events().is_empty());
+
+        assert_ok!(RDexnSwap::cancel_limit_order(Origin::signed(SWAPPER), SYMBOL, 0));
+        assert_eq!(RDexnSwap::limit_order_fill_errors((SYMBOL, 0)), None);
+    });
+}
@@ -4,9 +4,12 @@ use sp_std::{convert::Infallible};
 use codec::{Encode, Decode};
 use frame_support::{
 	decl_event, decl_storage, decl_module, decl_error, ensure,
+	storage::migration::{put_storage_value, StorageIterator},
+	traits::{BalanceStatus, Get, LockIdentifier},
+	weights::Weight,
 };
 use sp_runtime::{
-	RuntimeDebug, DispatchResult,
+	RuntimeDebug, DispatchResult, DispatchError,
 	traits::{
 		Zero, StaticLookup,
 	},
@@ -16,6 +19,11 @@ use node_primitives::RSymbol;
 
 pub mod traits;
 
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
 pub trait Trait: system::Trait {
     /// The overarching event type.
     type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
@@ -31,6 +39,18 @@ decl_event!(
 		Minted(AccountId, RSymbol, u128),
 		/// Some balance was burned
 		Burned(AccountId, RSymbol, u128),
+		/// Some balance was reserved (moved from free to reserved). \[who, symbol, value\]
+		Reserved(AccountId, RSymbol, u128),
+		/// Some balance was unreserved (moved from reserved to free). \[who, symbol, value\]
+		Unreserved(AccountId, RSymbol, u128),
+		/// Some balance was moved from the reserve of the first account to the second account.
+		/// Final argument indicates the destination balance type.
+		/// \[from, to, symbol, value, destination_status\]
+		ReserveRepatriated(AccountId, AccountId, RSymbol, u128, BalanceStatus),
+		/// A lock was set or extended on the free balance. \[who, symbol, id, amount\]
+		LockSet(AccountId, RSymbol, LockIdentifier, u128),
+		/// A lock was removed from the free balance. \[who, symbol, id\]
+		LockRemoved(AccountId, RSymbol, LockIdentifier),
 	}
 );
 
@@ -42,6 +62,8 @@ decl_error! {
 		Overflow,
 		/// Balance too low to send value
 		InsufficientBalance,
+		/// minting of the rtoken is disabled
+		MintDisabled,
 	}
 }
 
@@ -50,6 +72,54 @@ decl_error! {
 pub struct AccountRData {
 	/// Non-reserved part of the balance.
 	pub free: u128,
+	/// Balance which is reserved and may not be used at all.
+	///
+	/// This balance is a 'reserve' balance that other subsystems use in order to set aside tokens
+	/// that are still 'owned' by the account holder, but which are suspendable.
+	pub reserved: u128,
+	/// The amount that `free` may not drop below when withdrawing, the largest of the locks.
+	pub frozen: u128,
+}
+
+/// Balance information of an account before reserves and locks.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct AccountRDataV1 {
+	pub free: u128,
+}
+
+impl From<AccountRDataV1> for AccountRData {
+	fn from(data: AccountRDataV1) -> Self {
+		AccountRData {
+			free: data.free,
+			reserved: 0,
+			frozen: 0,
+		}
+	}
+}
+
+/// A single lock on the free balance of an rtoken. There can be many of these on an account and
+/// they "overlap", so the same balance is frozen by multiple locks.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct RBalanceLock {
+	/// An identifier for this lock. Only one lock may be in existence for each identifier.
+	pub id: LockIdentifier,
+	/// The amount which the free balance may not drop below when this lock is in effect.
+	pub amount: u128,
+}
+
+/// Storage version of the pallet
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Releases {
+	/// account data only has a free balance
+	V1_0_0,
+	/// account data has reserved and frozen balances
+	V2_0_0,
+}
+
+impl Default for Releases {
+	fn default() -> Self {
+		Releases::V1_0_0
+	}
 }
 
 decl_storage! {
@@ -57,9 +127,19 @@ decl_storage! {
 		/// The total units issued in the system.
         pub TotalIssuance get(fn total_issuance): map hasher(blake2_128_concat) RSymbol => u128;
 
+		/// rtokens that can no longer be minted, such as those migrated to another chain
+		pub MintDisabled get(fn mint_disabled): map hasher(blake2_128_concat) RSymbol => bool;
+
 		/// NOTE: This is only used in the case that this module is used to store balances.
 		pub Account get(fn account):
 			double_map hasher(blake2_128_concat) RSymbol, hasher(blake2_128_concat) T::AccountId => Option<AccountRData>;
+
+		/// Any liquidity locks on some account balances.
+		pub Locks get(fn locks):
+			double_map hasher(blake2_128_concat) RSymbol, hasher(blake2_128_concat) T::AccountId => Vec<RBalanceLock>;
+
+		/// Storage version of the pallet.
+		StorageVersion get(fn storage_version) build(|_| Releases::V2_0_0): Releases;
 	}
 }
 
@@ -67,6 +147,16 @@ decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
 		fn deposit_event() = default;
 
+		fn on_runtime_upgrade() -> Weight {
+			if Self::storage_version() == Releases::V1_0_0 {
+				let weight = Self::migrate_account_data();
+				StorageVersion::put(Releases::V2_0_0);
+				weight
+			} else {
+				0
+			}
+		}
+
 		/// Transfer some liquid free balance to another account.
 		#[weight = 195_000_000]
 		pub fn transfer(
@@ -110,6 +200,30 @@ impl<T: Trait> Module<T> {
             result
         })
 	}
+
+	/// Store the locks of an account and freeze its free balance at the largest of them.
+	fn update_locks(who: &T::AccountId, symbol: RSymbol, locks: &[RBalanceLock]) {
+		let frozen = locks.iter().map(|lock| lock.amount).max().unwrap_or(0);
+		Self::mutate_account(who, symbol, |account_rdata| account_rdata.frozen = frozen);
+		if locks.is_empty() {
+			<Locks<T>>::remove(symbol, who);
+		} else {
+			<Locks<T>>::insert(symbol, who, locks);
+		}
+	}
+
+	/// Decodes account data stored before reserves and locks.
+	fn migrate_account_data() -> Weight {
+		let module: &[u8] = b"RBalances";
+		let item: &[u8] = b"Account";
+		let mut migrated: Weight = 0;
+		for (key, data) in StorageIterator::<AccountRDataV1>::new(module, item) {
+			put_storage_value(module, item, &key, AccountRData::from(data));
+			migrated += 1;
+		}
+
+		T::DbWeight::get().reads_writes(migrated + 1, migrated + 1)
+	}
 }
 
 impl<T: Trait> traits::Currency<T::AccountId> for Module<T>
@@ -130,14 +244,14 @@ impl<T: Trait> traits::Currency<T::AccountId> for Module<T>
 	// restrictions like locks and vesting balance.
 	// Is a no-op if amount to be withdrawn is zero.
 	fn ensure_can_withdraw(
-		_who: &T::AccountId,
-		_symbol: RSymbol,
+		who: &T::AccountId,
+		symbol: RSymbol,
 		amount: u128,
 		new_balance: u128,
 	) -> DispatchResult {
 		if amount.is_zero() { return Ok(()) }
-		// let min_balance = <Account<T>>::get(symbol, &who).unwrap_or_default().frozen;
-		ensure!(new_balance >= Zero::zero(), Error::<T>::LiquidityRestrictions);
+		let min_balance = <Account<T>>::get(symbol, &who).unwrap_or_default().frozen;
+		ensure!(new_balance >= min_balance, Error::<T>::LiquidityRestrictions);
 		Ok(())
 	}
 
@@ -179,6 +293,7 @@ impl<T: Trait> traits::Currency<T::AccountId> for Module<T>
 		value: u128
 	) -> DispatchResult {
 		if value.is_zero() { return Ok(()) }
+		ensure!(!Self::mint_disabled(symbol), Error::<T>::MintDisabled);
 
 		Self::try_mutate_account(who, symbol, |account_rdata| -> DispatchResult {
 			account_rdata.free = account_rdata.free.checked_add(value).ok_or(Error::<T>::Overflow)?;
@@ -223,4 +338,152 @@ impl<T: Trait> traits::Currency<T::AccountId> for Module<T>
 		Self::deposit_event(RawEvent::Burned(who.clone(), symbol.clone(), value));
 		Ok(())
 	}
-}
\ No newline at end of file
+
+	fn disable_mint(symbol: RSymbol) {
+		<MintDisabled>::insert(symbol, true);
+	}
+}
+
+impl<T: Trait> traits::ReservableCurrency<T::AccountId> for Module<T>
+{
+	/// Check if `who` can reserve `value` from their free balance.
+	///
+	/// Always `true` if value to be reserved is zero.
+	fn can_reserve(who: &T::AccountId, symbol: RSymbol, value: u128) -> bool {
+		if value.is_zero() { return true }
+		let free = <Self as traits::Currency<_>>::free_balance(who, symbol);
+		free.checked_sub(value).map_or(false, |new_balance|
+			Self::ensure_can_withdraw(who, symbol, value, new_balance).is_ok()
+		)
+	}
+
+	fn reserved_balance(who: &T::AccountId, symbol: RSymbol) -> u128 {
+		<Account<T>>::get(symbol, &who).map(|rdata| rdata.reserved).unwrap_or(0)
+	}
+
+	/// Move `value` from the free balance from `who` to their reserved balance.
+	///
+	/// Is a no-op if value to be reserved is zero.
+	fn reserve(who: &T::AccountId, symbol: RSymbol, value: u128) -> DispatchResult {
+		if value.is_zero() { return Ok(()) }
+
+		Self::try_mutate_account(who, symbol, |account_rdata| -> DispatchResult {
+			account_rdata.free = account_rdata.free.checked_sub(value).ok_or(Error::<T>::InsufficientBalance)?;
+			account_rdata.reserved = account_rdata.reserved.checked_add(value).ok_or(Error::<T>::Overflow)?;
+			Self::ensure_can_withdraw(who, symbol, value, account_rdata.free)
+		})?;
+
+		Self::deposit_event(RawEvent::Reserved(who.clone(), symbol, value));
+		Ok(())
+	}
+
+	/// Unreserve some funds, returning any amount that was unable to be unreserved.
+	///
+	/// Is a no-op if the value to be unreserved is zero.
+	fn unreserve(who: &T::AccountId, symbol: RSymbol, value: u128) -> u128 {
+		if value.is_zero() { return 0 }
+		if Self::reserved_balance(who, symbol).is_zero() { return value }
+
+		let actual = Self::mutate_account(who, symbol, |account_rdata| {
+			let actual = value.min(account_rdata.reserved);
+			account_rdata.reserved -= actual;
+			// defensive only: free + reserved never exceeds total issuance.
+			account_rdata.free = account_rdata.free.saturating_add(actual);
+			actual
+		});
+
+		Self::deposit_event(RawEvent::Unreserved(who.clone(), symbol, actual));
+		value - actual
+	}
+
+	/// Move the reserved balance of one account into the balance of another, according to `status`.
+	///
+	/// Is a no-op if:
+	/// - the value to be moved is zero; or
+	/// - the `slashed` id equal to `beneficiary` and the `status` is `Reserved`.
+	fn repatriate_reserved(
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		symbol: RSymbol,
+		value: u128,
+		status: BalanceStatus,
+	) -> Result<u128, DispatchError> {
+		if value.is_zero() { return Ok(0) }
+
+		if slashed == beneficiary {
+			return match status {
+				BalanceStatus::Free => Ok(Self::unreserve(slashed, symbol, value)),
+				BalanceStatus::Reserved => Ok(value.saturating_sub(Self::reserved_balance(slashed, symbol))),
+			};
+		}
+
+		let actual = Self::try_mutate_account(beneficiary, symbol, |to_account_rdata| -> Result<u128, DispatchError> {
+			Self::try_mutate_account(slashed, symbol, |from_account_rdata| -> Result<u128, DispatchError> {
+				let actual = value.min(from_account_rdata.reserved);
+				match status {
+					BalanceStatus::Free => to_account_rdata.free = to_account_rdata.free.checked_add(actual).ok_or(Error::<T>::Overflow)?,
+					BalanceStatus::Reserved => to_account_rdata.reserved = to_account_rdata.reserved.checked_add(actual).ok_or(Error::<T>::Overflow)?,
+				}
+				from_account_rdata.reserved -= actual;
+				Ok(actual)
+			})
+		})?;
+
+		Self::deposit_event(RawEvent::ReserveRepatriated(slashed.clone(), beneficiary.clone(), symbol, actual, status));
+		Ok(value - actual)
+	}
+}
+
+impl<T: Trait> traits::LockableCurrency<T::AccountId> for Module<T>
+{
+	/// Get the balance of an account that can be used for transfers, reservations, or any other
+	/// non-locking, non-transaction-fee activity. Will be at most `free_balance`.
+	fn usable_balance(who: &T::AccountId, symbol: RSymbol) -> u128 {
+		let rdata = <Account<T>>::get(symbol, &who).unwrap_or_default();
+		rdata.free.saturating_sub(rdata.frozen)
+	}
+
+	// Set a lock on the balance of `who`.
+	// Is a no-op if lock amount is zero.
+	fn set_lock(id: LockIdentifier, who: &T::AccountId, symbol: RSymbol, amount: u128) {
+		if amount.is_zero() { return }
+		let mut new_lock = Some(RBalanceLock { id, amount });
+		let mut locks = Self::locks(symbol, who).into_iter()
+			.filter_map(|lock| if lock.id == id { new_lock.take() } else { Some(lock) })
+			.collect::<Vec<_>>();
+		if let Some(lock) = new_lock {
+			locks.push(lock)
+		}
+		Self::update_locks(who, symbol, &locks[..]);
+		Self::deposit_event(RawEvent::LockSet(who.clone(), symbol, id, amount));
+	}
+
+	// Extend a lock on the balance of `who`.
+	// Is a no-op if lock amount is zero.
+	fn extend_lock(id: LockIdentifier, who: &T::AccountId, symbol: RSymbol, amount: u128) {
+		if amount.is_zero() { return }
+		let mut new_lock = Some(RBalanceLock { id, amount });
+		let mut locks = Self::locks(symbol, who).into_iter()
+			.filter_map(|lock| if lock.id == id {
+				new_lock.take().map(|nl| RBalanceLock { id: lock.id, amount: lock.amount.max(nl.amount) })
+			} else {
+				Some(lock)
+			})
+			.collect::<Vec<_>>();
+		if let Some(lock) = new_lock {
+			locks.push(lock)
+		}
+		let amount = locks.iter().find(|lock| lock.id == id).map(|lock| lock.amount).unwrap_or(amount);
+		Self::update_locks(who, symbol, &locks[..]);
+		Self::deposit_event(RawEvent::LockSet(who.clone(), symbol, id, amount));
+	}
+
+	fn remove_lock(id: LockIdentifier, who: &T::AccountId, symbol: RSymbol) {
+		let mut locks = Self::locks(symbol, who);
+		let len = locks.len();
+		locks.retain(|lock| lock.id != id);
+		if locks.len() == len { return }
+		Self::update_locks(who, symbol, &locks[..]);
+		Self::deposit_event(RawEvent::LockRemoved(who.clone(), symbol, id));
+	}
+}
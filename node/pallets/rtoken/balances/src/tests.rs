@@ -0,0 +1,154 @@
+use super::mock::*;
+use super::*;
+use crate::traits::{Currency, LockableCurrency, ReservableCurrency};
+use frame_support::{assert_noop, assert_ok, traits::OnRuntimeUpgrade, Hashable};
+
+const SYMBOL: RSymbol = RSymbol::RDOT;
+const ID_1: LockIdentifier = *b"1       ";
+const ID_2: LockIdentifier = *b"2       ";
+const ID_3: LockIdentifier = *b"3       ";
+
+fn rdata(free: u128, reserved: u128, frozen: u128) -> Option<AccountRData> {
+	Some(AccountRData { free, reserved, frozen })
+}
+
+fn lock(id: LockIdentifier, amount: u128) -> RBalanceLock {
+	RBalanceLock { id, amount }
+}
+
+#[test]
+fn reserve_and_unreserve_should_work() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RBalances::mint(&ALICE, SYMBOL, 100));
+		assert_ok!(RBalances::reserve(&ALICE, SYMBOL, 30));
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(70, 30, 0));
+		assert_eq!(RBalances::reserved_balance(&ALICE, SYMBOL), 30);
+		assert_noop!(RBalances::reserve(&ALICE, SYMBOL, 80), Error::<Test>::InsufficientBalance);
+
+		// reserving can't take free balance below the locks
+		RBalances::set_lock(ID_1, &ALICE, SYMBOL, 60);
+		assert!(RBalances::can_reserve(&ALICE, SYMBOL, 10));
+		assert!(!RBalances::can_reserve(&ALICE, SYMBOL, 20));
+		assert_noop!(RBalances::reserve(&ALICE, SYMBOL, 20), Error::<Test>::LiquidityRestrictions);
+
+		assert_eq!(RBalances::unreserve(&ALICE, SYMBOL, 40), 10);
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(100, 0, 60));
+		assert_eq!(RBalances::unreserve(&ALICE, SYMBOL, 5), 5);
+		assert_eq!(RBalances::total_issuance(SYMBOL), 100);
+	});
+}
+
+#[test]
+fn repatriate_reserved_should_move_to_free_or_reserved() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RBalances::mint(&ALICE, SYMBOL, 100));
+		assert_ok!(RBalances::reserve(&ALICE, SYMBOL, 50));
+
+		assert_eq!(RBalances::repatriate_reserved(&ALICE, &BOB, SYMBOL, 20, BalanceStatus::Free), Ok(0));
+		assert_eq!(RBalances::account(SYMBOL, BOB), rdata(20, 0, 0));
+		assert_eq!(RBalances::repatriate_reserved(&ALICE, &BOB, SYMBOL, 20, BalanceStatus::Reserved), Ok(0));
+		assert_eq!(RBalances::account(SYMBOL, BOB), rdata(20, 20, 0));
+		// only what is left in the reserve is moved
+		assert_eq!(RBalances::repatriate_reserved(&ALICE, &BOB, SYMBOL, 30, BalanceStatus::Free), Ok(20));
+		assert_eq!(RBalances::account(SYMBOL, BOB), rdata(30, 20, 0));
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(50, 0, 0));
+
+		// to itself, free unreserves and reserved leaves the reserve as is
+		assert_ok!(RBalances::reserve(&ALICE, SYMBOL, 10));
+		assert_eq!(RBalances::repatriate_reserved(&ALICE, &ALICE, SYMBOL, 15, BalanceStatus::Reserved), Ok(5));
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(40, 10, 0));
+		assert_eq!(RBalances::repatriate_reserved(&ALICE, &ALICE, SYMBOL, 15, BalanceStatus::Free), Ok(5));
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(50, 0, 0));
+		assert_eq!(RBalances::total_issuance(SYMBOL), 100);
+	});
+}
+
+#[test]
+fn overlapping_locks_should_freeze_the_largest() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RBalances::mint(&ALICE, SYMBOL, 100));
+		RBalances::set_lock(ID_1, &ALICE, SYMBOL, 30);
+		assert_eq!(RBalances::usable_balance(&ALICE, SYMBOL), 70);
+		RBalances::set_lock(ID_2, &ALICE, SYMBOL, 50);
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(100, 0, 50));
+
+		// extending never lowers a lock, setting replaces it
+		RBalances::extend_lock(ID_1, &ALICE, SYMBOL, 40);
+		RBalances::extend_lock(ID_1, &ALICE, SYMBOL, 20);
+		assert_eq!(RBalances::locks(SYMBOL, ALICE), vec![lock(ID_1, 40), lock(ID_2, 50)]);
+		RBalances::set_lock(ID_2, &ALICE, SYMBOL, 10);
+		assert_eq!(RBalances::locks(SYMBOL, ALICE), vec![lock(ID_1, 40), lock(ID_2, 10)]);
+		assert_eq!(RBalances::usable_balance(&ALICE, SYMBOL), 60);
+
+		RBalances::remove_lock(ID_1, &ALICE, SYMBOL);
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(100, 0, 10));
+		RBalances::remove_lock(ID_2, &ALICE, SYMBOL);
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(100, 0, 0));
+		assert!(!<Locks<Test>>::contains_key(SYMBOL, ALICE));
+
+		RBalances::set_lock(ID_3, &ALICE, SYMBOL, 0);
+		RBalances::extend_lock(ID_3, &ALICE, SYMBOL, 25);
+		assert_eq!(RBalances::locks(SYMBOL, ALICE), vec![lock(ID_3, 25)]);
+		assert_eq!(RBalances::usable_balance(&ALICE, SYMBOL), 75);
+	});
+}
+
+#[test]
+fn transfer_and_burn_should_respect_frozen() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RBalances::mint(&ALICE, SYMBOL, 100));
+		RBalances::set_lock(ID_1, &ALICE, SYMBOL, 60);
+
+		assert_ok!(<RBalances as Currency<u64>>::transfer(&ALICE, &BOB, SYMBOL, 40));
+		assert_noop!(
+			<RBalances as Currency<u64>>::transfer(&ALICE, &BOB, SYMBOL, 1),
+			Error::<Test>::LiquidityRestrictions
+		);
+		assert_noop!(
+			RBalances::transfer(Origin::signed(ALICE), BOB, SYMBOL, 1),
+			Error::<Test>::LiquidityRestrictions
+		);
+		assert_noop!(RBalances::burn(&ALICE, SYMBOL, 1), Error::<Test>::LiquidityRestrictions);
+		assert_noop!(RBalances::burn(&ALICE, SYMBOL, 61), Error::<Test>::InsufficientBalance);
+
+		RBalances::remove_lock(ID_1, &ALICE, SYMBOL);
+		assert_ok!(RBalances::burn(&ALICE, SYMBOL, 60));
+		assert_eq!(RBalances::free_balance(&ALICE, SYMBOL), 0);
+		assert_eq!(RBalances::free_balance(&BOB, SYMBOL), 40);
+		assert_eq!(RBalances::total_issuance(SYMBOL), 40);
+	});
+}
+
+#[test]
+fn migrate_account_data_should_decode_v1() {
+	new_test_ext().execute_with(|| {
+		for (who, free) in vec![(ALICE, 42u128), (BOB, 7)] {
+			let key = [SYMBOL.blake2_128_concat(), who.blake2_128_concat()].concat();
+			put_storage_value(b"RBalances", b"Account", &key, AccountRDataV1 { free });
+		}
+		// v1 data doesn't decode as the current account data
+		assert_eq!(RBalances::account(SYMBOL, ALICE), None);
+
+		<RBalances as OnRuntimeUpgrade>::on_runtime_upgrade();
+		assert_eq!(RBalances::storage_version(), Releases::V2_0_0);
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(42, 0, 0));
+		assert_eq!(RBalances::account(SYMBOL, BOB), rdata(7, 0, 0));
+		assert_ok!(RBalances::reserve(&ALICE, SYMBOL, 2));
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(40, 2, 0));
+	});
+}
+
+#[test]
+fn genesis_built_chain_should_keep_account_data() {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	GenesisConfig::default().assimilate_storage::<Test>(&mut t).unwrap();
+	sp_io::TestExternalities::from(t).execute_with(|| {
+		assert_eq!(RBalances::storage_version(), Releases::V2_0_0);
+		assert_ok!(RBalances::mint(&ALICE, SYMBOL, 100));
+		assert_ok!(RBalances::reserve(&ALICE, SYMBOL, 30));
+		RBalances::set_lock(ID_1, &ALICE, SYMBOL, 50);
+
+		assert_eq!(<RBalances as OnRuntimeUpgrade>::on_runtime_upgrade(), 0);
+		assert_eq!(RBalances::account(SYMBOL, ALICE), rdata(70, 30, 50));
+	});
+}
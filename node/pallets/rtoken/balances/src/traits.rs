@@ -1,4 +1,5 @@
-use sp_runtime::{DispatchResult};
+use sp_runtime::{DispatchResult, DispatchError};
+use frame_support::traits::{BalanceStatus, LockIdentifier};
 use node_primitives::RSymbol;
 
 pub trait Currency<AccountId> {
@@ -49,4 +50,70 @@ pub trait Currency<AccountId> {
 		symbol: RSymbol,
 		value: u128,
 	) -> DispatchResult;
-}
\ No newline at end of file
+
+	/// Disable minting of `symbol` for good, `mint` fails afterwards
+	fn disable_mint(symbol: RSymbol);
+}
+
+/// A currency where funds can be reserved from the user.
+pub trait ReservableCurrency<AccountId>: Currency<AccountId> {
+	/// Same result as `reserve(who, symbol, value)` (but without the side-effects) assuming there
+	/// are no balance changes in the meantime.
+	fn can_reserve(who: &AccountId, symbol: RSymbol, value: u128) -> bool;
+
+	/// The amount of the balance of a given account that is externally reserved; this can still get
+	/// slashed, but gets slashed last of all.
+	fn reserved_balance(who: &AccountId, symbol: RSymbol) -> u128;
+
+	/// Moves `value` from balance to reserved balance.
+	///
+	/// If the free balance is lower than `value`, or locks forbid withdrawing it, then no funds
+	/// will be moved and an `Err` will be returned to notify of this.
+	fn reserve(who: &AccountId, symbol: RSymbol, value: u128) -> DispatchResult;
+
+	/// Moves up to `value` from reserved balance to free balance. This function cannot fail.
+	///
+	/// As much funds up to `value` will be moved as possible. If the reserve balance of `who`
+	/// is less than `value`, then the remaining amount will be returned.
+	fn unreserve(who: &AccountId, symbol: RSymbol, value: u128) -> u128;
+
+	/// Moves up to `value` from reserved balance of account `slashed` to balance of account
+	/// `beneficiary`. `beneficiary` must exist for this to succeed. If it does not, `Err` will be
+	/// returned. Funds will be placed in either the `free` balance or the `reserved` balance,
+	/// depending on the `status`.
+	///
+	/// As much funds up to `value` will be deducted as possible. If this is less than `value`,
+	/// then `Ok(non_zero)` will be returned.
+	fn repatriate_reserved(
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		symbol: RSymbol,
+		value: u128,
+		status: BalanceStatus,
+	) -> Result<u128, DispatchError>;
+}
+
+/// A currency whose accounts can have liquidity restrictions.
+pub trait LockableCurrency<AccountId>: Currency<AccountId> {
+	/// The part of the free balance of `who` that is not frozen by locks.
+	fn usable_balance(who: &AccountId, symbol: RSymbol) -> u128;
+
+	/// Create a new balance lock on account `who`.
+	///
+	/// Pushes the lock to the `Locks` vec in storage. Note that you can lock more funds than a
+	/// user has.
+	///
+	/// If the lock `id` already exists, this will update it.
+	fn set_lock(id: LockIdentifier, who: &AccountId, symbol: RSymbol, amount: u128);
+
+	/// Changes a balance lock (selected by `id`) so that it becomes less liquid in all
+	/// parameters or creates a new one if it does not exist.
+	///
+	/// Calling `extend_lock` on an existing lock `id` differs from `set_lock` in that it
+	/// applies the most severe constraints of the two, while `set_lock` replaces the lock
+	/// with the new parameters. As in, `extend_lock` will set the maximum `amount`.
+	fn extend_lock(id: LockIdentifier, who: &AccountId, symbol: RSymbol, amount: u128);
+
+	/// Remove an existing lock.
+	fn remove_lock(id: LockIdentifier, who: &AccountId, symbol: RSymbol);
+}
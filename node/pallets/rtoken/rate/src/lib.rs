@@ -29,6 +29,9 @@ decl_storage! {
         
         /// current rate
         pub Rate get(fn rate): map hasher(blake2_128_concat) RSymbol => Option<RateType>;
+
+        /// block the current rate was last set at
+        pub RateUpdatedAt get(fn rate_updated_at): map hasher(blake2_128_concat) RSymbol => Option<T::BlockNumber>;
     }
 }
 
@@ -40,6 +43,7 @@ decl_module! {
 
 impl<T: Trait> Module<T> {
     pub fn set_rate(symbol: RSymbol, total: u128, rtotal: u128) -> RateType {
+        <RateUpdatedAt<T>>::insert(symbol, system::Module::<T>::block_number());
         if total == 0 || rtotal == 0 {
             <Rate>::insert(symbol, RATEBASE);
             Self::deposit_event(Event::RateSet(symbol, RATEBASE));
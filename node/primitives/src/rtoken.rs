@@ -11,8 +11,11 @@
 
 use codec::{Decode, Encode};
 use sp_runtime::RuntimeDebug;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 
 /// Rtoken Identifier
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[derive(Encode, Decode, Copy, Clone, Eq, PartialEq, RuntimeDebug)]
 pub enum RSymbol {
 	/// rFIS
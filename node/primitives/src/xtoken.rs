@@ -12,8 +12,11 @@
 
 use codec::{Encode, Decode};
 use sp_runtime::RuntimeDebug;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 
 /// Xtoken Identifier
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[derive(Encode, Decode, Copy, Clone, Eq, PartialEq, RuntimeDebug)]
 pub enum XSymbol {
     /// WRA
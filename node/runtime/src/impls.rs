@@ -11,10 +11,12 @@
 
 //! Some configurable implementations as associated type for the stafi runtime.
 
+use codec::Encode;
+use sp_std::prelude::*;
 use node_primitives::Balance;
 use sp_runtime::traits::Convert;
 use frame_support::traits::{OnUnbalanced, Currency};
-use crate::{Balances, Authorship, NegativeImbalance};
+use crate::{Balances, Authorship, NegativeImbalance, Call};
 
 pub struct Author;
 impl OnUnbalanced<NegativeImbalance> for Author {
@@ -39,6 +41,29 @@ impl Convert<u128, Balance> for CurrencyToVoteHandler {
 	fn convert(x: u128) -> Balance { x * Self::factor() }
 }
 
+/// Exposes the resource and amount of bridge swap proposals to the bridge transfer limits.
+pub struct BridgeProposalInspector;
+
+impl bridge_common::ProposalInspector<Call> for BridgeProposalInspector {
+	fn transfer_of(proposal: &Call) -> Option<(bridge_common::ResourceId, u128)> {
+		match proposal {
+			Call::BridgeSwap(bridge_swap::Call::transfer_native_back(_, amount, rid)) => Some((*rid, *amount)),
+			Call::BridgeSwap(bridge_swap::Call::transfer_rtoken_back(_, amount, rid)) => Some((*rid, *amount)),
+			Call::BridgeSwap(bridge_swap::Call::transfer_xtoken_back(_, amount, rid)) => Some((*rid, *amount)),
+			_ => None,
+		}
+	}
+
+	fn recipient_of(proposal: &Call) -> Option<Vec<u8>> {
+		match proposal {
+			Call::BridgeSwap(bridge_swap::Call::transfer_native_back(recipient, _, _)) |
+			Call::BridgeSwap(bridge_swap::Call::transfer_rtoken_back(recipient, _, _)) |
+			Call::BridgeSwap(bridge_swap::Call::transfer_xtoken_back(recipient, _, _)) => Some(recipient.encode()),
+			_ => None,
+		}
+	}
+}
+
 #[cfg(test)]
 mod multiplier_tests {
 	use super::*;
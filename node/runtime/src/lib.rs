@@ -35,7 +35,7 @@ use sp_core::{
 };
 use sp_io::hashing::blake2_128;
 pub use node_primitives::{AccountId, Signature};
-use node_primitives::{AccountIndex, Balance, BlockNumber, Hash, Index, Moment, ChainId};
+use node_primitives::{AccountIndex, Balance, BlockNumber, Hash, Index, Moment, ChainId, RSymbol, XSymbol};
 use sp_api::impl_runtime_apis;
 use sp_runtime::{
 	Permill, Perbill, Perquintill, Percent, ApplyExtrinsicResult,
@@ -71,7 +71,7 @@ pub use pallet_staking::StakerStatus;
 
 /// Implementations of some helper traits passed into runtime modules as associated types.
 pub mod impls;
-use impls::{CurrencyToVoteHandler, Author};
+use impls::{CurrencyToVoteHandler, Author, BridgeProposalInspector};
 
 /// Constant values used within the runtime.
 pub mod constants;
@@ -102,7 +102,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// and set impl_version to 0. If only runtime
 	// implementation changes and behavior does not, then leave spec_version as
 	// is and increment impl_version.
-	spec_version: 23,
+	spec_version: 24,
 	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -851,6 +851,10 @@ impl pallet_vesting::Trait for Runtime {
 parameter_types! {
 	pub const ChainIdentity: ChainId = 1;
 	pub const ProposalLifetime: BlockNumber = 201600;
+	pub const BridgeLimitPeriod: BlockNumber = 1 * DAYS;
+	pub const BridgeHoldDelay: BlockNumber = 1 * HOURS;
+	pub const DepositRecordsRetained: bridge_common::DepositNonce = 100_000;
+	pub const CompletedProposalRetention: BlockNumber = 7 * DAYS;
 }
 
 impl bridge_relayers::Trait for Runtime {
@@ -863,6 +867,12 @@ impl bridge_common::Trait for Runtime {
 	type Proposal = Call;
 	type ChainIdentity = ChainIdentity;
 	type ProposalLifetime = ProposalLifetime;
+	type LimitPeriod = BridgeLimitPeriod;
+	type DefaultHoldDelay = BridgeHoldDelay;
+	type ProposalInspector = BridgeProposalInspector;
+	type DepositRecordsRetained = DepositRecordsRetained;
+	type CompletedProposalRetention = CompletedProposalRetention;
+	type RTokenCurrency = RBalances;
 }
 
 parameter_types! {
@@ -902,6 +912,7 @@ impl rtoken_series::Trait for Runtime {
 impl xclaim::Trait for Runtime {
 	type Event = Event;
 	type XCurrency = XBalances;
+	type TreasuryModuleId = TreasuryModuleId;
 }
 
 impl rclaim::Trait for Runtime {
@@ -923,6 +934,7 @@ impl rdexn_swap::Trait for Runtime {
 	type Event = Event;
 	type Currency = Balances;
 	type RCurrency = RBalances;
+	type PriceOracle = RDexSwap;
 }
 
 impl rdex_swap::Trait for Runtime {
@@ -932,10 +944,19 @@ impl rdex_swap::Trait for Runtime {
 	type LpCurrency = LpBalances;
 }
 
+parameter_types! {
+	pub const GuardPriceWindow: BlockNumber = 1 * HOURS;
+}
+
 impl rdex_mining::Trait for Runtime {
 	type Event = Event;
 	type Currency = Balances;
 	type LpCurrency = LpBalances;
+	type RCurrency = RBalances;
+	type XCurrency = XBalances;
+	type LiquidityProvider = RDexSwap;
+	type PriceOracle = RDexSwap;
+	type GuardPriceWindow = GuardPriceWindow;
 }
 
 construct_runtime!(
@@ -975,21 +996,21 @@ construct_runtime!(
 		Proxy: pallet_proxy::{Module, Call, Storage, Event<T>},
 		Multisig: pallet_multisig::{Module, Call, Storage, Event<T>},
 		XBalances: xtoken_balances::{Module, Call, Storage, Event<T>},
-		RBalances: rtoken_balances::{Module, Call, Storage, Event<T>},
+		RBalances: rtoken_balances::{Module, Call, Storage, Config, Event<T>},
 		RTokenRate: rtoken_rate::{Module, Call, Storage, Event},
 		RFis: rfis::{Module, Call, Storage, Event<T>, ValidateUnsigned},
 		BridgeRelayers: bridge_relayers::{Module, Call, Storage, Event<T>},
-		BridgeCommon: bridge_common::{Module, Call, Storage, Event<T>},
-		BridgeSwap: bridge_swap::{Module, Call},
+		BridgeCommon: bridge_common::{Module, Call, Storage, Config, Event<T>},
+		BridgeSwap: bridge_swap::{Module, Call, Storage, Config},
 		RTokenRelayers: rtoken_relayers::{Module, Call, Storage, Event<T>},
 		RTokenVotes: rtoken_votes::{Module, Call, Storage, Event<T>},
 		RTokenLedger: rtoken_ledger::{Module, Call, Storage, Event<T>},
 		RTokenSeries: rtoken_series::{Module, Call, Storage, Event<T>},
 		XClaim: xclaim::{Module, Call, Storage, Event<T>},
-		RClaim: rclaim::{Module, Call, Storage, Event<T>},
+		RClaim: rclaim::{Module, Call, Storage, Config, Event<T>},
 		RDexnPayers: rdexn_payers::{Module, Call, Storage, Event<T>},
-		RDexnSignatures: rdexn_signatures::{Module, Call, Storage, Event<T>},
-		RDexnSwap: rdexn_swap::{Module, Call, Storage, Event<T>},
+		RDexnSignatures: rdexn_signatures::{Module, Call, Storage, Config, Event<T>},
+		RDexnSwap: rdexn_swap::{Module, Call, Storage, Config, Event<T>},
 		RDexSwap: rdex_swap::{Module, Call, Storage, Event<T>},
 		LpBalances: rdex_balances::{Module, Call, Storage, Event<T>},
 		RDexMining: rdex_mining::{Module, Call, Storage, Event<T>},
@@ -1185,6 +1206,36 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl bridge_common_rpc_runtime_api::BridgeCommonApi<Block, AccountId, BlockNumber> for Runtime {
+		fn deposit_records(
+			dest_id: ChainId,
+			start: bridge_common::DepositNonce,
+			end: bridge_common::DepositNonce,
+		) -> Vec<(bridge_common::DepositNonce, bridge_common::DepositRecord<AccountId, BlockNumber>)> {
+			BridgeCommon::deposit_records_in_range(dest_id, start, end)
+		}
+
+		fn open_proposals(src_id: ChainId) -> Vec<bridge_common::ProposalInfo<AccountId, BlockNumber>> {
+			BridgeCommon::open_proposals(src_id)
+		}
+
+		fn reserves() -> Vec<bridge_common::ReserveStatus> {
+			BridgeSwap::reserve_statuses()
+		}
+	}
+
+	impl xclaim_rpc_runtime_api::XClaimApi<Block, AccountId> for Runtime {
+		fn claim_status(who: AccountId, symbol: XSymbol) -> xclaim::ClaimStatus {
+			XClaim::claim_status(&who, symbol)
+		}
+	}
+
+	impl rdex_swap_rpc_runtime_api::RDexSwapApi<Block> for Runtime {
+		fn twap(symbol: RSymbol, window: u32) -> Option<rdex_swap::TwapPrice> {
+			RDexSwap::twap(symbol, window)
+		}
+	}
+
 	impl sp_session::SessionKeys<Block> for Runtime {
 		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
 			SessionKeys::generate(seed)
@@ -104,5 +104,11 @@ pub fn config_endowed(
 		pallet_sudo: Some(Default::default()),
 		pallet_treasury: Some(Default::default()),
 		pallet_vesting: Some(Default::default()),
+		rclaim: Some(Default::default()),
+		rdexn_swap: Some(Default::default()),
+		rtoken_balances: Some(Default::default()),
+		rdexn_signatures: Some(Default::default()),
+		bridge_swap: Some(Default::default()),
+		bridge_common: Some(Default::default()),
 	}
 }